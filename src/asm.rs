@@ -0,0 +1,637 @@
+pub mod lexer;
+
+use core::fmt;
+use std::{
+  collections::{btree_map::Entry, BTreeMap, HashMap},
+  rc::Rc,
+};
+
+use crate::{
+  class::{Class, Field},
+  function::{builder::FunctionBuilder, Function, Handler},
+  module::Module,
+  opcode::{self, Operand},
+  pool_entry::PoolEntry,
+};
+
+use lexer::Token;
+
+/// Assemble a textual module, see `docs/assembler.md`.
+pub fn assemble(source: &str) -> Result<Module> {
+  let mut assembler = Assembler::default();
+  for (index, text) in source.lines().enumerate() {
+    let line = index + 1;
+    assembler.line = line;
+    let tokens = lexer::tokenize(text).map_err(|message| Error { line, message })?;
+    assembler.line(&tokens).map_err(|message| Error { line, message })?;
+  }
+  assembler.finish()
+}
+
+/// An assembler error.
+#[derive(Debug)]
+pub struct Error {
+  /// The line where the error happened.
+  pub line: usize,
+  /// The error description.
+  pub message: String,
+}
+
+impl fmt::Display for Error {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "line {}: {}", self.line, self.message)
+  }
+}
+
+impl std::error::Error for Error {}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+type Line<T> = std::result::Result<T, String>;
+
+#[derive(Default)]
+struct Pool {
+  entries: Vec<PoolEntry>,
+  names: HashMap<String, usize>,
+}
+
+impl Pool {
+  fn define(&mut self, name: String, entry: PoolEntry) -> Line<()> {
+    if self.names.contains_key(&name) {
+      return Err(format!("Constant '{name}' already defined."));
+    }
+    self.names.insert(name, self.entries.len());
+    self.entries.push(entry);
+    Ok(())
+  }
+
+  fn intern(&mut self, entry: PoolEntry) -> usize {
+    match self.entries.iter().position(|e| *e == entry) {
+      Some(index) => index,
+      None => {
+        self.entries.push(entry);
+        self.entries.len() - 1
+      }
+    }
+  }
+}
+
+struct ClassState {
+  name: Rc<str>,
+  pool: Pool,
+  fields: BTreeMap<Rc<str>, Field>,
+  methods: BTreeMap<Rc<str>, Function>,
+}
+
+struct FunctionState {
+  name: Rc<str>,
+  arguments: u8,
+  locals: u16,
+  code: Vec<u8>,
+  labels: HashMap<String, u16>,
+  fixups: Vec<Fixup>,
+  handlers: Vec<PendingHandler>,
+}
+
+enum Target {
+  Address(u16),
+  Label(String),
+}
+
+struct PendingHandler {
+  start: Target,
+  end: Target,
+  handler: Target,
+  class: Option<u16>,
+  line: usize,
+}
+
+struct Fixup {
+  offset: usize,
+  label: String,
+  line: usize,
+}
+
+#[derive(Default)]
+struct Assembler {
+  line: usize,
+  name: Option<Rc<str>>,
+  pool: Pool,
+  functions: BTreeMap<Rc<str>, Function>,
+  classes: BTreeMap<Rc<str>, Class>,
+  class: Option<ClassState>,
+  function: Option<FunctionState>,
+}
+
+impl Assembler {
+  fn line(&mut self, tokens: &[Token]) -> Line<()> {
+    match tokens {
+      [] => Ok(()),
+      [Token::Directive(directive), rest @ ..] => self.directive(directive, rest),
+      [Token::Label(label)] => self.label(label),
+      [Token::Address(address), rest @ ..] => {
+        self.address(*address)?;
+        self.line(rest)
+      }
+      [Token::Ident(mnemonic), rest @ ..] => self.instruction(mnemonic, rest),
+      [token, ..] => Err(format!("Unexpected {token:?}.")),
+    }
+  }
+
+  fn finish(self) -> Result<Module> {
+    let line = self.line;
+    if self.function.is_some() || self.class.is_some() {
+      return Err(Error { line, message: "Missing '.end'.".to_string() });
+    }
+    let Some(name) = self.name else {
+      return Err(Error { line, message: "Missing '.module'.".to_string() });
+    };
+    Ok(Module {
+      name,
+      constants: self.pool.entries,
+      functions: self.functions,
+      classes: self.classes,
+    })
+  }
+
+  fn pool(&mut self) -> &mut Pool {
+    match &mut self.class {
+      Some(class) => &mut class.pool,
+      None => &mut self.pool,
+    }
+  }
+
+  fn directive(&mut self, directive: &str, args: &[Token]) -> Line<()> {
+    if self.function.is_some() && !matches!(directive, "end" | "catch") {
+      return Err(format!("Unexpected '.{directive}' inside function."));
+    }
+    match (directive, args) {
+      ("module", [Token::Ident(name)]) => {
+        if self.name.is_some() {
+          return Err("Module name already defined.".to_string());
+        }
+        self.name = Some(Rc::from(name.as_str()));
+        Ok(())
+      }
+      ("const", [Token::Ident(name), value @ ..]) => {
+        let entry = constant(value)?;
+        self.pool().define(name.clone(), entry)
+      }
+      ("function" | "method", [Token::Ident(name), args @ ..]) => {
+        match (directive, &self.class) {
+          ("function", Some(_)) => return Err("Use '.method' inside a class.".to_string()),
+          ("method", None) => return Err("Use '.function' outside a class.".to_string()),
+          _ => (),
+        }
+        let (arguments, locals) = signature(args, directive == "method")?;
+        self.function = Some(FunctionState {
+          name: Rc::from(name.as_str()),
+          arguments,
+          locals,
+          code: Vec::new(),
+          labels: HashMap::new(),
+          fixups: Vec::new(),
+          handlers: Vec::new(),
+        });
+        Ok(())
+      }
+      ("class", [Token::Ident(name)]) => {
+        if self.class.is_some() {
+          return Err("Nested classes are not supported.".to_string());
+        }
+        self.class = Some(ClassState {
+          name: Rc::from(name.as_str()),
+          pool: Pool::default(),
+          fields: BTreeMap::new(),
+          methods: BTreeMap::new(),
+        });
+        Ok(())
+      }
+      ("field", [Token::Ident(name)]) => {
+        let Some(class) = &mut self.class else {
+          return Err("Field outside of class.".to_string());
+        };
+        let offset = u8::try_from(class.fields.len())
+          .map_err(|_| format!("Class '{}' has more than 256 fields.", class.name))?;
+        match class.fields.entry(Rc::from(name.as_str())) {
+          Entry::Vacant(v) => {
+            v.insert(Field { vis: Field::PUBLIC, offset });
+            Ok(())
+          }
+          Entry::Occupied(_) => Err(format!("Field '{name}' already defined.")),
+        }
+      }
+      ("catch", [start, end, handler, class @ ..]) => self.catch(start, end, handler, class),
+      ("end", []) => self.end(),
+      _ => Err(format!("Invalid '.{directive}' directive.")),
+    }
+  }
+
+  fn end(&mut self) -> Line<()> {
+    if let Some(function) = self.function.take() {
+      let function = self.end_function(function)?;
+      let functions = match &mut self.class {
+        Some(class) => &mut class.methods,
+        None => &mut self.functions,
+      };
+      if functions.contains_key(&function.name) {
+        return Err(format!("Function '{}' already defined.", function.name));
+      }
+      functions.insert(function.name.clone(), function);
+      Ok(())
+    } else if let Some(class) = self.class.take() {
+      if self.classes.contains_key(&class.name) {
+        return Err(format!("Class '{}' already defined.", class.name));
+      }
+      let class = Class {
+        name: class.name,
+        constants: class.pool.entries,
+        fields: class.fields,
+        methods: class.methods,
+      };
+      self.classes.insert(class.name.clone(), class);
+      Ok(())
+    } else {
+      Err("Unexpected '.end'.".to_string())
+    }
+  }
+
+  fn catch(&mut self, start: &Token, end: &Token, handler: &Token, class: &[Token]) -> Line<()> {
+    if self.function.is_none() {
+      return Err("Handler outside of function.".to_string());
+    }
+    let class = match class {
+      [] => None,
+      [class] => Some(self.operand(Operand::Class, class, 0)? as u16),
+      _ => return Err("Expected '.catch start end handler [class]'.".to_string()),
+    };
+    let handler = PendingHandler {
+      start: target(start)?,
+      end: target(end)?,
+      handler: target(handler)?,
+      class,
+      line: self.line,
+    };
+    self.function.as_mut().unwrap().handlers.push(handler);
+    Ok(())
+  }
+
+  fn end_function(&mut self, mut function: FunctionState) -> Line<Function> {
+    let resolve = |target: &Target, line: usize| match target {
+      Target::Address(address) => Ok(*address),
+      Target::Label(label) => function
+        .labels
+        .get(label)
+        .copied()
+        .ok_or(format!("Undefined label '{label}' (used at line {line}).")),
+    };
+
+    for fixup in function.fixups.iter() {
+      let target = resolve(&Target::Label(fixup.label.clone()), fixup.line)?;
+      function.code[fixup.offset..fixup.offset + 2].copy_from_slice(&target.to_be_bytes());
+    }
+    if function.code.len() > u16::MAX as usize {
+      return Err(format!("Function '{}' is too long.", function.name));
+    }
+
+    let mut builder = FunctionBuilder::new()
+      .with_name(&function.name)
+      .with_arguments(function.arguments)
+      .with_locals(function.locals)
+      .with_bytecode(&function.code);
+    for handler in function.handlers.iter() {
+      builder = builder.with_handler(Handler {
+        start: resolve(&handler.start, handler.line)?,
+        end: resolve(&handler.end, handler.line)?,
+        handler: resolve(&handler.handler, handler.line)?,
+        class: handler.class,
+      });
+    }
+    Ok(builder.build())
+  }
+
+  fn label(&mut self, label: &str) -> Line<()> {
+    let Some(function) = &mut self.function else {
+      return Err(format!("Label '{label}' outside of function."));
+    };
+    let address = address(function.code.len())?;
+    if function.labels.insert(label.to_string(), address).is_some() {
+      return Err(format!("Label '{label}' already defined."));
+    }
+    Ok(())
+  }
+
+  fn address(&mut self, address: u32) -> Line<()> {
+    let Some(function) = &self.function else {
+      return Err(format!("Address '@{address}' outside of function."));
+    };
+    if function.code.len() != address as usize {
+      return Err(format!("Expected address '@{}', found '@{address}'.", function.code.len()));
+    }
+    Ok(())
+  }
+
+  fn instruction(&mut self, mnemonic: &str, args: &[Token]) -> Line<()> {
+    if self.function.is_none() {
+      return Err(format!("Instruction '{mnemonic}' outside of function."));
+    }
+    let opcode = opcode::from_str(mnemonic).ok_or(format!("Unknown instruction '{mnemonic}'."))?;
+    let operands = opcode::operands(opcode);
+
+    let split;
+    let args = match (operands, args) {
+      ([Operand::Module, Operand::Function, ..], [Token::Ident(path), rest @ ..])
+        if args.len() + 1 == operands.len() =>
+      {
+        let (module, function) =
+          path.rsplit_once(':').ok_or(format!("Expected 'module:function', found '{path}'."))?;
+        split = [Token::Ident(module.to_string()), Token::Ident(function.to_string())]
+          .into_iter()
+          .chain(rest.iter().cloned())
+          .collect::<Vec<_>>();
+        &split[..]
+      }
+      _ => args,
+    };
+    let arity = |expected: usize| match args.len() {
+      found if found == expected => Ok(()),
+      found => Err(format!("'{mnemonic}' expects {expected} operand(s), found {found}.")),
+    };
+    let repeated = opcode::repeated_operands(opcode);
+    if repeated.is_empty() || args.len() < operands.len() {
+      arity(operands.len())?;
+    }
+
+    let mut bytes = vec![opcode];
+    let mut values = Vec::with_capacity(operands.len());
+    for (operand, arg) in operands.iter().zip(args) {
+      let value = self.operand(*operand, arg, bytes.len())?;
+      encode(&mut bytes, *operand, value);
+      values.push(value);
+    }
+    // The fixed operands of a switch give the number of repeated ones.
+    arity(operands.len() + repeated.len() * opcode::repeats(opcode, &values))?;
+    for (operand, arg) in repeated.iter().cycle().zip(&args[operands.len()..]) {
+      let value = self.operand(*operand, arg, bytes.len())?;
+      encode(&mut bytes, *operand, value);
+    }
+
+    let function = self.function.as_mut().unwrap();
+    function.code.extend(bytes);
+    Ok(())
+  }
+
+  fn operand(&mut self, operand: Operand, arg: &Token, offset: usize) -> Line<usize> {
+    let max = (1 << (8 * operand.width())) - 1;
+    let value = match (operand, arg) {
+      (Operand::Byte | Operand::Short | Operand::Local, Token::Integer(i)) => {
+        if *i < 0 || *i > max as i64 {
+          return Err(format!("Operand {i} out of range."));
+        }
+        *i as usize
+      }
+      (Operand::Int, Token::Integer(i)) => {
+        i32::try_from(*i).map_err(|_| format!("Operand {i} out of range."))? as u32 as usize
+      }
+      (Operand::Label, Token::Address(address)) => *address as usize,
+      (Operand::Label, Token::Ident(label)) => {
+        let line = self.line;
+        let function = self.function.as_mut().unwrap();
+        let offset = function.code.len() + offset;
+        function.fixups.push(Fixup { offset, label: label.clone(), line });
+        0
+      }
+      (_, Token::Index(index)) => *index as usize,
+      (_, Token::Named(name)) => {
+        let pool = self.pool();
+        let index = *pool.names.get(name).ok_or(format!("Undefined constant '{name}'."))?;
+        if !accepts(operand, &pool.entries[index]) {
+          return Err(format!("Constant '{name}' is not a valid {operand:?} entry."));
+        }
+        index
+      }
+      (Operand::Constant, Token::String(s)) => self.pool().intern(PoolEntry::String(s.clone())),
+      (Operand::Constant, Token::Integer(i)) => {
+        self.pool().intern(PoolEntry::Integer(integer(*i)?))
+      }
+      (Operand::Constant, Token::Float(f)) => self.pool().intern(PoolEntry::Float(*f as f32)),
+      (Operand::Constant, Token::Long(l)) => self.pool().intern(PoolEntry::Long(*l)),
+      (Operand::Constant, Token::Double(d)) => self.pool().intern(PoolEntry::Double(*d)),
+      (Operand::Module, Token::Ident(s)) => self.pool().intern(PoolEntry::Module(s.clone())),
+      (Operand::Function, Token::Ident(s)) => self.pool().intern(PoolEntry::Function(s.clone())),
+      (Operand::Class, Token::Ident(s)) => self.pool().intern(PoolEntry::Class(s.clone())),
+      (Operand::Field, Token::Ident(s)) => self.pool().intern(PoolEntry::Field(s.clone())),
+      (operand, arg) => return Err(format!("Invalid {operand:?} operand {arg:?}.")),
+    };
+    if value > max {
+      return Err(format!("{operand:?} operand {value} out of range."));
+    }
+    Ok(value)
+  }
+}
+
+fn accepts(operand: Operand, entry: &PoolEntry) -> bool {
+  matches!(
+    (operand, entry),
+    (
+      Operand::Constant,
+      PoolEntry::String(..)
+        | PoolEntry::Integer(..)
+        | PoolEntry::Float(..)
+        | PoolEntry::Long(..)
+        | PoolEntry::Double(..)
+    ) | (Operand::Module, PoolEntry::Module(..))
+      | (Operand::Function, PoolEntry::Function(..))
+      | (Operand::Class, PoolEntry::Class(..))
+      | (Operand::Field, PoolEntry::Field(..))
+  )
+}
+
+fn encode(bytes: &mut Vec<u8>, operand: Operand, value: usize) {
+  match operand.width() {
+    1 => bytes.push(value as u8),
+    2 => bytes.extend_from_slice(&(value as u16).to_be_bytes()),
+    _ => bytes.extend_from_slice(&(value as u32).to_be_bytes()),
+  }
+}
+
+fn target(token: &Token) -> Line<Target> {
+  match token {
+    Token::Ident(label) => Ok(Target::Label(label.clone())),
+    Token::Address(address) => u16::try_from(*address)
+      .map(Target::Address)
+      .map_err(|_| format!("Invalid address @{address}.")),
+    token => Err(format!("Expected a label or address, found {token:?}.")),
+  }
+}
+
+fn address(offset: usize) -> Line<u16> {
+  u16::try_from(offset).map_err(|_| "Function is too long.".to_string())
+}
+
+fn constant(value: &[Token]) -> Line<PoolEntry> {
+  let entry = match value {
+    [Token::String(s)] => PoolEntry::String(s.clone()),
+    [Token::Integer(i)] => PoolEntry::Integer(integer(*i)?),
+    [Token::Float(f)] => PoolEntry::Float(*f as f32),
+    [Token::Long(l)] => PoolEntry::Long(*l),
+    [Token::Double(d)] => PoolEntry::Double(*d),
+    [Token::Ident(kind), Token::Integer(i)] if kind == "integer" => {
+      PoolEntry::Integer(integer(*i)?)
+    }
+    [Token::Ident(kind), Token::Float(f)] if kind == "float" => PoolEntry::Float(*f as f32),
+    [Token::Ident(kind), Token::Ident(word)] if kind == "float" && is_special(word) => {
+      PoolEntry::Float(word.parse().unwrap())
+    }
+    [Token::Ident(kind), Token::Ident(word)] if kind == "double" && is_special(word) => {
+      PoolEntry::Double(word.parse().unwrap())
+    }
+    [Token::Ident(kind), Token::Integer(l) | Token::Long(l)] if kind == "long" => {
+      PoolEntry::Long(*l)
+    }
+    [Token::Ident(kind), Token::Float(d) | Token::Double(d)] if kind == "double" => {
+      PoolEntry::Double(*d)
+    }
+    [Token::Ident(kind), Token::String(s) | Token::Ident(s)] => match kind.as_str() {
+      "string" => PoolEntry::String(s.clone()),
+      "module" => PoolEntry::Module(s.clone()),
+      "function" => PoolEntry::Function(s.clone()),
+      "class" => PoolEntry::Class(s.clone()),
+      "field" => PoolEntry::Field(s.clone()),
+      _ => return Err(format!("Unknown constant kind '{kind}'.")),
+    },
+    _ => return Err("Invalid constant.".to_string()),
+  };
+  Ok(entry)
+}
+
+/// The float words that are not numbers, `-inf` is lexed as a float.
+fn is_special(word: &str) -> bool {
+  matches!(word, "inf" | "NaN")
+}
+
+fn integer(i: i64) -> Line<i32> {
+  i32::try_from(i).map_err(|_| format!("Integer {i} out of range."))
+}
+
+/// The arguments and locals of a function, a method also keeps `this` in local 0.
+fn signature(args: &[Token], method: bool) -> Line<(u8, u16)> {
+  let mut arguments = None;
+  let mut locals = None;
+  for pair in args.chunks(3) {
+    match pair {
+      [Token::Ident(key), Token::Equals, Token::Integer(value)] => match key.as_str() {
+        "args" => {
+          arguments = Some(u8::try_from(*value).map_err(|_| format!("Invalid args {value}."))?)
+        }
+        "locals" => {
+          locals = Some(u16::try_from(*value).map_err(|_| format!("Invalid locals {value}."))?)
+        }
+        _ => return Err(format!("Unknown attribute '{key}'.")),
+      },
+      _ => return Err("Expected 'args=N' or 'locals=N'.".to_string()),
+    }
+  }
+  let arguments = arguments.unwrap_or(0);
+  let slots = arguments as u16 + method as u16;
+  let locals = locals.unwrap_or(slots);
+  if locals < slots {
+    return Err(match method {
+      true => format!("Method has {arguments} arguments and 'this' but only {locals} locals."),
+      false => format!("Function has {arguments} arguments but only {locals} locals."),
+    });
+  }
+  Ok((arguments, locals))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::function::Code;
+
+  fn fail(source: &str) -> Error {
+    assemble(source).unwrap_err()
+  }
+
+  #[test]
+  fn encodes_operands_and_labels() {
+    let module = assemble(
+      ".module m\n.function f args=1 locals=2\nloop:\n  IINC 1 2\n  GOTO loop\n  LOAD_0\n  RETURN\n.end",
+    )
+    .unwrap();
+    let Code::Bytecode(code) = &module.functions["f"].code else { panic!("native function") };
+    assert_eq!(code[..], [opcode::IINC, 1, 2, opcode::GOTO, 0, 0, opcode::LOAD_0, opcode::RETURN]);
+  }
+
+  #[test]
+  fn unknown_instruction() {
+    let error = fail(".module m\n.function f\n  FROB\n.end");
+    assert_eq!((error.line, error.message.as_str()), (3, "Unknown instruction 'FROB'."));
+  }
+
+  #[test]
+  fn undefined_label() {
+    let error = fail(".module m\n.function f\n  GOTO nowhere\n.end");
+    assert_eq!(error.message, "Undefined label 'nowhere' (used at line 3).");
+  }
+
+  #[test]
+  fn bad_operands() {
+    let error = fail(".module m\n.function f locals=1\n  IINC 0 256\n.end");
+    assert_eq!((error.line, error.message.as_str()), (3, "Operand 256 out of range."));
+    let error = fail(".module m\n.function f\n  GOTO 3\n.end");
+    assert_eq!(error.message, "Invalid Label operand Integer(3).");
+    let error = fail(".module m\n.function f\n  POP 1\n.end");
+    assert_eq!(error.message, "'POP' expects 0 operand(s), found 1.");
+    let error = fail(".module m\n.function f\n  LOADCONST $missing\n.end");
+    assert_eq!(error.message, "Undefined constant 'missing'.");
+  }
+
+  #[test]
+  fn structure_errors() {
+    assert_eq!(fail(".module m\n.function f\n  RETURN").message, "Missing '.end'.");
+    assert_eq!(fail("  RETURN").message, "Instruction 'RETURN' outside of function.");
+    assert_eq!(fail(".module m\n.module n").message, "Module name already defined.");
+    let fields = (0..257).map(|i| format!(".field f{i}\n")).collect::<String>();
+    let error = fail(&format!(".module m\n.class C\n{fields}.end"));
+    assert_eq!((error.line, error.message.as_str()), (259, "Class 'C' has more than 256 fields."));
+  }
+
+  #[test]
+  fn method_locals_include_this() {
+    let source = ".module m\n.class C\n.method get args=1\n  LOAD_1\n  RETURN\n.end\n.end";
+    let method = &assemble(source).unwrap().classes["C"].methods["get"];
+    assert_eq!((method.arguments, method.locals), (1, 2));
+    let error = fail(".module m\n.class C\n.method put args=1 locals=1\n  RETURN\n.end\n.end");
+    assert_eq!(
+      (error.line, error.message.as_str()),
+      (3, "Method has 1 arguments and 'this' but only 1 locals.")
+    );
+  }
+
+  #[test]
+  fn lexer_tokens() {
+    use lexer::{tokenize, Token};
+
+    let tokens = tokenize(r#"loop: LOADCONST "a\n" -0x10 1.5 2L 0.5D $k #3 @4 ; comment"#).unwrap();
+    assert_eq!(
+      tokens,
+      [
+        Token::Label("loop".to_string()),
+        Token::Ident("LOADCONST".to_string()),
+        Token::String("a\n".to_string()),
+        Token::Integer(-16),
+        Token::Float(1.5),
+        Token::Long(2),
+        Token::Double(0.5),
+        Token::Named("k".to_string()),
+        Token::Index(3),
+        Token::Address(4),
+      ]
+    );
+    assert_eq!(tokenize(r#""open"#).unwrap_err(), "Unterminated string.");
+    assert_eq!(tokenize(r#""\q""#).unwrap_err(), "Unknown escape '\\q'.");
+    assert_eq!(tokenize("12x").unwrap_err(), "Invalid number '12x'.");
+    assert_eq!(tokenize("LOAD_0 !").unwrap_err(), "Unexpected character '!'.");
+  }
+}
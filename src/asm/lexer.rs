@@ -0,0 +1,150 @@
+use std::{iter::Peekable, str::Chars};
+
+/// An assembly token.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Token {
+  /// A directive, `.function`.
+  Directive(String),
+  /// A label definition, `loop:`.
+  Label(String),
+  /// An identifier or path, `std:out:println`.
+  Ident(String),
+  /// A named constant, `$hello`.
+  Named(String),
+  /// A raw constant pool index, `#12`.
+  Index(u32),
+  /// A raw bytecode address, `@12`.
+  Address(u32),
+  /// A string literal.
+  String(String),
+  /// An integer literal.
+  Integer(i64),
+  /// A float literal.
+  Float(f64),
+  /// A long literal, `42L`.
+  Long(i64),
+  /// A double literal, `0.5D`.
+  Double(f64),
+  /// The `=` sign.
+  Equals,
+}
+
+/// Split an assembly line into tokens, comments start with `;`.
+pub fn tokenize(line: &str) -> Result<Vec<Token>, String> {
+  let mut tokens = Vec::new();
+  let mut chars = line.chars().peekable();
+
+  while let Some(&c) = chars.peek() {
+    match c {
+      ';' => break,
+      c if c.is_whitespace() => _ = chars.next(),
+      '=' => {
+        chars.next();
+        tokens.push(Token::Equals);
+      }
+      '"' => {
+        chars.next();
+        tokens.push(Token::String(string(&mut chars)?));
+      }
+      '.' => {
+        chars.next();
+        tokens.push(Token::Directive(word(&mut chars)));
+      }
+      '$' => {
+        chars.next();
+        tokens.push(Token::Named(word(&mut chars)));
+      }
+      '#' => {
+        chars.next();
+        tokens.push(Token::Index(unsigned(&word(&mut chars))?));
+      }
+      '@' => {
+        chars.next();
+        tokens.push(Token::Address(unsigned(&word(&mut chars))?));
+      }
+      c if c.is_ascii_digit() || c == '-' => tokens.push(number(&word(&mut chars))?),
+      c if is_word(c) => {
+        let word = word(&mut chars);
+        match word.strip_suffix(':') {
+          Some(label) => tokens.push(Token::Label(label.to_string())),
+          None => tokens.push(Token::Ident(word)),
+        }
+      }
+      c => return Err(format!("Unexpected character '{c}'.")),
+    }
+  }
+
+  Ok(tokens)
+}
+
+pub(crate) fn is_word(c: char) -> bool {
+  c.is_alphanumeric() || matches!(c, '_' | ':' | '.' | '-' | '+')
+}
+
+fn word(chars: &mut Peekable<Chars>) -> String {
+  let mut word = String::new();
+  while let Some(&c) = chars.peek() {
+    if !is_word(c) {
+      break;
+    }
+    word.push(c);
+    chars.next();
+  }
+  word
+}
+
+fn string(chars: &mut Peekable<Chars>) -> Result<String, String> {
+  let mut s = String::new();
+  loop {
+    match chars.next() {
+      Some('"') => break Ok(s),
+      Some('\\') => match chars.next() {
+        Some('n') => s.push('\n'),
+        Some('r') => s.push('\r'),
+        Some('t') => s.push('\t'),
+        Some('0') => s.push('\0'),
+        Some('\\') => s.push('\\'),
+        Some('"') => s.push('"'),
+        Some(c) => break Err(format!("Unknown escape '\\{c}'.")),
+        None => break Err("Unterminated string.".to_string()),
+      },
+      Some(c) => s.push(c),
+      None => break Err("Unterminated string.".to_string()),
+    }
+  }
+}
+
+fn unsigned(word: &str) -> Result<u32, String> {
+  let result = match word.strip_prefix("0x") {
+    Some(hex) => u32::from_str_radix(hex, 16),
+    None => word.parse(),
+  };
+  result.map_err(|_| format!("Invalid number '{word}'."))
+}
+
+/// A number, suffixed by `L` for a long or `D` for a double.
+fn number(word: &str) -> Result<Token, String> {
+  if let Some(long) = word.strip_suffix('L') {
+    return match number(long)? {
+      Token::Integer(integer) => Ok(Token::Long(integer)),
+      _ => Err(format!("Invalid number '{word}'.")),
+    };
+  }
+  let (negative, digits) = match word.strip_prefix('-') {
+    Some(digits) => (true, digits),
+    None => (false, word),
+  };
+
+  if digits == "inf" {
+    Ok(Token::Float(if negative { f64::NEG_INFINITY } else { f64::INFINITY }))
+  } else if let Some(hex) = digits.strip_prefix("0x") {
+    let integer = i64::from_str_radix(hex, 16).map_err(|_| format!("Invalid number '{word}'."))?;
+    Ok(Token::Integer(if negative { -integer } else { integer }))
+  } else if let Some(double) = word.strip_suffix('D') {
+    double.parse().map(Token::Double).map_err(|_| format!("Invalid number '{word}'."))
+  } else if digits.contains(['.', 'e', 'E']) {
+    word.parse().map(Token::Float).map_err(|_| format!("Invalid number '{word}'."))
+  } else {
+    word.parse().map(Token::Integer).map_err(|_| format!("Invalid number '{word}'."))
+  }
+}
@@ -1,4 +1,5 @@
 pub mod builder;
+pub mod error;
 pub mod read;
 pub mod write;
 
@@ -34,7 +35,15 @@ pub struct Class {
 impl Class {
   pub const TAG: u8 = 0x2;
 
+  /// Name of the method run on unreachable objects before they are freed.
+  pub const FINALIZER: &'static str = "drop";
+
   pub fn fetch_function_with_name_unchecked(&self, function_name: &str) -> &Function {
     &self.methods[function_name]
   }
+
+  /// The finalizer method, a `drop` method taking no argument besides the object.
+  pub fn finalizer(&self) -> Option<&Function> {
+    self.methods.get(Self::FINALIZER).filter(|method| method.arguments == 0)
+  }
 }
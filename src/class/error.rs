@@ -0,0 +1,35 @@
+use crate::{
+  function::{builder::FunctionBuilder, NativeRet},
+  gc::Gc,
+  runtime::native::NativeCtx,
+};
+
+use super::{Class, ClassBuilder};
+
+/// The class of exceptions raised by the runtime and native functions.
+pub const NAME: &str = "Error";
+
+/// The exception message field.
+pub const MESSAGE: &str = "message";
+
+fn new(ctx: &mut NativeCtx) -> NativeRet {
+  let (this, message) = (ctx.value(0), ctx.value(1));
+  ctx.gc().write_barrier(this, message);
+  Gc::set_field2(this.reference(), MESSAGE, message);
+  Ok(Some(this))
+}
+
+pub fn class() -> Class {
+  ClassBuilder::new()
+    .with_name(NAME)
+    .with_field(MESSAGE)
+    .with_method(
+      FunctionBuilder::new()
+        .with_name("new")
+        .with_arguments(1)
+        .with_locals(2)
+        .with_native(new)
+        .build(),
+    )
+    .build()
+}
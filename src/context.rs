@@ -6,6 +6,7 @@ use crate::{
   runtime::{Error, Result},
 };
 
+#[derive(Default)]
 pub struct Context<'c> {
   pub(crate) modules: BTreeMap<Rc<str>, &'c Module>,
   pub(crate) classes: BTreeMap<Rc<str>, &'c Class>,
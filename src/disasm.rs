@@ -0,0 +1,212 @@
+use core::fmt;
+
+use crate::{
+  asm::lexer,
+  class::Class,
+  formatting::Formatting,
+  function::{Code, Function},
+  instruction::{Instruction, Instructions},
+  module::Module,
+  opcode::{self, Operand},
+  pool_entry::PoolEntry,
+};
+
+/// Display a module as an assembler-friendly listing, see `docs/assembler.md`.
+pub fn display_module(module: &Module) -> impl fmt::Display + '_ {
+  Formatting(move |f| {
+    writeln!(f, ".module {}", module.name)?;
+    write_constants(f, &module.constants, "")?;
+    for function in module.functions.values() {
+      writeln!(f)?;
+      write_function(f, function, &module.constants, "")?;
+    }
+    for class in module.classes.values() {
+      writeln!(f)?;
+      write!(f, "{}", display_class(class))?;
+    }
+    Ok(())
+  })
+}
+
+/// Display a class as an assembler-friendly listing.
+pub fn display_class(class: &Class) -> impl fmt::Display + '_ {
+  Formatting(move |f| {
+    writeln!(f, ".class {}", class.name)?;
+    let mut fields = class.fields.iter().collect::<Vec<_>>();
+    fields.sort_by_key(|(_, field)| field.offset);
+    for (name, _) in fields {
+      writeln!(f, "  .field {name}")?;
+    }
+    write_constants(f, &class.constants, "  ")?;
+    for method in class.methods.values() {
+      writeln!(f)?;
+      write_function(f, method, &class.constants, "  ")?;
+    }
+    writeln!(f, ".end")
+  })
+}
+
+/// Display a function, resolving operands against `constants`.
+pub fn display_function<'a>(
+  function: &'a Function,
+  constants: &'a [PoolEntry],
+) -> impl fmt::Display + 'a {
+  Formatting(move |f| write_function(f, function, constants, ""))
+}
+
+fn write_constants(f: &mut fmt::Formatter, constants: &[PoolEntry], indent: &str) -> fmt::Result {
+  if !constants.is_empty() {
+    writeln!(f)?;
+  }
+  for (index, entry) in constants.iter().enumerate() {
+    let constant = match entry {
+      PoolEntry::String(s) => format!("string {}", quote(s)),
+      PoolEntry::Integer(i) => format!("integer {i}"),
+      PoolEntry::Float(x) => format!("float {x:?}"),
+      PoolEntry::Long(l) => format!("long {l}"),
+      PoolEntry::Double(x) => format!("double {x:?}"),
+      PoolEntry::Module(s) => format!("module {}", ident(s)),
+      PoolEntry::Function(s) => format!("function {}", ident(s)),
+      PoolEntry::Class(s) => format!("class {}", ident(s)),
+      PoolEntry::Field(s) => format!("field {}", ident(s)),
+    };
+    writeln!(f, "{indent}.const c{index} {constant:<40} ; #{index}")?;
+  }
+  Ok(())
+}
+
+fn write_function(
+  f: &mut fmt::Formatter,
+  function: &Function,
+  constants: &[PoolEntry],
+  indent: &str,
+) -> fmt::Result {
+  let kind = if indent.is_empty() { "function" } else { "method" };
+  write!(
+    f,
+    "{indent}.{kind} {} args={} locals={}",
+    function.name, function.arguments, function.locals
+  )?;
+
+  let code = match &function.code {
+    Code::Bytecode(code) => code,
+    Code::Native(..) => return writeln!(f, " ; <native>\n{indent}.end"),
+  };
+  writeln!(f)?;
+
+  for instruction in Instructions::new(code) {
+    match instruction {
+      Ok(instruction) => {
+        let line = display_instruction(&instruction, constants);
+        writeln!(f, "{indent}  {:<6} {line}", format!("@{}", instruction.ip))?
+      }
+      Err(e) => writeln!(f, "{indent}  ; {e}")?,
+    }
+  }
+
+  for handler in function.handlers.iter() {
+    write!(f, "{indent}  .catch @{} @{} @{}", handler.start, handler.end, handler.handler)?;
+    match handler.class {
+      Some(class) => {
+        writeln!(f, " {}", display_operand(Operand::Class, class as usize, constants))?
+      }
+      None => writeln!(f)?,
+    }
+  }
+
+  writeln!(f, "{indent}.end")
+}
+
+/// Display an instruction with its operands resolved against `constants`.
+pub fn display_instruction(instruction: &Instruction, constants: &[PoolEntry]) -> String {
+  let mnemonic = opcode::TO_STR[instruction.opcode as usize];
+  let operands = opcode::operands(instruction.opcode);
+
+  if let ([Operand::Module, Operand::Function, rest @ ..], [module, function, values @ ..]) =
+    (operands, &instruction.operands[..])
+  {
+    let module = resolve(Operand::Module, *module, constants);
+    let function = resolve(Operand::Function, *function, constants);
+    if let (Some(module), Some(function)) = (module, function) {
+      if !function.contains(':') {
+        let mut line = format!("{mnemonic} {module}:{function}");
+        for (operand, value) in rest.iter().zip(values.iter()) {
+          line.push(' ');
+          line.push_str(&display_operand(*operand, *value, constants));
+        }
+        return line;
+      }
+    }
+  }
+
+  let mut line = mnemonic.to_string();
+  for (operand, value) in instruction.kinds().zip(instruction.operands.iter()) {
+    line.push(' ');
+    line.push_str(&display_operand(operand, *value, constants));
+  }
+  line
+}
+
+fn display_operand(operand: Operand, value: usize, constants: &[PoolEntry]) -> String {
+  match operand {
+    Operand::Byte | Operand::Short | Operand::Local => value.to_string(),
+    Operand::Int => (value as u32 as i32).to_string(),
+    Operand::Label => format!("@{value}"),
+    _ => resolve(operand, value, constants).unwrap_or_else(|| format!("#{value}")),
+  }
+}
+
+/// Render a pool operand the way the assembler would intern it back to `index`.
+fn resolve(operand: Operand, index: usize, constants: &[PoolEntry]) -> Option<String> {
+  let entry = constants.get(index)?;
+  if constants.iter().position(|e| e == entry) != Some(index) {
+    return None;
+  }
+  match (operand, entry) {
+    (Operand::Constant, PoolEntry::String(s)) => Some(quote(s)),
+    (Operand::Constant, PoolEntry::Integer(i)) => Some(i.to_string()),
+    (Operand::Constant, PoolEntry::Float(x)) if x.is_finite() => Some(format!("{x:?}")),
+    (Operand::Constant, PoolEntry::Long(l)) => Some(format!("{l}L")),
+    (Operand::Constant, PoolEntry::Double(x)) if x.is_finite() => Some(format!("{x:?}D")),
+    (Operand::Module, PoolEntry::Module(s))
+    | (Operand::Function, PoolEntry::Function(s))
+    | (Operand::Class, PoolEntry::Class(s))
+    | (Operand::Field, PoolEntry::Field(s))
+      if is_ident(s) =>
+    {
+      Some(s.clone())
+    }
+    _ => None,
+  }
+}
+
+fn is_ident(s: &str) -> bool {
+  s.chars().all(lexer::is_word)
+    && s.starts_with(|c: char| !c.is_ascii_digit() && c != '-')
+    && !s.ends_with(':')
+}
+
+fn ident(s: &str) -> String {
+  if is_ident(s) {
+    s.to_string()
+  } else {
+    quote(s)
+  }
+}
+
+fn quote(s: &str) -> String {
+  let mut quoted = String::from('"');
+  for c in s.chars() {
+    match c {
+      '"' => quoted.push_str("\\\""),
+      '\\' => quoted.push_str("\\\\"),
+      '\n' => quoted.push_str("\\n"),
+      '\r' => quoted.push_str("\\r"),
+      '\t' => quoted.push_str("\\t"),
+      '\0' => quoted.push_str("\\0"),
+      c => quoted.push(c),
+    }
+  }
+  quoted.push('"');
+  quoted
+}
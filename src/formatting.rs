@@ -1,11 +1,11 @@
 use core::fmt;
 
 use crate::{
-  gc::{Gc, ObjArray, ObjDict, ObjString},
+  gc::{Gc, ObjArray, ObjBytes, ObjDict, ObjFunction, ObjNative, ObjString, ObjWeak},
   value::Value,
 };
 
-struct Formatting<F: Fn(&mut fmt::Formatter) -> fmt::Result>(pub F);
+pub(crate) struct Formatting<F: Fn(&mut fmt::Formatter) -> fmt::Result>(pub F);
 
 impl<F: Fn(&mut fmt::Formatter) -> fmt::Result> fmt::Display for Formatting<F> {
   fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -18,6 +18,10 @@ pub fn display_value<'a>(v: &'a Value, _heap: &'a Gc) -> impl fmt::Display + 'a
     Value::TAG_BYTE => write!(f, "{}", v.byte()),
     Value::TAG_INTEGER => write!(f, "{}", v.integer()),
     Value::TAG_FLOAT => write!(f, "{}", v.float()),
+    Value::TAG_LONG => write!(f, "{}", v.long()),
+    Value::TAG_DOUBLE => write!(f, "{}", v.double()),
+    Value::TAG_BOOL => write!(f, "{}", v.bool()),
+    Value::TAG_CHAR => write!(f, "{}", v.char()),
     Value::TAG_NULL => write!(f, "null"),
     Value::TAG_STRING => {
       let ptr = v.reference() as *mut ObjString;
@@ -32,6 +36,27 @@ pub fn display_value<'a>(v: &'a Value, _heap: &'a Gc) -> impl fmt::Display + 'a
       write!(f, "array({:?})", unsafe { &(*ptr).arr })
     }
     Value::TAG_CLASS => write!(f, "class({:?})", v),
+    Value::TAG_FUNCTION => {
+      let ptr = v.reference() as *mut ObjFunction;
+      let (module, function) = unsafe { (&(*(*ptr).module).name, &(*(*ptr).function).name) };
+      write!(f, "function({module}:{function})")
+    }
+    Value::TAG_BYTES => {
+      let ptr = v.reference() as *mut ObjBytes;
+      write!(f, "<<")?;
+      for byte in unsafe { &(*ptr).bytes } {
+        write!(f, " {byte}")?;
+      }
+      write!(f, " >>")
+    }
+    Value::TAG_NATIVE => {
+      let ptr = v.reference() as *mut ObjNative;
+      write!(f, "native({})", unsafe { (*ptr).type_name })
+    }
+    Value::TAG_WEAK => {
+      let ptr = v.reference() as *mut ObjWeak;
+      write!(f, "weak({:?})", unsafe { (*ptr).target })
+    }
     _ => unreachable!(),
   })
 }
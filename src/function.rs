@@ -5,11 +5,14 @@ pub mod write;
 use core::fmt;
 use std::rc::Rc;
 
-use crate::{gc::Gc, local::Local, runtime::Result, value::Value};
+use crate::{
+  runtime::{native::NativeCtx, Result},
+  value::Value,
+};
 
 /// Bytecode Function representation.
 ///
-/// ```
+/// ```text
 /// {
 ///   function_name_length: u16,
 ///   function_name: str<function_name_length>,
@@ -17,6 +20,8 @@ use crate::{gc::Gc, local::Local, runtime::Result, value::Value};
 ///   arguments: u8,
 ///   code_length: u16,
 ///   code: Vec<code_length>,
+///   handlers_count: u16,
+///   handlers: Vec<Handler, handlers_count>,
 /// }
 /// ```
 #[derive(Debug)]
@@ -29,10 +34,45 @@ pub struct Function {
   pub arguments: u8,
   /// The function bytecode or native call.
   pub code: Code,
+  /// The exception handlers, in lookup order.
+  pub handlers: Box<[Handler]>,
+}
+
+/// Exception handler, catches exceptions thrown by instructions in `start..end`.
+///
+/// ```text
+/// {
+///   start: u16,
+///   end: u16,
+///   handler: u16,
+///   class: u16,
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Handler {
+  /// The first covered instruction address.
+  pub start: u16,
+  /// The address after the last covered instruction.
+  pub end: u16,
+  /// The handler address.
+  pub handler: u16,
+  /// Class entry index of the caught exceptions, `None` catches everything.
+  pub class: Option<u16>,
+}
+
+impl Handler {
+  /// The encoded `class` of a catch-all handler.
+  pub const ANY: u16 = u16::MAX;
+
+  /// Whether the handler covers an instruction interrupted with the instruction pointer at `ip`.
+  pub fn covers(&self, ip: usize) -> bool {
+    self.start as usize <= ip.saturating_sub(1) && ip.saturating_sub(1) < self.end as usize
+  }
 }
 
 pub type NativeRet = Result<Option<Value>>;
-pub type NativeFn = fn(&mut Local, &mut Gc) -> NativeRet;
+/// A native function, a plain `fn` or a closure capturing host state.
+pub type NativeFn = Box<dyn Fn(&mut NativeCtx) -> NativeRet>;
 
 pub enum Code {
   Bytecode(Box<[u8]>),
@@ -49,7 +89,13 @@ impl fmt::Debug for Code {
 }
 
 impl Function {
-  pub fn native(name: &str, args: u8, f: NativeFn) -> Self {
-    Self { name: Rc::from(name), locals: args as u16, arguments: args, code: Code::Native(f) }
+  pub fn native(name: &str, args: u8, f: impl Fn(&mut NativeCtx) -> NativeRet + 'static) -> Self {
+    Self {
+      name: Rc::from(name),
+      locals: args as u16,
+      arguments: args,
+      code: Code::Native(Box::new(f)),
+      handlers: Box::new([]),
+    }
   }
 }
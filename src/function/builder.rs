@@ -1,4 +1,6 @@
-use super::{Code, Function, NativeFn};
+use crate::runtime::native::NativeCtx;
+
+use super::{Code, Function, Handler, NativeRet};
 
 #[derive(Default)]
 pub struct FunctionBuilder {
@@ -6,6 +8,7 @@ pub struct FunctionBuilder {
   locals: u16,
   arguments: u8,
   code: Option<Code>,
+  handlers: Vec<Handler>,
 }
 
 impl FunctionBuilder {
@@ -33,8 +36,13 @@ impl FunctionBuilder {
     self
   }
 
-  pub fn with_native(mut self, native: NativeFn) -> Self {
-    self.code = Some(Code::Native(native));
+  pub fn with_native(mut self, native: impl Fn(&mut NativeCtx) -> NativeRet + 'static) -> Self {
+    self.code = Some(Code::Native(Box::new(native)));
+    self
+  }
+
+  pub fn with_handler(mut self, handler: Handler) -> Self {
+    self.handlers.push(handler);
     self
   }
 
@@ -46,6 +54,7 @@ impl FunctionBuilder {
       locals: self.locals,
       arguments: self.arguments,
       code: self.code.unwrap(),
+      handlers: self.handlers.into(),
     }
   }
 }
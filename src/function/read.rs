@@ -1,4 +1,4 @@
-use super::{Code, Function};
+use super::{Code, Function, Handler};
 use crate::read_bytes::ReadBytes;
 
 impl Function {
@@ -13,6 +13,19 @@ impl Function {
 
     let code = Code::Bytecode(Box::from(code_buf));
 
-    Ok(Self { name, locals, arguments, code })
+    let handlers_count = rd.read_u16()?;
+    let handlers = (0..handlers_count).map(|_| Handler::read(rd)).collect::<Result<_, _>>()?;
+
+    Ok(Self { name, locals, arguments, code, handlers })
+  }
+}
+
+impl Handler {
+  pub fn read<R: std::io::Read>(rd: &mut R) -> std::io::Result<Self> {
+    let start = rd.read_u16()?;
+    let end = rd.read_u16()?;
+    let handler = rd.read_u16()?;
+    let class = Some(rd.read_u16()?).filter(|class| *class != Handler::ANY);
+    Ok(Self { start, end, handler, class })
   }
 }
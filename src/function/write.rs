@@ -1,4 +1,4 @@
-use super::{Code, Function};
+use super::{Code, Function, Handler};
 use crate::write_bytes::WriteBytes;
 
 impl Function {
@@ -12,6 +12,19 @@ impl Function {
     } else {
       panic!("Cannot write native function")
     }
+    wr.write_u16(self.handlers.len() as u16)?;
+    for handler in self.handlers.iter() {
+      handler.write(wr)?;
+    }
     Ok(())
   }
 }
+
+impl Handler {
+  pub fn write<W: std::io::Write>(&self, wr: &mut W) -> std::io::Result<()> {
+    wr.write_u16(self.start)?;
+    wr.write_u16(self.end)?;
+    wr.write_u16(self.handler)?;
+    wr.write_u16(self.class.unwrap_or(Handler::ANY))
+  }
+}
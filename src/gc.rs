@@ -1,48 +1,402 @@
+pub mod copying;
+pub mod incremental;
 pub mod mark_sweep;
-
-use std::collections::{BTreeMap, BTreeSet, HashSet};
+pub mod roots;
+pub mod snapshot;
+pub mod stats;
+
+use core::fmt;
+use std::{
+  alloc::Layout,
+  any::Any,
+  cmp::Ordering,
+  collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+  mem::size_of,
+};
 
 use crate::{
   runtime::{Error, Result},
-  value::{Reference, Value},
+  value::{Reference, Value, BOXED},
 };
 
+use copying::Chunk;
+use incremental::{Cursor, Phase, BYTES_PER_OBJECT};
+use stats::GcStats;
+
+/// Bytes allocated in the nursery before a minor collection.
+pub const NURSERY_SIZE: usize = 1 << 20;
+
+/// Minimum old generation size before a major collection.
+pub const MAJOR_THRESHOLD: usize = 8 << 20;
+
+/// The collection strategy of a [`Gc`], chosen when it is created.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Collector {
+  /// Generational mark and sweep, objects are allocated individually and never move.
+  #[default]
+  Generational,
+  /// Semi-space copying, objects are bump allocated in chunks and moved by every collection.
+  Copying,
+  /// Incremental mark and sweep, collections run in slices scanning or sweeping at most `budget`
+  /// objects and slots, see [`Gc::collect_slice`].
+  Incremental { budget: usize },
+}
+
+/// Generational heap, objects start in the nursery and are promoted to the old generation when
+/// they survive a collection.
+///
+/// Every object is accounted with its size, see [`Gc::object_size`], allocations fail with
+/// [`Error::OutOfMemory`] past the heap limit.
+///
+/// With [`Collector::Copying`] and [`Collector::Incremental`] every object stays in the nursery,
+/// collections copy the live ones to new chunks or trace the whole heap in slices.
 pub struct Gc {
-  roots: Vec<Value>,
+  collector: Collector,
+  /// Objects allocated since the last collection, with their accounted size.
+  young: HashMap<Value, usize>,
+  /// Objects that survived a collection, with their accounted size.
+  old: HashMap<Value, usize>,
+  /// Allocation sequence numbers of the tracked objects, identifying them while they move.
+  ids: HashMap<Value, u64>,
+  next_id: u64,
+  /// Old objects that were given a reference to a young object, roots of minor collections.
+  remembered: HashSet<Value>,
   marked: HashSet<Value>,
+  /// Bytes allocated since the last collection.
+  allocated: usize,
+  /// Bytes of the old generation, or of the objects surviving the last copying collection.
+  old_bytes: usize,
+  /// Bytes of the whole heap.
+  heap_bytes: usize,
+  /// Old generation size triggering the next major collection.
+  major_threshold: usize,
+  /// Heap size triggering the next major collection when the heap is limited.
+  pressure: usize,
+  max_heap: Option<usize>,
+  /// Bytes allocated before a minor collection.
+  nursery_size: usize,
+  /// Tracked objects by tag.
+  live: [usize; 16],
+  /// Weak references, cleared when their target is collected.
+  weaks: HashSet<Value>,
+  /// Objects with a finalizer that did not run yet.
+  finalizable: HashSet<Value>,
+  /// Unreachable objects waiting for their finalizer, kept alive until it ran.
+  finalizing: Vec<Value>,
+  /// Interned strings by contents, rooted by the runtime.
+  interned: HashMap<String, Value>,
+  /// The chunks of the current semi-space, allocating in the last one.
+  chunks: Vec<Chunk>,
+  /// Chunks reserved for the objects moved by the copying collection in progress.
+  to_space: Vec<Chunk>,
+  /// New addresses of the objects moved by the last copying collection.
+  forwarding: HashMap<Value, Value>,
+  phase: Phase,
+  /// Marked objects left to scan by an incremental collection.
+  grey: Vec<Value>,
+  /// The grey object partly scanned by an incremental collection, and where to resume.
+  scanning: Option<(Value, Cursor)>,
+  /// Objects left to sweep by an incremental collection, they stay in the nursery until swept.
+  sweeping: Vec<Value>,
+  /// Objects of the incremental collector in allocation order, the sweep takes them whole and
+  /// gives the survivors back.
+  unswept: Vec<Value>,
+  stats: GcStats,
 }
 
 impl Gc {
   #[inline(always)]
   pub fn track(&mut self, value: Value) {
-    self.roots.push(value)
+    let size = Self::object_size(value);
+    self.allocated += size;
+    self.heap_bytes += size;
+    self.live[value.tag() as usize] += 1;
+    self.young.insert(value, size);
+    self.ids.insert(value, self.next_id);
+    self.next_id += 1;
+    if let Collector::Incremental { .. } = self.collector {
+      self.unswept.push(value);
+    }
+    if self.phase.marking() {
+      self.marked.insert(value);
+    }
   }
 
   #[inline(always)]
   pub fn mark(&mut self, value: Value) -> bool {
     self.marked.insert(value)
   }
+
+  /// Whether enough bytes were allocated since the last collection to run one.
+  ///
+  /// A copying or incremental collection traces the whole live heap, it waits for at least as many
+  /// bytes as survived the previous one. Slices of an incremental collection run every
+  /// [`BYTES_PER_OBJECT`] bytes per object of budget.
+  #[inline(always)]
+  pub fn should_collect(&self) -> bool {
+    let threshold = match self.collector {
+      Collector::Generational => self.nursery_size,
+      Collector::Incremental { budget } if self.phase != Phase::Idle => {
+        budget.saturating_mul(BYTES_PER_OBJECT)
+      }
+      Collector::Copying | Collector::Incremental { .. } => self.nursery_size.max(self.old_bytes),
+    };
+    self.allocated >= threshold || self.heap_bytes >= self.pressure
+  }
+
+  pub fn collector(&self) -> Collector {
+    self.collector
+  }
+
+  /// Objects scanned or freed by a slice of the incremental collector.
+  fn budget(&self) -> usize {
+    match self.collector {
+      Collector::Incremental { budget } => budget.max(1),
+      _ => usize::MAX,
+    }
+  }
+
+  /// Collect the nursery once `bytes` were allocated, [`NURSERY_SIZE`] by default.
+  pub fn set_threshold(&mut self, bytes: usize) {
+    self.nursery_size = bytes;
+  }
+
+  /// Whether unreachable objects wait for their finalizer.
+  #[inline(always)]
+  pub fn has_finalizers(&self) -> bool {
+    !self.finalizing.is_empty()
+  }
+
+  /// Take the next object whose finalizer must run, it must be rooted until the finalizer ran.
+  pub fn next_finalizer(&mut self) -> Option<Value> {
+    self.finalizing.pop()
+  }
+
+  /// The collector statistics, as of the last collection.
+  pub fn stats(&self) -> &GcStats {
+    &self.stats
+  }
+
+  /// The collector statistics with the heap size and live objects of the heap as it is now, even
+  /// before the first collection.
+  pub fn current_stats(&self) -> GcStats {
+    let mut stats = self.stats.clone();
+    stats.heap_bytes = self.heap_bytes;
+    stats.live = BTreeMap::new();
+    for (object, _) in self.objects() {
+      *stats.live.entry(object.type_name()).or_default() += 1;
+    }
+    stats
+  }
+
+  /// Record the store of `value` into `object`, must be called by anything mutating an object.
+  ///
+  /// Old objects given a young one are remembered, and stored values are shaded grey while an
+  /// incremental collection is marking.
+  #[inline(always)]
+  pub fn write_barrier(&mut self, object: Value, value: Value) {
+    if self.phase.marking() {
+      self.shade(value);
+    }
+    if value.is_reference() && self.young.contains_key(&value) && self.old.contains_key(&object) {
+      self.remembered.insert(object);
+    }
+  }
+
+  /// Account for the new size of `object` after it grew or shrank, like a dict given a new key.
+  ///
+  /// Growing past the heap limit fails with [`Error::OutOfMemory`] and keeps the old size
+  /// accounted, the caller undoes the growth.
+  pub fn resize(&mut self, object: Value) -> Result<()> {
+    let size = Self::object_size(object);
+    let Some(&accounted) = self.young.get(&object).or(self.old.get(&object)) else {
+      return Ok(());
+    };
+    if size > accounted {
+      self.reserve(object.type_name(), size - accounted)?;
+    }
+    if let Some(old) = self.old.get_mut(&object) {
+      self.old_bytes = self.old_bytes - accounted + size;
+      *old = size;
+    } else {
+      self.young.insert(object, size);
+    }
+    self.allocated += size.saturating_sub(accounted);
+    self.heap_bytes = self.heap_bytes - accounted + size;
+    Ok(())
+  }
+
+  /// The heap size limit, `None` when unlimited.
+  pub fn max_heap(&self) -> Option<usize> {
+    self.max_heap
+  }
+
+  /// Limit the heap to `max_heap` bytes, allocations past it fail with [`Error::OutOfMemory`].
+  pub fn set_max_heap(&mut self, max_heap: Option<usize>) {
+    self.max_heap = max_heap;
+    self.update_pressure();
+  }
+
+  /// The tracked objects with their accounted size.
+  pub fn objects(&self) -> impl Iterator<Item = (Value, usize)> + '_ {
+    self.young.iter().chain(self.old.iter()).map(|(value, size)| (*value, *size))
+  }
+
+  /// The allocation sequence number of the tracked object `value`, stable across collections
+  /// unlike its address.
+  pub fn id(&self, value: Value) -> Option<u64> {
+    self.ids.get(&value).copied()
+  }
+
+  /// Bytes of the whole heap.
+  pub fn heap_bytes(&self) -> usize {
+    self.heap_bytes
+  }
+
+  /// Whether `size` more bytes fit in the heap limit.
+  pub fn fits(&self, size: usize) -> bool {
+    self.max_heap.is_none_or(|limit| self.heap_bytes.saturating_add(size) <= limit)
+  }
+
+  /// Run `f` ignoring the heap limit, for the small allocations reporting errors.
+  pub(crate) fn without_limit<T>(&mut self, f: impl FnOnce(&mut Self) -> T) -> T {
+    let max_heap = self.max_heap.take();
+    let result = f(self);
+    self.max_heap = max_heap;
+    result
+  }
+
+  /// Collect at half of the remaining heap, so a full heap is not collected at every allocation.
+  fn update_pressure(&mut self) {
+    self.pressure = match self.max_heap {
+      Some(limit) => self.heap_bytes + limit.saturating_sub(self.heap_bytes) / 2,
+      None => usize::MAX,
+    };
+  }
+
+  /// Check that an object of `size` bytes fits in the heap.
+  fn reserve(&self, kind: &'static str, size: usize) -> Result<()> {
+    match self.fits(size) {
+      true => Ok(()),
+      false => Err(Error::OutOfMemory { kind, size, site: None }),
+    }
+  }
+
+  /// Move `object` to the heap and track it, `contents` being the size of the data it owns.
+  fn alloc<T>(
+    &mut self,
+    tag: u64,
+    kind: &'static str,
+    contents: usize,
+    object: T,
+  ) -> Result<Value> {
+    self.reserve(kind, size_of::<T>().saturating_add(contents))?;
+    let ptr = match self.collector {
+      Collector::Copying => self.bump(Layout::new::<T>()),
+      _ => unsafe { std::alloc::alloc(Layout::new::<T>()) },
+    };
+    if ptr.is_null() {
+      return Err(Error::OutOfMemory { kind, size: size_of::<T>(), site: None });
+    }
+    unsafe { ptr.cast::<T>().write(object) };
+
+    let boxed = match tag {
+      Value::TAG_LONG | Value::TAG_DOUBLE => BOXED,
+      _ => 0,
+    };
+    let value = Value::new(tag, ptr as u64 | boxed);
+    self.track(value);
+    Ok(value)
+  }
+
+  /// Size of the object `value` points to, including its contents.
+  pub fn object_size(value: Value) -> usize {
+    let ptr = value.reference();
+    unsafe {
+      match value.tag() {
+        Value::TAG_STRING => {
+          let string = &*(ptr as *const ObjString);
+          size_of::<ObjString>() + string.contents.capacity()
+        }
+        Value::TAG_DICT => {
+          let dict = &*(ptr as *const ObjDict);
+          size_of::<ObjDict>() + dict.fields.len() * size_of::<[Value; 2]>()
+        }
+        Value::TAG_ARRAY => {
+          let array = &*(ptr as *const ObjArray);
+          size_of::<ObjArray>() + array.arr.len() * size_of::<Value>()
+        }
+        Value::TAG_CLASS => {
+          let object = &*(ptr as *const ObjClass);
+          size_of::<ObjClass>() + object.fields.len() * size_of::<Value>()
+        }
+        Value::TAG_FUNCTION => {
+          let function = &*(ptr as *const ObjFunction);
+          size_of::<ObjFunction>() + function.captures.len() * size_of::<Value>()
+        }
+        Value::TAG_BYTES => {
+          let bytes = &*(ptr as *const ObjBytes);
+          size_of::<ObjBytes>() + bytes.bytes.capacity()
+        }
+        Value::TAG_NATIVE => size_of::<ObjNative>(),
+        Value::TAG_WEAK => size_of::<ObjWeak>(),
+        Value::TAG_LONG | Value::TAG_DOUBLE if value.is_boxed() => size_of::<ObjBoxed>(),
+        _ => 0,
+      }
+    }
+  }
 }
 
 impl Gc {
   #[inline(always)]
   pub fn new() -> Self {
-    Self { roots: Vec::new(), marked: HashSet::new() }
+    Self::with_collector(Collector::default())
+  }
+
+  pub fn with_collector(collector: Collector) -> Self {
+    Self {
+      collector,
+      young: HashMap::new(),
+      old: HashMap::new(),
+      ids: HashMap::new(),
+      next_id: 0,
+      remembered: HashSet::new(),
+      marked: HashSet::new(),
+      allocated: 0,
+      old_bytes: 0,
+      heap_bytes: 0,
+      major_threshold: MAJOR_THRESHOLD,
+      pressure: usize::MAX,
+      max_heap: None,
+      nursery_size: NURSERY_SIZE,
+      live: [0; 16],
+      weaks: HashSet::new(),
+      finalizable: HashSet::new(),
+      finalizing: Vec::new(),
+      interned: HashMap::new(),
+      chunks: Vec::new(),
+      to_space: Vec::new(),
+      forwarding: HashMap::new(),
+      phase: Phase::Idle,
+      grey: Vec::new(),
+      scanning: None,
+      sweeping: Vec::new(),
+      unswept: Vec::new(),
+      stats: GcStats::default(),
+    }
   }
 
   #[inline(always)]
-  pub fn class(&mut self, fields: usize, class_ref: *const crate::class::Class) -> Value {
-    let layout = std::alloc::Layout::new::<ObjClass>();
+  pub fn class(&mut self, fields: usize, class_ref: *const crate::class::Class) -> Result<Value> {
+    let contents = fields.saturating_mul(size_of::<Value>());
+    self.reserve("object", contents)?;
     let fields = vec![Value::NULL; fields].into();
-    let ptr = unsafe {
-      let ptr = std::alloc::alloc(layout);
-      ptr.cast::<ObjClass>().write(ObjClass { fields, class_ref });
-      ptr
-    };
-    let addr = ptr as usize;
-    let value = Value::new(Value::TAG_CLASS, addr as u64);
-    self.track(value);
-    value
+    let value = self.alloc(Value::TAG_CLASS, "object", contents, ObjClass { fields, class_ref })?;
+    let object = value.reference() as *const ObjClass;
+    if unsafe { (*(*object).class_ref).finalizer() }.is_some() {
+      self.finalizable.insert(value);
+    }
+    Ok(value)
   }
 
   pub fn get_method(r#ref: Reference, name: &str) -> &crate::function::Function {
@@ -64,7 +418,7 @@ impl Gc {
     let ptr = r#ref as *mut ObjClass;
     unsafe {
       let class_ref = (*ptr).class_ref;
-      let field = &(*class_ref).fields[field_name];
+      let field = &(&(*class_ref).fields)[field_name];
       (*ptr).fields[field.offset as usize] = value;
     }
   }
@@ -80,63 +434,64 @@ impl Gc {
     let ptr = r#ref as *mut ObjClass;
     unsafe {
       let class_ref = (*ptr).class_ref;
-      let field = &(*class_ref).fields[field_name];
+      let field = &(&(*class_ref).fields)[field_name];
       (*ptr).fields[field.offset as usize]
     }
   }
 
+  /// The value of `field`, `None` when the dict has no such key.
   #[inline(always)]
-  pub fn get_dict(r#ref: Reference, field: Value) -> Value {
+  pub fn get_dict(r#ref: Reference, field: Value) -> Option<Value> {
     let ptr = r#ref as *mut ObjDict;
-    unsafe { (*ptr).fields[&field] }
+    unsafe { (*ptr).fields.get(&DictKey(field)).copied() }
   }
 
+  /// Set the value of `field`, returning the previous one.
   #[inline(always)]
-  pub fn set_dict(r#ref: Reference, field: Value, value: Value) {
+  pub fn set_dict(r#ref: Reference, field: Value, value: Value) -> Option<Value> {
     let ptr = r#ref as *mut ObjDict;
-    unsafe { (*ptr).fields.insert(field, value) };
+    unsafe { (*ptr).fields.insert(DictKey(field), value) }
   }
 
   #[inline(always)]
-  pub fn alloc_array(&mut self, size: i32) -> Value {
-    let layout = std::alloc::Layout::new::<ObjArray>();
-    let ptr = unsafe {
-      let ptr = std::alloc::alloc(layout);
-      ptr
-        .cast::<ObjArray>()
-        .write(ObjArray { arr: vec![Value::NULL; size as usize].into_boxed_slice() });
-      ptr
-    };
-    let addr = ptr as usize;
-    let value = Value::new(Value::TAG_ARRAY, addr as u64);
-    self.track(value);
-    value
+  pub fn get_dict_fields<'a>(r#ref: Reference) -> &'a mut BTreeMap<DictKey, Value> {
+    let ptr = r#ref as *mut ObjDict;
+    unsafe { &mut (*ptr).fields }
   }
 
   #[inline(always)]
-  pub fn array_get(r#ref: Reference, index: i32) -> Value {
+  pub fn alloc_array(&mut self, size: i32) -> Result<Value> {
+    let len = usize::try_from(size).map_err(|_| Error::NegativeArraySize(size))?;
+    let contents = len.saturating_mul(size_of::<Value>());
+    self.reserve("array", contents)?;
+    let mut arr = Vec::new();
+    arr.try_reserve_exact(len).map_err(|_| Error::OutOfMemory {
+      kind: "array",
+      size: contents,
+      site: None,
+    })?;
+    arr.resize(len, Value::NULL);
+    self.alloc(Value::TAG_ARRAY, "array", contents, ObjArray { arr: arr.into_boxed_slice() })
+  }
+
+  /// The element at `index`, an error when out of bounds.
+  #[inline(always)]
+  pub fn array_get(r#ref: Reference, index: i32) -> Result<Value> {
     let ptr = r#ref as *mut ObjArray;
-    let index = index as usize;
-    unsafe {
-      if index > (*ptr).arr.len() {
-        panic!()
-      } else {
-        (*ptr).arr[index]
-      }
-    }
+    let arr = unsafe { &(*ptr).arr };
+    let element = usize::try_from(index).ok().and_then(|index| arr.get(index));
+    element.copied().ok_or(Error::IndexOutOfBounds { index: index as i64, len: arr.len() })
   }
 
+  /// Set the element at `index`, an error when out of bounds.
   #[inline(always)]
-  pub fn array_set(r#ref: Reference, index: i32, value: Value) {
+  pub fn array_set(r#ref: Reference, index: i32, value: Value) -> Result<()> {
     let ptr = r#ref as *mut ObjArray;
-    let index = index as usize;
-    unsafe {
-      if index > (*ptr).arr.len() {
-        panic!()
-      } else {
-        (*ptr).arr[index] = value;
-      }
-    }
+    let arr = unsafe { &mut (*ptr).arr };
+    let len = arr.len();
+    let element = usize::try_from(index).ok().and_then(|index| arr.get_mut(index));
+    *element.ok_or(Error::IndexOutOfBounds { index: index as i64, len })? = value;
+    Ok(())
   }
 
   pub(crate) fn call_method(
@@ -152,33 +507,126 @@ impl Gc {
   }
 
   #[inline(always)]
-  pub(crate) fn alloc_string(&mut self, s: String) -> Value {
-    let layout = std::alloc::Layout::new::<ObjString>();
-    let ptr = unsafe {
-      let ptr = std::alloc::alloc(layout);
-      ptr.cast::<ObjString>().write(ObjString { contents: s });
-      ptr
-    };
+  pub(crate) fn alloc_string(&mut self, s: String) -> Result<Value> {
+    let contents = s.capacity();
+    self.alloc(Value::TAG_STRING, "string", contents, ObjString { contents: s })
+  }
 
-    let addr = ptr as usize;
-    let value = Value::new(Value::TAG_STRING, addr as u64);
-    self.track(value);
-    value
+  /// The interned string with contents `s`, allocated the first time.
+  pub fn intern(&mut self, s: &str) -> Result<Value> {
+    if let Some(value) = self.interned.get(s) {
+      return Ok(*value);
+    }
+    let value = self.alloc_string(s.to_string())?;
+    self.interned.insert(s.to_string(), value);
+    Ok(value)
+  }
+
+  /// The interned strings, live as long as the runtime.
+  pub(crate) fn interned(&self) -> impl Iterator<Item = &Value> {
+    self.interned.values()
+  }
+
+  /// Forget the interned strings, so the next collection can free them.
+  pub(crate) fn clear_interned(&mut self) {
+    self.interned.clear();
   }
 
   #[inline(always)]
-  pub(crate) fn alloc_dict(&mut self) -> Value {
-    let layout = std::alloc::Layout::new::<ObjDict>();
-    let ptr = unsafe {
-      let ptr = std::alloc::alloc(layout);
-      ptr.cast::<ObjDict>().write(ObjDict { fields: BTreeMap::new() });
-      ptr
-    };
+  pub(crate) fn alloc_function(
+    &mut self,
+    module: *const crate::module::Module,
+    function: *const crate::function::Function,
+    captures: Box<[Value]>,
+  ) -> Result<Value> {
+    let contents = captures.len() * size_of::<Value>();
+    // A new object is black while marking, the captures are not scanned through it.
+    if self.phase.marking() {
+      captures.iter().for_each(|capture| self.shade(*capture));
+    }
+    self.alloc(
+      Value::TAG_FUNCTION,
+      "function",
+      contents,
+      ObjFunction { module, function, captures },
+    )
+  }
 
-    let addr = ptr as usize;
-    let value = Value::new(Value::TAG_DICT, addr as u64);
-    self.track(value);
-    value
+  #[inline(always)]
+  pub fn alloc_bytes(&mut self, bytes: Vec<u8>) -> Result<Value> {
+    let contents = bytes.capacity();
+    self.alloc(Value::TAG_BYTES, "bytes", contents, ObjBytes { bytes })
+  }
+
+  #[inline(always)]
+  pub fn get_string<'a>(r#ref: Reference) -> &'a str {
+    let ptr = r#ref as *const ObjString;
+    unsafe { &(*ptr).contents }
+  }
+
+  #[inline(always)]
+  pub fn get_bytes<'a>(r#ref: Reference) -> &'a mut Vec<u8> {
+    let ptr = r#ref as *mut ObjBytes;
+    unsafe { &mut (*ptr).bytes }
+  }
+
+  /// Allocate a native object owning `data`, dropped when the object is released or collected.
+  pub fn alloc_native<T: Any>(&mut self, type_name: &'static str, data: T) -> Result<Value> {
+    self.alloc_native_with_drop(type_name, data, std::mem::drop)
+  }
+
+  /// Allocate a native object owning `data`, `on_drop` runs when the object is released or
+  /// collected.
+  pub fn alloc_native_with_drop<T: Any>(
+    &mut self,
+    type_name: &'static str,
+    data: T,
+    on_drop: impl FnOnce(T) + 'static,
+  ) -> Result<Value> {
+    let on_drop: NativeDrop = Box::new(move |data| on_drop(*data.downcast::<T>().unwrap()));
+    let object = ObjNative { type_name, data: Some(Box::new(data)), on_drop: Some(on_drop) };
+    self.alloc(Value::TAG_NATIVE, "native", 0, object)
+  }
+
+  /// The data of a native object, `None` if it was released or is not a `T`.
+  pub fn get_native<'a, T: Any>(r#ref: Reference) -> Option<&'a mut T> {
+    let ptr = r#ref as *mut ObjNative;
+    unsafe { (*ptr).data.as_mut()?.downcast_mut() }
+  }
+
+  /// Release a native object now, returns `false` if it was already released.
+  pub fn release_native(r#ref: Reference) -> bool {
+    let ptr = r#ref as *mut ObjNative;
+    unsafe { (*ptr).release() }
+  }
+
+  #[inline(always)]
+  pub(crate) fn alloc_dict(&mut self) -> Result<Value> {
+    self.alloc(Value::TAG_DICT, "dict", 0, ObjDict { fields: BTreeMap::new() })
+  }
+
+  /// Allocate a weak reference to `target`, which does not keep it alive.
+  pub fn alloc_weak(&mut self, target: Value) -> Result<Value> {
+    let value = self.alloc(Value::TAG_WEAK, "weak", 0, ObjWeak { target })?;
+    self.weaks.insert(value);
+    Ok(value)
+  }
+
+  /// Box `long` on the heap, for longs too wide for a value, see [`Value::try_mk_long`].
+  pub fn alloc_long(&mut self, long: i64) -> Result<Value> {
+    self.alloc(Value::TAG_LONG, "long", 0, ObjBoxed { bits: long as u64 })
+  }
+
+  /// Box `double` on the heap, for doubles too precise for a value, see
+  /// [`Value::try_mk_double`].
+  pub fn alloc_double(&mut self, double: f64) -> Result<Value> {
+    self.alloc(Value::TAG_DOUBLE, "double", 0, ObjBoxed { bits: double.to_bits() })
+  }
+
+  /// The target of a weak reference, `null` once it was collected.
+  pub fn get_weak(r#ref: Reference) -> Value {
+    let ptr = r#ref as *const ObjWeak;
+    unsafe { (*ptr).target }
   }
 }
 
@@ -195,7 +643,60 @@ pub struct ObjString {
 
 #[derive(Debug)]
 pub struct ObjDict {
-  pub fields: BTreeMap<Value, Value>,
+  pub fields: BTreeMap<DictKey, Value>,
+}
+
+/// A dict key. Strings are equal when their contents are, longs and doubles when their values are,
+/// other values when they are identical.
+#[derive(Clone, Copy)]
+#[repr(transparent)]
+pub struct DictKey(pub Value);
+
+impl DictKey {
+  fn contents(&self) -> Option<&str> {
+    match self.0.tag() {
+      Value::TAG_STRING => Some(unsafe { &(*(self.0.reference() as *const ObjString)).contents }),
+      _ => None,
+    }
+  }
+}
+
+impl Ord for DictKey {
+  fn cmp(&self, other: &Self) -> Ordering {
+    match (self.0.tag(), other.0.tag()) {
+      (Value::TAG_STRING, Value::TAG_STRING) => self.contents().cmp(&other.contents()),
+      // Two boxes of the same number are distinct values.
+      (Value::TAG_LONG, Value::TAG_LONG) => self.0.long().cmp(&other.0.long()),
+      (Value::TAG_DOUBLE, Value::TAG_DOUBLE) => {
+        self.0.double().to_bits().cmp(&other.0.double().to_bits())
+      }
+      // The tag comes first, values of different types sort by type.
+      _ => self.0.cmp(&other.0),
+    }
+  }
+}
+
+impl PartialOrd for DictKey {
+  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+impl PartialEq for DictKey {
+  fn eq(&self, other: &Self) -> bool {
+    self.cmp(other) == Ordering::Equal
+  }
+}
+
+impl Eq for DictKey {}
+
+impl fmt::Debug for DictKey {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self.contents() {
+      Some(contents) => write!(f, "{contents:?}"),
+      None => write!(f, "{:?}", self.0),
+    }
+  }
 }
 
 #[derive(Debug)]
@@ -203,19 +704,74 @@ pub struct ObjArray {
   pub arr: Box<[Value]>,
 }
 
-// #[derive(Debug)]
-// pub struct ObjBytes {
-//   pub bytes: Vec<u8>,
-// }
+/// A function value, with the values captured when it was created.
+#[derive(Debug)]
+pub struct ObjFunction {
+  pub(crate) module: *const crate::module::Module,
+  pub(crate) function: *const crate::function::Function,
+  pub captures: Box<[Value]>,
+}
+
+#[derive(Debug)]
+pub struct ObjBytes {
+  pub bytes: Vec<u8>,
+}
+
+/// A long or a double boxed on the heap, read by [`Value::long`] and [`Value::double`].
+#[derive(Debug)]
+#[repr(transparent)]
+pub struct ObjBoxed {
+  pub bits: u64,
+}
+
+/// A reference that does not keep its target alive.
+#[derive(Debug)]
+pub struct ObjWeak {
+  pub target: Value,
+}
+
+/// Hook releasing the data of a native object.
+pub type NativeDrop = Box<dyn FnOnce(Box<dyn Any>)>;
+
+/// An opaque host object, like a socket or a file.
+pub struct ObjNative {
+  pub type_name: &'static str,
+  pub(crate) data: Option<Box<dyn Any>>,
+  pub(crate) on_drop: Option<NativeDrop>,
+}
+
+impl ObjNative {
+  fn release(&mut self) -> bool {
+    match (self.data.take(), self.on_drop.take()) {
+      (Some(data), Some(on_drop)) => {
+        on_drop(data);
+        true
+      }
+      _ => false,
+    }
+  }
+}
+
+impl Drop for ObjNative {
+  fn drop(&mut self) {
+    self.release();
+  }
+}
+
+impl fmt::Debug for ObjNative {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "native({})", self.type_name)
+  }
+}
 
 impl ObjDict {
   pub fn refs(&self) -> BTreeSet<&Value> {
     let mut set = BTreeSet::new();
-    for (key, value) in self.fields.iter() {
-      if key.is_not_null() {
+    for (DictKey(key), value) in self.fields.iter() {
+      if key.is_reference() {
         set.insert(key);
       }
-      if value.is_not_null() {
+      if value.is_reference() {
         set.insert(value);
       }
     }
@@ -225,13 +781,28 @@ impl ObjDict {
 
 impl ObjArray {
   pub fn refs(&self) -> BTreeSet<&Value> {
-    self.arr.iter().filter_map(|v| if v.is_not_null() { Some(v) } else { None }).collect()
+    self.arr.iter().filter(|v| v.is_reference()).collect()
   }
 }
 
 impl ObjClass {
   pub fn refs(&self) -> BTreeSet<&Value> {
-    self.fields.iter().filter_map(|v| if v.is_not_null() { Some(v) } else { None }).collect()
+    self.fields.iter().filter(|v| v.is_reference()).collect()
+  }
+}
+
+impl ObjFunction {
+  pub fn refs(&self) -> BTreeSet<&Value> {
+    self.captures.iter().filter(|v| v.is_reference()).collect()
+  }
+}
+
+impl Drop for Gc {
+  fn drop(&mut self) {
+    let objects = self.young.drain().chain(self.old.drain()).collect::<Vec<_>>();
+    for (value, _) in objects {
+      unsafe { self.free(value) };
+    }
   }
 }
 
@@ -240,21 +811,3 @@ impl Default for Gc {
     Self::new()
   }
 }
-
-// impl ObjectType {
-//   pub fn refs(&self) -> BTreeSet<Reference> {
-//     match self {
-//       ObjectType::Dict(map) => BTreeSet::new(),
-//       ObjectType::Array(arr) => BTreeSet::new(),
-//       ObjectType::Class(class) => class
-//         .fields
-//         .iter()
-//         .filter_map(|v| if v.is_not_null() { Some(v.reference()) } else { None })
-//         .collect(),
-//       ObjectType::Null
-//       | ObjectType::String(..)
-//       | ObjectType::Bytes(..)
-//       | ObjectType::Native(..) => BTreeSet::new(),
-//     }
-//   }
-// }
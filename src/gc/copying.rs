@@ -0,0 +1,278 @@
+use std::{
+  alloc::Layout,
+  collections::{HashMap, HashSet},
+  time::Instant,
+};
+
+use crate::{
+  runtime::{Error, Result},
+  value::{Value, BOXED},
+};
+
+use super::{
+  roots::RootSet, DictKey, Gc, ObjArray, ObjBoxed, ObjBytes, ObjClass, ObjDict, ObjFunction,
+  ObjNative, ObjString, ObjWeak,
+};
+
+/// Size of the regions objects are bump allocated in.
+pub const CHUNK_SIZE: usize = 1 << 20;
+
+/// A region of a semi-space, filled from its start.
+#[derive(Debug)]
+pub(crate) struct Chunk {
+  base: *mut u8,
+  top: usize,
+}
+
+impl Chunk {
+  const LAYOUT: Layout = match Layout::from_size_align(CHUNK_SIZE, 16) {
+    Ok(layout) => layout,
+    Err(_) => panic!(),
+  };
+
+  fn new() -> Option<Self> {
+    let base = unsafe { std::alloc::alloc(Self::LAYOUT) };
+    (!base.is_null()).then_some(Self { base, top: 0 })
+  }
+
+  fn contains(&self, ptr: usize) -> bool {
+    (self.base as usize..self.base as usize + CHUNK_SIZE).contains(&ptr)
+  }
+}
+
+impl Drop for Chunk {
+  fn drop(&mut self) {
+    unsafe { std::alloc::dealloc(self.base, Self::LAYOUT) };
+  }
+}
+
+impl Gc {
+  /// Bump allocate `layout` in the current chunk, or in a new one when it is full, taken from the
+  /// reserved to-space during a collection.
+  pub(crate) fn bump(&mut self, layout: Layout) -> *mut u8 {
+    let fits = |chunk: &Chunk| chunk.top.next_multiple_of(layout.align()) + layout.size();
+    if self.chunks.last().is_none_or(|chunk| fits(chunk) > CHUNK_SIZE) {
+      match self.to_space.pop().or_else(Chunk::new) {
+        Some(chunk) => self.chunks.push(chunk),
+        None => return std::ptr::null_mut(),
+      }
+    }
+    let chunk = self.chunks.last_mut().unwrap();
+    let offset = chunk.top.next_multiple_of(layout.align());
+    chunk.top = offset + layout.size();
+    unsafe { chunk.base.add(offset) }
+  }
+
+  /// Copy the objects reachable from the roots to a new semi-space and free the old one.
+  ///
+  /// Pinned roots stay in place with the chunks holding them, the other references are updated
+  /// through the forwarding table, see [`Gc::relocate`].
+  ///
+  /// The to-space is allocated first, so a collection fails with [`Error::OutOfMemory`] before
+  /// moving anything when it can't be.
+  pub fn collect_copying(&mut self, roots: &RootSet) -> Result<()> {
+    let start = Instant::now();
+    self.reserve_to_space()?;
+    let from = std::mem::take(&mut self.chunks);
+    let objects = std::mem::take(&mut self.young);
+    self.forwarding.clear();
+
+    let mut pending = Vec::new();
+    let mut pinned = HashSet::new();
+    for root in roots.pinned() {
+      if objects.contains_key(root) && self.forwarding.insert(*root, *root).is_none() {
+        pinned.extend(from.iter().position(|chunk| chunk.contains(root.reference())));
+        pending.push(*root);
+      }
+    }
+    // The runtime moves its roots afterwards, see `Gc::relocate`.
+    for root in roots.values() {
+      let _ = self.evacuate(*root, &objects, &mut pending);
+    }
+    for object in std::mem::take(&mut self.finalizing) {
+      let object = self.evacuate(object, &objects, &mut pending);
+      self.finalizing.push(object);
+    }
+    self.scan(&objects, &mut pending);
+
+    // Clear the weak references to unreachable objects, before finalizers resurrect them.
+    for weak in &self.weaks {
+      let object = self.forwarding.get(weak).unwrap_or(weak).reference() as *mut ObjWeak;
+      let target = unsafe { (*object).target };
+      if target.is_reference() {
+        unsafe { (*object).target = self.forwarding.get(&target).copied().unwrap_or(Value::NULL) };
+      }
+    }
+
+    let dead = self
+      .finalizable
+      .iter()
+      .filter(|object| !self.forwarding.contains_key(object))
+      .copied()
+      .collect::<Vec<_>>();
+    for object in dead {
+      self.finalizable.remove(&object);
+      let object = self.evacuate(object, &objects, &mut pending);
+      self.finalizing.push(object);
+    }
+    self.scan(&objects, &mut pending);
+
+    self.weaks = self.weaks.iter().filter_map(|weak| self.forwarding.get(weak)).copied().collect();
+    self.finalizable =
+      self.finalizable.iter().filter_map(|object| self.forwarding.get(object)).copied().collect();
+
+    let mut live = HashMap::with_capacity(objects.len());
+    for (object, size) in objects {
+      match self.forwarding.get(&object) {
+        Some(moved) => {
+          live.insert(*moved, size);
+          if let Some(id) = self.ids.remove(&object) {
+            self.ids.insert(*moved, id);
+          }
+        }
+        None => self.release(object, size),
+      }
+    }
+    self.young = live;
+
+    // Keep the chunks of pinned objects, filled so nothing is allocated over their dead objects.
+    let mut kept = from
+      .into_iter()
+      .enumerate()
+      .filter(|(index, _)| pinned.contains(index))
+      .map(|(_, mut chunk)| {
+        chunk.top = CHUNK_SIZE;
+        chunk
+      })
+      .collect::<Vec<_>>();
+    kept.append(&mut self.chunks);
+    self.chunks = kept;
+    self.to_space.clear();
+
+    self.old_bytes = self.heap_bytes;
+    self.allocated = 0;
+    self.update_pressure();
+    self.stats.major_collections += 1;
+    self.record(start);
+    Ok(())
+  }
+
+  /// Allocate enough chunks to copy every object, whatever order they are copied in.
+  fn reserve_to_space(&mut self) -> Result<()> {
+    let footprints = self.young.keys().map(|object| {
+      let layout = Self::layout(*object);
+      layout.size() + layout.align() - 1
+    });
+    let (total, largest) =
+      footprints.fold((0, 0), |(total, largest), size| (total + size, largest.max(size)));
+    // A chunk can't fit the next object past `CHUNK_SIZE - largest` bytes.
+    let chunks = total.div_ceil(CHUNK_SIZE - largest);
+    while self.to_space.len() < chunks {
+      let Some(chunk) = Chunk::new() else {
+        let size = (chunks - self.to_space.len()) * CHUNK_SIZE;
+        self.to_space.clear();
+        return Err(Error::OutOfMemory { kind: "copying collection", size, site: None });
+      };
+      self.to_space.push(chunk);
+    }
+    Ok(())
+  }
+
+  /// Update the root slots `values` and the interned strings to the new addresses of the objects
+  /// moved by the last collection, then forget the forwarding table. Every root must be given in
+  /// one call.
+  pub fn relocate<'a>(&mut self, values: impl IntoIterator<Item = &'a mut Value>) {
+    if self.forwarding.is_empty() {
+      return;
+    }
+    let forwarding = &self.forwarding;
+    let relocate = |value: &mut Value| {
+      if let Some(moved) = forwarding.get(value) {
+        *value = *moved;
+      }
+    };
+    values.into_iter().for_each(relocate);
+    self.interned.values_mut().for_each(relocate);
+    self.forwarding.clear();
+  }
+
+  /// Copy the object `value` points to, unless it was already, and return its new address.
+  fn evacuate(
+    &mut self,
+    value: Value,
+    objects: &HashMap<Value, usize>,
+    pending: &mut Vec<Value>,
+  ) -> Value {
+    if !value.is_reference() {
+      return value;
+    }
+    if let Some(moved) = self.forwarding.get(&value) {
+      return *moved;
+    }
+    if !objects.contains_key(&value) {
+      return value;
+    }
+
+    let layout = Self::layout(value);
+    // The to-space was reserved, see `Gc::reserve_to_space`.
+    let ptr = self.bump(layout);
+    unsafe { std::ptr::copy_nonoverlapping(value.reference() as *const u8, ptr, layout.size()) };
+
+    let moved = Value::new(value.tag(), ptr as u64 | (value.0 & BOXED));
+    self.forwarding.insert(value, moved);
+    pending.push(moved);
+    moved
+  }
+
+  /// Evacuate the objects referenced by the copied objects, updating their fields.
+  fn scan(&mut self, objects: &HashMap<Value, usize>, pending: &mut Vec<Value>) {
+    while let Some(object) = pending.pop() {
+      Self::update_refs(object, |value| self.evacuate(value, objects, pending));
+    }
+  }
+
+  /// Replace each reference held by the object `value` points to with `f` of it.
+  fn update_refs(value: Value, mut f: impl FnMut(Value) -> Value) {
+    let mut update = |slot: &mut Value| {
+      if slot.is_reference() {
+        *slot = f(*slot);
+      }
+    };
+    let ptr = value.reference();
+    unsafe {
+      match value.tag() {
+        Value::TAG_DICT => {
+          let dict = &mut *(ptr as *mut ObjDict);
+          dict.fields = std::mem::take(&mut dict.fields)
+            .into_iter()
+            .map(|(DictKey(mut key), mut value)| {
+              update(&mut key);
+              update(&mut value);
+              (DictKey(key), value)
+            })
+            .collect();
+        }
+        Value::TAG_ARRAY => (*(ptr as *mut ObjArray)).arr.iter_mut().for_each(update),
+        Value::TAG_CLASS => (*(ptr as *mut ObjClass)).fields.iter_mut().for_each(update),
+        Value::TAG_FUNCTION => (*(ptr as *mut ObjFunction)).captures.iter_mut().for_each(update),
+        _ => (),
+      }
+    }
+  }
+
+  /// Layout of the object `value` points to, without its contents.
+  fn layout(value: Value) -> Layout {
+    match value.tag() {
+      Value::TAG_STRING => Layout::new::<ObjString>(),
+      Value::TAG_DICT => Layout::new::<ObjDict>(),
+      Value::TAG_ARRAY => Layout::new::<ObjArray>(),
+      Value::TAG_CLASS => Layout::new::<ObjClass>(),
+      Value::TAG_FUNCTION => Layout::new::<ObjFunction>(),
+      Value::TAG_BYTES => Layout::new::<ObjBytes>(),
+      Value::TAG_NATIVE => Layout::new::<ObjNative>(),
+      Value::TAG_WEAK => Layout::new::<ObjWeak>(),
+      Value::TAG_LONG | Value::TAG_DOUBLE => Layout::new::<ObjBoxed>(),
+      _ => unreachable!(),
+    }
+  }
+}
@@ -0,0 +1,246 @@
+use std::{ops::Bound, time::Instant};
+
+use crate::value::Value;
+
+use super::{roots::RootSet, DictKey, Gc, ObjArray, ObjClass, ObjDict, ObjFunction};
+
+/// Objects and slots scanned, or objects swept, by a slice of the incremental collector.
+pub const INCREMENTAL_BUDGET: usize = 1000;
+
+/// Bytes allocated per object of budget between two slices, so a collection scans and frees
+/// objects faster than they are allocated.
+pub const BYTES_PER_OBJECT: usize = 32;
+
+/// The progress of an incremental collection.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Phase {
+  #[default]
+  Idle,
+  /// Tracing the heap, marked objects are grey while in the grey list and black afterwards.
+  Marking,
+  /// Tracing the objects reachable from the finalizers queued once marking ended.
+  Finalizing,
+  /// Freeing the objects found unreachable.
+  Sweeping,
+}
+
+impl Phase {
+  /// Whether stored values are shaded and new objects allocated black.
+  pub(super) fn marking(self) -> bool {
+    matches!(self, Phase::Marking | Phase::Finalizing)
+  }
+}
+
+/// Where the scan of a grey object resumes.
+#[derive(Debug, Clone, Copy)]
+pub(super) enum Cursor {
+  Start,
+  /// The slot at this index of an array, instance or function.
+  Slot(usize),
+  /// The dict entry after this key.
+  After(Value),
+}
+
+impl Gc {
+  /// Run a slice of an incremental collection, starting one when idle.
+  ///
+  /// Marking starts from the roots and scans a budget of objects and slots per slice, resuming
+  /// large objects where the previous slice stopped. Stores into objects shade their value grey,
+  /// see [`Gc::write_barrier`], and objects allocated meanwhile are black. Once no object is grey
+  /// the roots are scanned again, marking ends when that finds nothing new. The unreachable
+  /// objects are then freed a budget per slice.
+  pub fn collect_slice(&mut self, roots: &RootSet) {
+    let start = Instant::now();
+    if self.phase == Phase::Idle {
+      self.start_marking(roots);
+    }
+    let work = self.step(roots, self.budget());
+    self.stats.max_slice_work = self.stats.max_slice_work.max(work);
+    self.allocated = 0;
+    self.stats.slices += 1;
+    self.record(start);
+  }
+
+  /// Run the current incremental collection to its end, then a whole one unless it was marking.
+  ///
+  /// This is a single pause going through the same steps as the slices, run when a program asks
+  /// for a collection or an allocation does not fit.
+  pub fn collect_full(&mut self, roots: &RootSet) {
+    let start = Instant::now();
+    // The objects allocated since marking ended are only found by marking again.
+    while self.phase == Phase::Sweeping {
+      self.step(roots, self.budget());
+    }
+    if self.phase == Phase::Idle {
+      self.start_marking(roots);
+    }
+    while self.phase != Phase::Idle {
+      self.step(roots, self.budget());
+    }
+    self.allocated = 0;
+    self.stats.slices += 1;
+    self.record(start);
+  }
+
+  /// Mark `value` grey if it is white.
+  #[inline(always)]
+  pub(super) fn shade(&mut self, value: Value) {
+    if value.is_reference() && self.marked.insert(value) {
+      self.grey.push(value);
+    }
+  }
+
+  fn start_marking(&mut self, roots: &RootSet) {
+    self.phase = Phase::Marking;
+    self.shade_roots(roots);
+  }
+
+  fn shade_roots(&mut self, roots: &RootSet) {
+    for root in roots.values().iter().chain(&self.finalizing.clone()) {
+      self.shade(*root);
+    }
+  }
+
+  /// Advance the collection by up to `budget` of work, returning the work done.
+  fn step(&mut self, roots: &RootSet, budget: usize) -> usize {
+    let work = match self.phase {
+      Phase::Idle => 0,
+      Phase::Marking | Phase::Finalizing => self.mark_step(budget),
+      Phase::Sweeping => self.sweep_step(budget),
+    };
+    if self.phase == Phase::Marking && self.scanned_all() {
+      // Stores into the roots have no barrier, what they gained is marked from them.
+      self.shade_roots(roots);
+      if self.scanned_all() {
+        self.queue_dead_finalizers();
+      }
+    }
+    if self.phase == Phase::Finalizing && self.scanned_all() {
+      self.start_sweep();
+    }
+    work
+  }
+
+  /// Whether no object is left to scan.
+  fn scanned_all(&self) -> bool {
+    self.grey.is_empty() && self.scanning.is_none()
+  }
+
+  /// Scan grey objects until `budget` objects and slots were scanned, shading the objects they
+  /// reference. An object left partly scanned is resumed by the next step.
+  fn mark_step(&mut self, budget: usize) -> usize {
+    let mut work = 0;
+    while work < budget {
+      let (object, from) = match self.scanning.take() {
+        Some(scanning) => scanning,
+        None => match self.grey.pop() {
+          Some(object) => {
+            work += 1;
+            (object, Cursor::Start)
+          }
+          None => break,
+        },
+      };
+      let (scanned, rest) = self.scan_grey(object, from, budget - work);
+      work += scanned;
+      self.scanning = rest.map(|cursor| (object, cursor));
+    }
+    work
+  }
+
+  /// Shade the values of `object` from `from` until `budget` slots were scanned, returning the
+  /// slots scanned and where to resume when some are left.
+  fn scan_grey(&mut self, object: Value, from: Cursor, budget: usize) -> (usize, Option<Cursor>) {
+    let ptr = object.reference();
+    let slots: &[Value] = unsafe {
+      match object.tag() {
+        Value::TAG_DICT => return self.scan_dict(&*(ptr as *const ObjDict), from, budget),
+        Value::TAG_ARRAY => &(*(ptr as *const ObjArray)).arr,
+        Value::TAG_CLASS => &(*(ptr as *const ObjClass)).fields,
+        Value::TAG_FUNCTION => &(*(ptr as *const ObjFunction)).captures,
+        _ => return (0, None),
+      }
+    };
+    let start = match from {
+      Cursor::Slot(index) => index,
+      _ => 0,
+    };
+    let end = slots.len().min(start.saturating_add(budget));
+    for slot in &slots[start..end] {
+      self.shade(*slot);
+    }
+    (end - start, (end < slots.len()).then_some(Cursor::Slot(end)))
+  }
+
+  /// Like [`Gc::scan_grey`] for the entries of a dict, resuming after the last key scanned so entries
+  /// added or removed meanwhile don't move the cursor.
+  fn scan_dict(&mut self, dict: &ObjDict, from: Cursor, budget: usize) -> (usize, Option<Cursor>) {
+    let entries = match from {
+      Cursor::After(key) => dict.fields.range((Bound::Excluded(DictKey(key)), Bound::Unbounded)),
+      _ => dict.fields.range(..),
+    };
+    let mut work = 0;
+    let mut last = None;
+    for (DictKey(key), value) in entries {
+      if work == budget {
+        return (work, Some(last.map_or(from, Cursor::After)));
+      }
+      self.shade(*key);
+      self.shade(*value);
+      work += 1;
+      last = Some(*key);
+    }
+    (work, None)
+  }
+
+  /// Clear the weak references to unmarked objects, then queue the finalizers of the unmarked
+  /// finalizable objects and mark what they reference.
+  fn queue_dead_finalizers(&mut self) {
+    self.clear_weaks(false);
+    let dead = self
+      .finalizable
+      .iter()
+      .filter(|object| !self.marked.contains(object))
+      .copied()
+      .collect::<Vec<_>>();
+    for object in dead {
+      self.finalizable.remove(&object);
+      self.finalizing.push(object);
+      self.shade(object);
+    }
+    self.phase = Phase::Finalizing;
+  }
+
+  /// Leave every object allocated so far to sweep, without copying them.
+  fn start_sweep(&mut self) {
+    self.sweeping = std::mem::take(&mut self.unswept);
+    self.old_bytes = 0;
+    self.phase = Phase::Sweeping;
+    self.stats.major_collections += 1;
+  }
+
+  /// Sweep up to `budget` objects, freeing the unmarked ones and keeping the others for the next
+  /// collection, ending this one once none is left. Returns the objects swept.
+  fn sweep_step(&mut self, budget: usize) -> usize {
+    let mut work = 0;
+    while work < budget {
+      let Some(object) = self.sweeping.pop() else {
+        break;
+      };
+      work += 1;
+      if self.marked.contains(&object) {
+        self.old_bytes += self.young[&object];
+        self.unswept.push(object);
+      } else {
+        let size = self.young.remove(&object).unwrap();
+        self.release(object, size);
+      }
+    }
+    if self.sweeping.is_empty() {
+      self.marked.clear();
+      self.phase = Phase::Idle;
+      self.update_pressure();
+    }
+    work
+  }
+}
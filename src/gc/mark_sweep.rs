@@ -1,62 +1,219 @@
-use crate::{local::Local, stack::Stack};
+use std::time::Instant;
 
-use super::{Gc, ObjArray, ObjClass, ObjDict, ObjString};
+use crate::{runtime::Result, value::Value};
+
+use super::{
+  roots::RootSet, Collector, Gc, ObjArray, ObjBoxed, ObjBytes, ObjClass, ObjDict, ObjFunction,
+  ObjNative, ObjString, ObjWeak, MAJOR_THRESHOLD,
+};
 
 impl Gc {
-  pub fn mark_sweep<const SIZE: usize>(&mut self, local: &Local, stack: &Stack<SIZE>) {
-    let mut stack_and_local = stack.iter().chain(local.iter()).collect::<Vec<_>>();
-    while let Some(value) = stack_and_local.pop() {
-      match value.tag() {
-        crate::value::Value::TAG_STRING => _ = self.mark(*value),
-        crate::value::Value::TAG_DICT => {
-          self.mark(*value);
-          let ptr = value.reference() as *mut ObjDict;
-
-          let refs = unsafe { (*ptr).refs() };
-          stack_and_local.extend(refs);
-        }
-        crate::value::Value::TAG_ARRAY => {
-          self.mark(*value);
-          let ptr = value.reference() as *mut ObjArray;
-
-          let refs = unsafe { (*ptr).refs() };
-          stack_and_local.extend(refs);
-        }
-        crate::value::Value::TAG_CLASS => {
-          self.mark(*value);
-          let ptr = value.reference() as *mut ObjClass;
-
-          let refs = unsafe { (*ptr).refs() };
-          stack_and_local.extend(refs);
-        }
-        _ => (),
-      }
-    }
-
-    self.roots.retain(|root| {
-      if self.marked.contains(root) {
-        return true;
-      }
-
-      match root.tag() {
-        crate::value::Value::TAG_STRING => unsafe {
-          std::alloc::dealloc(root.reference() as *mut _, std::alloc::Layout::new::<ObjString>());
-        },
-        crate::value::Value::TAG_DICT => unsafe {
-          std::alloc::dealloc(root.reference() as *mut _, std::alloc::Layout::new::<ObjDict>());
-        },
-        crate::value::Value::TAG_ARRAY => unsafe {
-          std::alloc::dealloc(root.reference() as *mut _, std::alloc::Layout::new::<ObjArray>());
-        },
-        crate::value::Value::TAG_CLASS => unsafe {
-          std::alloc::dealloc(root.reference() as *mut _, std::alloc::Layout::new::<ObjClass>())
-        },
-        _ => unreachable!(),
-      }
-
-      return false;
-    });
+  /// Run a minor collection, followed by a major one when the old generation outgrew its
+  /// threshold.
+  ///
+  /// Only a copying collection can fail, when its to-space can't be allocated.
+  pub fn collect(&mut self, roots: &RootSet) -> Result<()> {
+    match self.collector {
+      Collector::Copying => return self.collect_copying(roots),
+      Collector::Incremental { .. } => {
+        self.collect_slice(roots);
+        return Ok(());
+      }
+      Collector::Generational => (),
+    }
+    self.collect_minor(roots)?;
+    if self.old_bytes >= self.major_threshold || self.heap_bytes >= self.pressure {
+      self.collect_major(roots)?;
+    }
+    Ok(())
+  }
+
+  /// Collect the nursery, tracing from the roots and the remembered old objects, and promote the
+  /// survivors.
+  pub fn collect_minor(&mut self, roots: &RootSet) -> Result<()> {
+    match self.collector {
+      Collector::Copying => return self.collect_copying(roots),
+      Collector::Incremental { .. } => {
+        self.collect_slice(roots);
+        return Ok(());
+      }
+      Collector::Generational => (),
+    }
+    let start = Instant::now();
+    let mut pending = roots.values().to_vec();
+    pending.extend(&self.finalizing);
+    for object in std::mem::take(&mut self.remembered) {
+      pending.extend(Self::refs(object));
+    }
+    self.trace(pending, true);
+    self.clear_weaks(true);
+    self.queue_finalizers(true);
+    self.promote();
+
+    self.stats.minor_collections += 1;
+    self.record(start);
+    Ok(())
+  }
+
+  /// Collect both generations, tracing the whole heap from the roots.
+  pub fn collect_major(&mut self, roots: &RootSet) -> Result<()> {
+    match self.collector {
+      Collector::Copying => return self.collect_copying(roots),
+      Collector::Incremental { .. } => {
+        self.collect_full(roots);
+        return Ok(());
+      }
+      Collector::Generational => (),
+    }
+    let start = Instant::now();
+    let mut pending = roots.values().to_vec();
+    pending.extend(&self.finalizing);
+    self.trace(pending, false);
+    self.clear_weaks(false);
+    self.queue_finalizers(false);
+
+    let dead =
+      self.old.keys().filter(|old| !self.marked.contains(old)).copied().collect::<Vec<_>>();
+    for old in dead {
+      let size = self.old.remove(&old).unwrap();
+      self.old_bytes -= size;
+      self.release(old, size);
+    }
+    self.remembered.clear();
+    self.promote();
+
+    self.major_threshold = MAJOR_THRESHOLD.max(self.old_bytes * 2);
+    self.stats.major_collections += 1;
+    self.record(start);
+    Ok(())
+  }
+
+  /// Record the pause of a collection started at `start` and the heap it left.
+  pub(super) fn record(&mut self, start: Instant) {
+    let pause = start.elapsed();
+    self.stats.last_pause = pause;
+    self.stats.max_pause = self.stats.max_pause.max(pause);
+    self.stats.total_pause += pause;
+    self.stats.heap_bytes = self.heap_bytes;
+    self.stats.live = (0..self.live.len())
+      .filter(|tag| self.live[*tag] > 0)
+      .map(|tag| (Value::new(tag as u64, 0).type_name(), self.live[tag]))
+      .collect();
+  }
+
+  /// Whether the collection frees `value`, only young objects are collected when `minor`.
+  fn is_dead(&self, value: Value, minor: bool) -> bool {
+    value.is_reference()
+      && (!minor || self.young.contains_key(&value))
+      && !self.marked.contains(&value)
+  }
+
+  /// Clear the weak references to the objects the collection frees.
+  pub(super) fn clear_weaks(&mut self, minor: bool) {
+    for weak in &self.weaks {
+      let ptr = weak.reference() as *mut ObjWeak;
+      if self.is_dead(unsafe { (*ptr).target }, minor) {
+        unsafe { (*ptr).target = Value::NULL };
+      }
+    }
+  }
 
+  /// Queue the unreachable objects with a finalizer, and keep them alive with everything they
+  /// reference until it ran. Each finalizer runs once.
+  fn queue_finalizers(&mut self, minor: bool) {
+    let dead = self
+      .finalizable
+      .iter()
+      .filter(|object| self.is_dead(**object, minor))
+      .copied()
+      .collect::<Vec<_>>();
+    for object in &dead {
+      self.finalizable.remove(object);
+    }
+    self.finalizing.extend(&dead);
+    self.trace(dead, minor);
+  }
+
+  /// Free the unreachable object `value` of `size` bytes.
+  pub(super) fn release(&mut self, value: Value, size: usize) {
+    if value.tag() == Value::TAG_WEAK {
+      self.weaks.remove(&value);
+    }
+    self.ids.remove(&value);
+    self.heap_bytes -= size;
+    self.live[value.tag() as usize] -= 1;
+    self.stats.objects_freed += 1;
+    self.stats.bytes_freed += size;
+    unsafe { self.free(value) };
+  }
+
+  /// Mark the objects reachable from `pending`, only following young objects when `minor`.
+  fn trace(&mut self, mut pending: Vec<Value>, minor: bool) {
+    while let Some(value) = pending.pop() {
+      if !value.is_reference() || (minor && !self.young.contains_key(&value)) || !self.mark(value) {
+        continue;
+      }
+      pending.extend(Self::refs(value));
+    }
+  }
+
+  /// Move the marked young objects to the old generation and free the others.
+  fn promote(&mut self) {
+    for (young, size) in std::mem::take(&mut self.young) {
+      if self.marked.contains(&young) {
+        self.old_bytes += size;
+        self.old.insert(young, size);
+      } else {
+        self.release(young, size);
+      }
+    }
     self.marked.clear();
+    self.allocated = 0;
+    self.update_pressure();
+  }
+
+  /// The references held by the object `value` points to.
+  pub(crate) fn refs(value: Value) -> Vec<Value> {
+    let ptr = value.reference();
+    let refs = unsafe {
+      match value.tag() {
+        Value::TAG_DICT => (*(ptr as *const ObjDict)).refs(),
+        Value::TAG_ARRAY => (*(ptr as *const ObjArray)).refs(),
+        Value::TAG_CLASS => (*(ptr as *const ObjClass)).refs(),
+        Value::TAG_FUNCTION => (*(ptr as *const ObjFunction)).refs(),
+        _ => return Vec::new(),
+      }
+    };
+    refs.into_iter().copied().collect()
+  }
+
+  /// Drop the object `value` points to, and deallocate it unless it lives in a chunk.
+  ///
+  /// # Safety
+  ///
+  /// `value` must be a tracked reference that is no longer used.
+  pub(crate) unsafe fn free(&self, value: Value) {
+    unsafe fn free<T>(ptr: usize, dealloc: bool) {
+      std::ptr::drop_in_place(ptr as *mut T);
+      if dealloc {
+        std::alloc::dealloc(ptr as *mut u8, std::alloc::Layout::new::<T>());
+      }
+    }
+
+    let dealloc = self.collector != Collector::Copying;
+
+    let ptr = value.reference();
+    match value.tag() {
+      Value::TAG_STRING => free::<ObjString>(ptr, dealloc),
+      Value::TAG_DICT => free::<ObjDict>(ptr, dealloc),
+      Value::TAG_ARRAY => free::<ObjArray>(ptr, dealloc),
+      Value::TAG_CLASS => free::<ObjClass>(ptr, dealloc),
+      Value::TAG_FUNCTION => free::<ObjFunction>(ptr, dealloc),
+      Value::TAG_BYTES => free::<ObjBytes>(ptr, dealloc),
+      Value::TAG_NATIVE => free::<ObjNative>(ptr, dealloc),
+      Value::TAG_WEAK => free::<ObjWeak>(ptr, dealloc),
+      Value::TAG_LONG | Value::TAG_DOUBLE => free::<ObjBoxed>(ptr, dealloc),
+      _ => unreachable!(),
+    }
   }
 }
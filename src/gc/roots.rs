@@ -0,0 +1,46 @@
+use crate::value::Value;
+
+/// The values a collection traces from, populated by the runtime before collecting.
+#[derive(Debug, Default)]
+pub struct RootSet {
+  values: Vec<Value>,
+  /// Roots that must not move, a subset of `values`.
+  pinned: Vec<Value>,
+}
+
+impl RootSet {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Add `value`, ignored when it is not a reference.
+  #[inline(always)]
+  pub fn add(&mut self, value: Value) {
+    if value.is_reference() {
+      self.values.push(value);
+    }
+  }
+
+  pub fn extend<'a>(&mut self, values: impl IntoIterator<Item = &'a Value>) {
+    for value in values {
+      self.add(*value);
+    }
+  }
+
+  /// Add the references of `values` as roots a copying collection does not move, like the values
+  /// held by native functions.
+  pub fn pin<'a>(&mut self, values: impl IntoIterator<Item = &'a Value>) {
+    for value in values.into_iter().filter(|value| value.is_reference()) {
+      self.values.push(*value);
+      self.pinned.push(*value);
+    }
+  }
+
+  pub(crate) fn values(&self) -> &[Value] {
+    &self.values
+  }
+
+  pub(crate) fn pinned(&self) -> &[Value] {
+    &self.pinned
+  }
+}
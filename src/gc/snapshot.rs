@@ -0,0 +1,214 @@
+use core::fmt;
+use std::{
+  collections::{BTreeMap, VecDeque},
+  io::{self, BufRead, Write},
+};
+
+use crate::value::Value;
+
+use super::{roots::RootSet, Gc, ObjClass, ObjNative};
+
+/// First line of a snapshot file.
+const HEADER: &str = "grape-heap-snapshot 2";
+
+/// Retention paths printed for each kind that grew.
+const PATHS_PER_KIND: usize = 3;
+
+/// The objects of a heap and the references between them, for leak hunting.
+///
+/// Objects are identified by their allocation sequence number, see [`Gc::id`], which survives
+/// the copying collector moving them and is never reused for a later object.
+///
+/// ```text
+/// grape-heap-snapshot 2
+/// root 2a
+/// object 2a array 40 2b
+/// object 2b string 35
+/// ```
+#[derive(Debug, Default)]
+pub struct Snapshot {
+  pub roots: Vec<u64>,
+  pub objects: BTreeMap<u64, SnapshotObject>,
+}
+
+#[derive(Debug)]
+pub struct SnapshotObject {
+  /// The object type, like `string`, `class(Error)` or `native(TcpStream)`.
+  pub kind: String,
+  pub size: usize,
+  pub refs: Vec<u64>,
+}
+
+impl Snapshot {
+  /// Snapshot the objects of `gc`, reachable from `roots` or not.
+  pub fn capture(gc: &Gc, roots: &RootSet) -> Self {
+    let roots = roots.values().iter().filter_map(|root| gc.id(*root)).collect();
+    let objects = gc
+      .objects()
+      .filter_map(|(value, size)| {
+        let refs = Gc::refs(value).iter().filter_map(|r| gc.id(*r)).collect();
+        Some((gc.id(value)?, SnapshotObject { kind: kind(value), size, refs }))
+      })
+      .collect();
+    Self { roots, objects }
+  }
+
+  pub fn write(&self, w: &mut impl Write) -> io::Result<()> {
+    writeln!(w, "{HEADER}")?;
+    for root in &self.roots {
+      writeln!(w, "root {root:x}")?;
+    }
+    for (id, object) in &self.objects {
+      write!(w, "object {id:x} {} {}", object.kind, object.size)?;
+      for r in &object.refs {
+        write!(w, " {r:x}")?;
+      }
+      writeln!(w)?;
+    }
+    Ok(())
+  }
+
+  pub fn read(r: &mut impl BufRead) -> io::Result<Self> {
+    let invalid =
+      |line: &str| io::Error::new(io::ErrorKind::InvalidData, format!("bad line: {line}"));
+    let id = |word: &str| u64::from_str_radix(word, 16).map_err(|_| invalid(word));
+
+    let mut lines = r.lines();
+    match lines.next().transpose()? {
+      Some(header) if header == HEADER => (),
+      _ => return Err(io::Error::new(io::ErrorKind::InvalidData, "not a heap snapshot")),
+    }
+
+    let mut snapshot = Snapshot::default();
+    for line in lines {
+      let line = line?;
+      let mut words = line.split_whitespace();
+      match words.next() {
+        Some("root") => snapshot.roots.push(id(words.next().ok_or_else(|| invalid(&line))?)?),
+        Some("object") => {
+          let (Some(object), Some(kind), Some(size)) = (words.next(), words.next(), words.next())
+          else {
+            return Err(invalid(&line));
+          };
+          let size = size.parse().map_err(|_| invalid(&line))?;
+          let refs = words.map(id).collect::<io::Result<_>>()?;
+          snapshot
+            .objects
+            .insert(id(object)?, SnapshotObject { kind: kind.to_string(), size, refs });
+        }
+        None => (),
+        Some(_) => return Err(invalid(&line)),
+      }
+    }
+    Ok(snapshot)
+  }
+
+  /// Object count and bytes by kind.
+  pub fn kinds(&self) -> BTreeMap<&str, (usize, usize)> {
+    let mut kinds = BTreeMap::<&str, (usize, usize)>::new();
+    for object in self.objects.values() {
+      let (count, bytes) = kinds.entry(&object.kind).or_default();
+      *count += 1;
+      *bytes += object.size;
+    }
+    kinds
+  }
+
+  /// The shortest path from a root to each reachable object, as the object it was reached from.
+  fn parents(&self) -> BTreeMap<u64, Option<u64>> {
+    let mut parents = BTreeMap::new();
+    let mut queue = VecDeque::new();
+    for root in &self.roots {
+      if parents.insert(*root, None).is_none() {
+        queue.push_back(*root);
+      }
+    }
+    while let Some(id) = queue.pop_front() {
+      for r in self.objects.get(&id).map(|object| &object.refs[..]).unwrap_or_default() {
+        if !parents.contains_key(r) {
+          parents.insert(*r, Some(id));
+          queue.push_back(*r);
+        }
+      }
+    }
+    parents
+  }
+
+  /// Compare with a later snapshot `after`.
+  pub fn diff<'s>(&'s self, after: &'s Snapshot) -> SnapshotDiff<'s> {
+    SnapshotDiff { before: self, after }
+  }
+}
+
+/// Growth by kind between two snapshots and retention paths of the new objects.
+pub struct SnapshotDiff<'s> {
+  before: &'s Snapshot,
+  after: &'s Snapshot,
+}
+
+impl fmt::Display for SnapshotDiff<'_> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let (before, after) = (self.before.kinds(), self.after.kinds());
+    let mut kinds = before.keys().chain(after.keys()).copied().collect::<Vec<_>>();
+    kinds.sort();
+    kinds.dedup();
+
+    let delta = |kind: &str| {
+      let (count_a, bytes_a) = before.get(kind).copied().unwrap_or_default();
+      let (count_b, bytes_b) = after.get(kind).copied().unwrap_or_default();
+      (count_a, count_b, bytes_a, bytes_b)
+    };
+    kinds.sort_by_key(|kind| {
+      let (_, _, bytes_a, bytes_b) = delta(kind);
+      std::cmp::Reverse(bytes_b as i64 - bytes_a as i64)
+    });
+
+    writeln!(f, "{:<24} {:>10} {:>10} {:>12}", "kind", "count", "delta", "bytes delta")?;
+    for kind in &kinds {
+      let (count_a, count_b, bytes_a, bytes_b) = delta(kind);
+      writeln!(
+        f,
+        "{kind:<24} {count_b:>10} {:>+10} {:>+12}",
+        count_b as i64 - count_a as i64,
+        bytes_b as i64 - bytes_a as i64
+      )?;
+    }
+
+    let parents = self.after.parents();
+    let new = |id: &u64| !self.before.objects.contains_key(id) && parents.contains_key(id);
+    write!(f, "\nretention paths of new reachable objects:")?;
+    for kind in kinds.iter().filter(|kind| {
+      let (count_a, count_b, ..) = delta(kind);
+      count_b > count_a
+    }) {
+      let ids = self.after.objects.iter().filter(|(id, object)| object.kind == *kind && new(id));
+      for (id, _) in ids.take(PATHS_PER_KIND) {
+        let mut path = vec![*id];
+        while let Some(Some(parent)) = parents.get(path.last().unwrap()) {
+          path.push(*parent);
+        }
+        write!(f, "\nroot")?;
+        for id in path.iter().rev() {
+          let kind = self.after.objects.get(id).map(|object| &object.kind[..]).unwrap_or("?");
+          write!(f, " -> {kind}@{id:x}")?;
+        }
+      }
+    }
+    Ok(())
+  }
+}
+
+/// The kind of the object `value` points to.
+fn kind(value: Value) -> String {
+  match value.tag() {
+    Value::TAG_CLASS => {
+      let ptr = value.reference() as *const ObjClass;
+      format!("class({})", unsafe { &(*(*ptr).class_ref).name })
+    }
+    Value::TAG_NATIVE => {
+      let ptr = value.reference() as *const ObjNative;
+      format!("native({})", unsafe { (*ptr).type_name })
+    }
+    _ => value.type_name().to_string(),
+  }
+}
@@ -0,0 +1,60 @@
+use core::fmt;
+use std::{collections::BTreeMap, time::Duration};
+
+/// Collector statistics, since the creation of the [`Gc`](super::Gc).
+#[derive(Debug, Clone, Default)]
+pub struct GcStats {
+  pub minor_collections: usize,
+  pub major_collections: usize,
+  /// Slices run by the incremental collector, each one a pause.
+  pub slices: usize,
+  /// Most objects and slots scanned, or objects swept, by one slice.
+  pub max_slice_work: usize,
+  pub objects_freed: usize,
+  pub bytes_freed: usize,
+  /// Bytes of the heap after the last collection.
+  pub heap_bytes: usize,
+  pub last_pause: Duration,
+  pub max_pause: Duration,
+  pub total_pause: Duration,
+  /// Live objects by kind, like `string` or `dict`.
+  pub live: BTreeMap<&'static str, usize>,
+}
+
+impl GcStats {
+  pub fn collections(&self) -> usize {
+    self.minor_collections + self.major_collections
+  }
+}
+
+impl fmt::Display for GcStats {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    writeln!(f, "GC statistics:")?;
+    writeln!(
+      f,
+      "  collections: {} ({} minor, {} major, {} slices)",
+      self.collections(),
+      self.minor_collections,
+      self.major_collections,
+      self.slices
+    )?;
+    if self.slices > 0 {
+      writeln!(f, "  slice work: {} max", self.max_slice_work)?;
+    }
+    writeln!(f, "  freed: {} objects, {} bytes", self.objects_freed, self.bytes_freed)?;
+    writeln!(f, "  heap: {} bytes", self.heap_bytes)?;
+    writeln!(
+      f,
+      "  pause: {:?} total, {:?} max, {:?} last",
+      self.total_pause, self.max_pause, self.last_pause
+    )?;
+    write!(f, "  live:")?;
+    if self.live.is_empty() {
+      write!(f, " none")?;
+    }
+    for (kind, count) in &self.live {
+      write!(f, " {kind}={count}")?;
+    }
+    Ok(())
+  }
+}
@@ -0,0 +1,117 @@
+use core::fmt;
+
+use crate::opcode::{self, Operand};
+
+/// A decoded instruction.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Instruction {
+  /// The instruction address.
+  pub ip: usize,
+  /// The instruction opcode.
+  pub opcode: u8,
+  /// The operand values, in the order of [`Instruction::kinds`].
+  pub operands: Vec<usize>,
+}
+
+impl Instruction {
+  /// The address of the next instruction.
+  pub fn next(&self) -> usize {
+    self.ip + 1 + self.kinds().map(|operand| operand.width()).sum::<usize>()
+  }
+
+  /// The operand kinds, [`opcode::operands`] followed by the repeated operands of a switch.
+  pub fn kinds(&self) -> impl Iterator<Item = Operand> + '_ {
+    let fixed = opcode::operands(self.opcode);
+    let repeated = opcode::repeated_operands(self.opcode).iter().cycle();
+    fixed.iter().chain(repeated.take(self.operands.len() - fixed.len())).copied()
+  }
+}
+
+/// An instruction decoding error.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DecodeError {
+  UnknownOpcode { ip: usize, opcode: u8 },
+  Truncated { ip: usize, opcode: u8 },
+}
+
+impl DecodeError {
+  /// The address of the faulty instruction.
+  pub fn ip(&self) -> usize {
+    match self {
+      DecodeError::UnknownOpcode { ip, .. } | DecodeError::Truncated { ip, .. } => *ip,
+    }
+  }
+}
+
+impl fmt::Display for DecodeError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      DecodeError::UnknownOpcode { ip, opcode } => {
+        write!(f, "Unknown opcode {opcode:#X} at @{ip}.")
+      }
+      DecodeError::Truncated { ip, opcode } => {
+        write!(f, "Truncated {} operands at @{ip}.", opcode::TO_STR[*opcode as usize])
+      }
+    }
+  }
+}
+
+impl std::error::Error for DecodeError {}
+
+/// Iterator over the instructions of a bytecode.
+pub struct Instructions<'a> {
+  code: &'a [u8],
+  ip: usize,
+}
+
+impl<'a> Instructions<'a> {
+  pub fn new(code: &'a [u8]) -> Self {
+    Self { code, ip: 0 }
+  }
+}
+
+impl Instructions<'_> {
+  /// Decode `kinds` at `offset` into `operands`, `None` when truncated.
+  fn decode(&self, kinds: &[Operand], offset: &mut usize, operands: &mut Vec<usize>) -> Option<()> {
+    for operand in kinds {
+      let bytes = self.code.get(*offset..*offset + operand.width())?;
+      operands.push(bytes.iter().fold(0, |acc, byte| acc << 8 | *byte as usize));
+      *offset += operand.width();
+    }
+    Some(())
+  }
+}
+
+impl Iterator for Instructions<'_> {
+  type Item = Result<Instruction, DecodeError>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    let ip = self.ip;
+    let opcode = *self.code.get(ip)?;
+    if opcode as usize >= opcode::TO_STR.len() {
+      self.ip = self.code.len();
+      return Some(Err(DecodeError::UnknownOpcode { ip, opcode }));
+    }
+
+    let mut offset = ip + 1;
+    let mut operands = Vec::new();
+    let repeated = opcode::repeated_operands(opcode);
+    let decoded =
+      self.decode(opcode::operands(opcode), &mut offset, &mut operands).and_then(|()| {
+        let repeats = opcode::repeats(opcode, &operands);
+        let width = repeated.iter().map(Operand::width).sum::<usize>();
+        // A table larger than the code is truncated, do not decode it.
+        if repeats.saturating_mul(width) > self.code.len() - offset {
+          return None;
+        }
+        (0..repeats).try_for_each(|_| self.decode(repeated, &mut offset, &mut operands))
+      });
+    if decoded.is_none() {
+      self.ip = self.code.len();
+      return Some(Err(DecodeError::Truncated { ip, opcode }));
+    }
+
+    self.ip = offset;
+    Some(Ok(Instruction { ip, opcode, operands }))
+  }
+}
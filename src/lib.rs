@@ -0,0 +1,27 @@
+//! Grape Virtual Machine, see [`Vm`] to embed it.
+
+pub mod asm;
+pub mod class;
+pub mod context;
+pub mod disasm;
+pub mod formatting;
+pub mod function;
+pub mod gc;
+pub mod instruction;
+pub mod loader;
+pub mod local;
+pub mod module;
+pub mod module_path;
+pub mod opcode;
+pub mod pool_entry;
+pub mod read_bytes;
+pub mod runtime;
+pub mod stack;
+pub mod value;
+pub mod verifier;
+pub mod vm;
+pub mod write_bytes;
+
+pub use runtime::{Error, Result};
+pub use value::Value;
+pub use vm::{FromValue, Vm};
@@ -9,6 +9,7 @@ use crate::{
   module::Module,
   module_path,
   runtime::{Error, Result},
+  verifier::Verifier,
 };
 
 #[derive(Default)]
@@ -17,6 +18,13 @@ pub struct LoaderArena {
   classes: typed_arena::Arena<Class>,
 }
 
+impl LoaderArena {
+  /// Allocate a module without registering it.
+  pub(crate) fn alloc_module(&self, module: Module) -> &Module {
+    self.modules.alloc(module)
+  }
+}
+
 pub struct Loader<'c> {
   arena: &'c LoaderArena,
   modules: BTreeMap<Rc<str>, &'c Module>,
@@ -28,11 +36,29 @@ impl<'c> Loader<'c> {
     let std_out: &'c Module = arena.modules.alloc(crate::module::std_out::module());
     let file: &'c Module = arena.modules.alloc(crate::module::file::module());
     let tcp: &'c Module = arena.modules.alloc(crate::module::tcp::module());
+    let bytes: &'c Module = arena.modules.alloc(crate::module::bytes::module());
+    let gc: &'c Module = arena.modules.alloc(crate::module::gc::module());
+    let weak: &'c Module = arena.modules.alloc(crate::module::weak::module());
+    let dict: &'c Module = arena.modules.alloc(crate::module::dict::module());
+    let string: &'c Module = arena.modules.alloc(crate::module::string::module());
     let mut modules = BTreeMap::new();
     modules.insert(Rc::from("std:out"), std_out);
     modules.insert(Rc::from("file"), file);
     modules.insert(Rc::from("tcp"), tcp);
-    Self { arena, modules, classes: Default::default() }
+    modules.insert(Rc::from("bytes"), bytes);
+    modules.insert(Rc::from("gc"), gc);
+    modules.insert(Rc::from("weak"), weak);
+    modules.insert(Rc::from("dict"), dict);
+    modules.insert(Rc::from("string"), string);
+    let error: &'c Class = arena.classes.alloc(crate::class::error::class());
+    let mut classes = BTreeMap::new();
+    classes.insert(Rc::from(crate::class::error::NAME), error);
+    Self { arena, modules, classes }
+  }
+
+  /// A loader adding modules to an existing `context`.
+  pub fn with_context(arena: &'c LoaderArena, context: Context<'c>) -> Self {
+    Self { arena, modules: context.modules, classes: context.classes }
   }
 
   pub fn to_context(self) -> Context<'c> {
@@ -40,28 +66,72 @@ impl<'c> Loader<'c> {
   }
 
   pub fn load_path(&mut self, module: &str) -> Result<()> {
-    let mut loaded = BTreeSet::new();
-    let mut to_load = vec![module];
+    if self.modules.contains_key(module) {
+      return Ok(());
+    }
+    let module = self.read_module(module)?;
+    self.load_module(module)
+  }
 
-    while let Some(module) = to_load.pop() {
-      if self.modules.contains_key(module) {
-        continue;
-      }
-      let mut module = self.read_module(module)?;
+  /// Load a module from its binary representation.
+  pub fn load_bytes(&mut self, mut bytes: &[u8]) -> Result<()> {
+    let module = Module::read(&mut bytes).map_err(Error::other)?;
+    self.load_module(module)
+  }
+
+  /// Load `module` and the modules it references from their paths, then verify them. Nothing is
+  /// added when one of them fails to load or verify.
+  pub fn load_module(&mut self, module: Module) -> Result<()> {
+    let (modules, classes) = (self.modules.clone(), self.classes.clone());
+    let result = self.add_modules(module);
+    if result.is_err() {
+      self.modules = modules;
+      self.classes = classes;
+    }
+    result
+  }
+
+  fn add_modules(&mut self, module: Module) -> Result<()> {
+    let mut loaded = BTreeSet::new();
+    let mut to_load = Vec::new();
+    let mut modules = Vec::new();
+    let mut classes = Vec::new();
+    let mut next = Some(module);
 
-      let classes = std::mem::take(&mut module.classes);
-      for class in classes.into_values() {
-        self.add_class(class)?;
+    while let Some(mut module) = next.take() {
+      let module_classes = std::mem::take(&mut module.classes);
+      let mut constants = Vec::new();
+      for class in module_classes.into_values() {
+        let class = self.add_class(class)?;
+        constants.extend(class.constants.iter());
+        classes.push(class);
       }
 
       let module = self.add_module(module)?;
-      for constant in module.constants.iter() {
+      constants.extend(module.constants.iter());
+      modules.push(module);
+      for constant in constants {
         if let crate::pool_entry::PoolEntry::Module(name) = constant {
           if loaded.insert(name) {
             to_load.push(name);
           }
         }
       }
+
+      while let Some(name) = to_load.pop() {
+        if !self.modules.contains_key(name.as_str()) {
+          next = Some(self.read_module(name)?);
+          break;
+        }
+      }
+    }
+
+    let mut verifier = Verifier::new(&self.modules, &self.classes);
+    for module in modules {
+      verifier.verify_module(module)?;
+    }
+    for class in classes {
+      verifier.verify_class(class)?;
     }
 
     Ok(())
@@ -80,12 +150,9 @@ impl<'c> Loader<'c> {
     }
   }
 
-  pub(crate) fn add_class(&mut self, class: Class) -> Result<()> {
+  pub(crate) fn add_class(&mut self, class: Class) -> Result<&'c Class> {
     match self.classes.entry(class.name.clone()) {
-      Entry::Vacant(v) => {
-        v.insert(self.arena.classes.alloc(class));
-        Ok(())
-      }
+      Entry::Vacant(v) => Ok(v.insert(self.arena.classes.alloc(class))),
       Entry::Occupied(_) => Err(Error::ClassAlreadyExists(class.name.to_string())),
     }
   }
@@ -1,6 +1,9 @@
-use std::slice::Iter;
+use std::slice::{Iter, IterMut};
 
-use crate::value::Value;
+use crate::{
+  runtime::Result,
+  value::{Int32, Value},
+};
 
 #[derive(Debug)]
 pub struct Local {
@@ -13,10 +16,19 @@ impl Local {
     Self { local: vec![Value::mk_integer(0); capacity], base: 0 }
   }
 
-  pub(crate) fn iter(&self) -> Iter<Value> {
+  pub(crate) fn iter(&self) -> Iter<'_, Value> {
     self.local.iter()
   }
 
+  pub(crate) fn iter_mut(&mut self) -> IterMut<'_, Value> {
+    self.local.iter_mut()
+  }
+
+  /// Start of the current frame.
+  pub(crate) fn base(&self) -> usize {
+    self.base
+  }
+
   #[inline(always)]
   pub fn push_frame(&mut self, size: usize) -> usize {
     let new_base = self.local.len();
@@ -42,9 +54,10 @@ impl Local {
   }
 
   #[inline(always)]
-  pub fn iinc(&mut self, index: usize, inc: i32) {
+  pub fn iinc(&mut self, index: usize, inc: i32) -> Result<()> {
     let value = &mut self.local[self.base + index];
-    assert!(value.tag() == Value::TAG_INTEGER);
-    unsafe { *value.integer_mut() += inc };
+    let integer = Int32::try_from(*value)?;
+    *value = Value::mk_integer(integer.wrapping_add(inc));
+    Ok(())
   }
 }
@@ -1,31 +1,14 @@
-use function::builder::FunctionBuilder;
-use loader::{Loader, LoaderArena};
-use module::builder::ModuleBuilder;
-use pool_entry::PoolEntry;
-use runtime::BootOptions;
-
-use crate::{
+use grape::{
+  asm, disasm,
+  function::builder::FunctionBuilder,
+  gc::{incremental::INCREMENTAL_BUDGET, snapshot::Snapshot, Collector},
+  loader::{Loader, LoaderArena},
+  module::{self, builder::ModuleBuilder},
   opcode::*,
-  runtime::{Result, Runtime},
+  pool_entry::PoolEntry,
+  runtime::{self, BootOptions, Error, Result, Runtime},
 };
 
-pub mod class;
-pub mod context;
-pub mod formatting;
-pub mod function;
-pub mod gc;
-pub mod loader;
-pub mod local;
-pub mod module;
-pub mod module_path;
-pub mod opcode;
-pub mod pool_entry;
-pub mod read_bytes;
-pub mod runtime;
-pub mod stack;
-pub mod value;
-pub mod write_bytes;
-
 #[rustfmt::skip]
 fn run() -> Result<()> {
   let matches = clap::Command::new("gvm")
@@ -38,8 +21,96 @@ fn run() -> Result<()> {
         .long("entrypoint")
         .default_value(None)
     )
+    .arg(
+      clap::Arg::new("max-heap")
+        .help("Maximum heap size in bytes, with an optional K, M or G suffix")
+        .long("max-heap")
+        .value_parser(parse_size)
+    )
+    .arg(
+      clap::Arg::new("gc")
+        .help("Garbage collector, copying compacts the heap and incremental bounds pauses")
+        .long("gc")
+        .value_parser(["generational", "copying", "incremental"])
+        .default_value("generational")
+    )
+    .arg(
+      clap::Arg::new("gc-budget")
+        .help("Objects and slots scanned by a slice of the incremental collector")
+        .long("gc-budget")
+        .value_parser(clap::value_parser!(usize))
+    )
+    .arg(
+      clap::Arg::new("heap-snapshot")
+        .help("Write a heap snapshot to this path at exit")
+        .long("heap-snapshot")
+    )
+    .arg(
+      clap::Arg::new("gc-stats")
+        .help("Print garbage collector statistics at exit")
+        .long("gc-stats")
+        .action(clap::ArgAction::SetTrue)
+    )
+    .subcommand(
+      clap::Command::new("asm")
+        .about("Assemble a textual module into a .grape file")
+        .arg(
+          clap::Arg::new("input")
+            .help("Path to the assembly source")
+            .required(true)
+        )
+        .arg(
+          clap::Arg::new("output")
+            .help("Path to the assembled module, defaults to the input with a .grape extension")
+            .short('o')
+            .long("output")
+        )
+    )
+    .subcommand(
+      clap::Command::new("heap-diff")
+        .about("Compare two heap snapshots, reporting growth by kind and retention paths")
+        .arg(
+          clap::Arg::new("before")
+            .help("Path to the earlier snapshot")
+            .required(true)
+        )
+        .arg(
+          clap::Arg::new("after")
+            .help("Path to the later snapshot")
+            .required(true)
+        )
+    )
+    .subcommand(
+      clap::Command::new("disasm")
+        .about("Print a .grape file as an assembly listing")
+        .arg(
+          clap::Arg::new("input")
+            .help("Path to the module")
+            .required(true)
+        )
+    )
     .get_matches();
 
+  if let Some(("disasm", matches)) = matches.subcommand() {
+    let input: &String = matches.get_one("input").unwrap();
+    return disassemble(input);
+  }
+
+  if let Some(("heap-diff", matches)) = matches.subcommand() {
+    let before: &String = matches.get_one("before").unwrap();
+    let after: &String = matches.get_one("after").unwrap();
+    return heap_diff(before, after);
+  }
+
+  if let Some(("asm", matches)) = matches.subcommand() {
+    let input: &String = matches.get_one("input").unwrap();
+    let output = matches
+      .get_one::<String>("output")
+      .map(std::path::PathBuf::from)
+      .unwrap_or_else(|| std::path::Path::new(input).with_extension("grape"));
+    return assemble(input, &output);
+  }
+
   // let m = main_module();
   // let mut f = std::fs::File::options().create_new(true).write(true).open("./main.grape").unwrap();
   // m.write(&mut f).unwrap();
@@ -53,20 +124,71 @@ fn run() -> Result<()> {
   } else {
     loader.load_path("main")?;
   }
-  let context = &mut loader.to_context();
+  let context = loader.to_context();
   // ctx.add_module(main_class())?;
 
-  let mut runtime = Runtime::boot(BootOptions { entrypoint_module, context })?;
+  let max_heap = matches.get_one::<usize>("max-heap").copied();
+  let collector = match matches.get_one::<String>("gc").map(String::as_str) {
+    Some("copying") => Collector::Copying,
+    Some("incremental") => {
+      let budget = matches.get_one::<usize>("gc-budget").copied();
+      Collector::Incremental { budget: budget.unwrap_or(INCREMENTAL_BUDGET) }
+    }
+    _ => Collector::Generational,
+  };
+  let mut runtime =
+    Runtime::boot(BootOptions { entrypoint_module, context, max_heap, collector })?;
   if let Err(e) = runtime.run() {
     eprintln!("Error: {e}");
     runtime.accept(runtime::stack_trace::StackTrace);
-    runtime.accept(runtime::gc::CleanGc);
+  }
+  if let Some(path) = matches.get_one::<String>("heap-snapshot") {
+    runtime.accept(runtime::heap_snapshot::HeapSnapshot { path: path.into() });
+  }
+  if matches.get_flag("gc-stats") {
+    eprintln!("{}", runtime.gc().current_stats());
   }
   runtime.accept(runtime::gc::CleanGc);
 
   Ok(())
 }
 
+/// Parse a size in bytes like `512`, `64K`, `16M` or `1G`.
+fn parse_size(size: &str) -> std::result::Result<usize, String> {
+  let (digits, unit) = match size.char_indices().last() {
+    Some((index, 'K' | 'k')) => (&size[..index], 1 << 10),
+    Some((index, 'M' | 'm')) => (&size[..index], 1 << 20),
+    Some((index, 'G' | 'g')) => (&size[..index], 1 << 30),
+    _ => (size, 1),
+  };
+  let count = digits.parse::<usize>().map_err(|e| e.to_string())?;
+  count.checked_mul(unit).ok_or_else(|| format!("size {size} is too large"))
+}
+
+fn assemble(input: &str, output: &std::path::Path) -> Result<()> {
+  let source = std::fs::read_to_string(input).map_err(Error::other)?;
+  let module = asm::assemble(&source).map_err(Error::other)?;
+  let mut file = std::fs::File::create(output).map_err(Error::other)?;
+  module.write(&mut file).map_err(Error::other)
+}
+
+fn heap_diff(before: &str, after: &str) -> Result<()> {
+  let read = |path: &str| {
+    let file = std::fs::File::open(path).map_err(Error::other)?;
+    Snapshot::read(&mut std::io::BufReader::new(file)).map_err(Error::other)
+  };
+  let (before, after) = (read(before)?, read(after)?);
+  println!("{}", before.diff(&after));
+  Ok(())
+}
+
+fn disassemble(input: &str) -> Result<()> {
+  let mut file = std::fs::File::open(input).map_err(Error::other)?;
+  let module = module::Module::read(&mut file).map_err(Error::other)?;
+  print!("{}", disasm::display_module(&module));
+  Ok(())
+}
+
 fn main() {
   if let Err(e) = run() {
     eprintln!("{e}");
@@ -99,7 +221,7 @@ fn main_class() -> module::Module {
           LOAD_0,
           GET_FIELD, 0, 4,
           LOAD_0,
-          CALL_METHOD, 0, 5,
+          CALL_METHOD, 0, 5, 0,
           HALT,
         ])
         .build()
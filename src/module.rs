@@ -1,8 +1,13 @@
 pub mod builder;
+pub mod bytes;
+pub mod dict;
 pub mod file;
+pub mod gc;
 pub mod read;
 pub mod std_out;
+pub mod string;
 pub mod tcp;
+pub mod weak;
 pub mod write;
 
 use std::collections::BTreeMap;
@@ -15,9 +20,10 @@ use crate::runtime::{Error, Result};
 
 /// Bytecode Module representation.
 ///
-/// ```
+/// ```text
 /// {
 ///   magic_number: u32,
+///   version: u32,
 ///   module_name_length: u16,
 ///   module_name: str<module_name_length>,
 ///   pool_count: u16,
@@ -46,6 +52,8 @@ impl Module {
 
 impl Module {
   pub const MAGIC: u32 = 0x75_76_61_73;
+  /// The bytecode format version, bumped when the encoding changes.
+  pub const VERSION: u32 = 2;
 
   pub fn fetch_function_with_name(&self, name: &str) -> Result<&Function> {
     self.functions.get(name).ok_or(Error::FunctionNotFound(name.to_string()))
@@ -1,4 +1,7 @@
-use crate::function::Function;
+use crate::{
+  function::{Function, NativeRet},
+  runtime::native::NativeCtx,
+};
 
 use super::{Module, PoolEntry};
 
@@ -30,6 +33,16 @@ impl ModuleBuilder {
     self
   }
 
+  /// Add a native function taking `args` arguments.
+  pub fn with_native(
+    self,
+    name: &str,
+    args: u8,
+    native: impl Fn(&mut NativeCtx) -> NativeRet + 'static,
+  ) -> Self {
+    self.with_function(Function::native(name, args, native))
+  }
+
   pub fn build(self) -> Module {
     let functions = self.functions.into_iter().map(|f| (f.name.clone(), f)).collect();
     Module {
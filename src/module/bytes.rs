@@ -0,0 +1,88 @@
+use crate::{
+  function::{Function, NativeRet},
+  runtime::{native::NativeCtx, Error, Result},
+  value::Value,
+};
+
+use super::{builder::ModuleBuilder, Module};
+
+fn len(ctx: &mut NativeCtx) -> NativeRet {
+  let len = ctx.bytes(0)?.len();
+  Ok(Some(Value::mk_integer(len as i32)))
+}
+
+fn get(ctx: &mut NativeCtx) -> NativeRet {
+  let index = ctx.arg::<i32>(1)?;
+  let bytes = ctx.bytes(0)?;
+  let index = check_index(index, bytes.len())?;
+  Ok(Some(Value::mk_byte(bytes[index])))
+}
+
+fn set(ctx: &mut NativeCtx) -> NativeRet {
+  let index = ctx.arg::<i32>(1)?;
+  let byte = ctx.arg::<u8>(2)?;
+  let bytes = ctx.bytes(0)?;
+  let index = check_index(index, bytes.len())?;
+  bytes[index] = byte;
+  Ok(None)
+}
+
+fn slice(ctx: &mut NativeCtx) -> NativeRet {
+  let (start, end) = (ctx.arg::<i32>(1)?, ctx.arg::<i32>(2)?);
+  let bytes = ctx.bytes(0)?;
+  let len = bytes.len();
+  if start < 0 || start as usize > len {
+    return Err(Error::IndexOutOfBounds { index: start as i64, len });
+  }
+  if end < start || end as usize > len {
+    return Err(Error::IndexOutOfBounds { index: end as i64, len });
+  }
+  let slice = bytes[start as usize..end as usize].to_vec();
+  Ok(Some(ctx.alloc_bytes(slice)?))
+}
+
+fn append(ctx: &mut NativeCtx) -> NativeRet {
+  let other = ctx.bytes(1)?.clone();
+  let contents = ctx.bytes(0)?;
+  let (len, capacity) = (contents.len(), contents.capacity());
+  contents.extend(other);
+  let bytes = ctx.value(0);
+  if let Err(e) = ctx.gc().resize(bytes) {
+    let contents = ctx.bytes(0)?;
+    contents.truncate(len);
+    contents.shrink_to(capacity);
+    return Err(e);
+  }
+  Ok(None)
+}
+
+fn from_string(ctx: &mut NativeCtx) -> NativeRet {
+  let bytes = ctx.string(0)?.as_bytes().to_vec();
+  Ok(Some(ctx.alloc_bytes(bytes)?))
+}
+
+fn to_string(ctx: &mut NativeCtx) -> NativeRet {
+  let s = String::from_utf8(ctx.bytes(0)?.clone()).map_err(Error::other)?;
+  Ok(Some(ctx.alloc_string(s)?))
+}
+
+fn check_index(index: i32, len: usize) -> Result<usize> {
+  if index < 0 || index as usize >= len {
+    Err(Error::IndexOutOfBounds { index: index as i64, len })
+  } else {
+    Ok(index as usize)
+  }
+}
+
+pub fn module() -> Module {
+  ModuleBuilder::new()
+    .with_name("bytes")
+    .with_function(Function::native("len", 1, len))
+    .with_function(Function::native("get", 2, get))
+    .with_function(Function::native("set", 3, set))
+    .with_function(Function::native("slice", 3, slice))
+    .with_function(Function::native("append", 2, append))
+    .with_function(Function::native("from_string", 1, from_string))
+    .with_function(Function::native("to_string", 1, to_string))
+    .build()
+}
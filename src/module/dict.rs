@@ -0,0 +1,84 @@
+use crate::{
+  function::{Function, NativeRet},
+  gc::{DictKey, Gc},
+  runtime::{native::NativeCtx, Result},
+  value::Value,
+};
+
+use super::{builder::ModuleBuilder, Module};
+
+fn len(ctx: &mut NativeCtx) -> NativeRet {
+  let len = ctx.dict(0)?.len();
+  Ok(Some(Value::mk_integer(len as i32)))
+}
+
+fn has(ctx: &mut NativeCtx) -> NativeRet {
+  let key = DictKey(ctx.value(1));
+  let has = ctx.dict(0)?.contains_key(&key);
+  Ok(Some(Value::mk_bool(has)))
+}
+
+fn get(ctx: &mut NativeCtx) -> NativeRet {
+  let key = DictKey(ctx.value(1));
+  let value = ctx.dict(0)?.get(&key).copied();
+  Ok(Some(value.unwrap_or(Value::NULL)))
+}
+
+fn remove(ctx: &mut NativeCtx) -> NativeRet {
+  let key = DictKey(ctx.value(1));
+  let value = ctx.dict(0)?.remove(&key);
+  let dict = ctx.value(0);
+  ctx.gc().resize(dict)?;
+  Ok(Some(value.unwrap_or(Value::NULL)))
+}
+
+fn keys(ctx: &mut NativeCtx) -> NativeRet {
+  let len = ctx.dict(0)?.len();
+  let array = ctx.alloc_array(len as i32)?;
+  // Read after allocating, a collection can move the keys.
+  let keys = ctx.dict(0)?.keys().map(|DictKey(key)| *key).collect();
+  fill(ctx, array, keys)?;
+  Ok(Some(array))
+}
+
+fn values(ctx: &mut NativeCtx) -> NativeRet {
+  let len = ctx.dict(0)?.len();
+  let array = ctx.alloc_array(len as i32)?;
+  let values = ctx.dict(0)?.values().copied().collect();
+  fill(ctx, array, values)?;
+  Ok(Some(array))
+}
+
+/// Call `f(key, value)` for each entry, in key order. Entries added by `f` are not visited.
+fn each(ctx: &mut NativeCtx) -> NativeRet {
+  let function = ctx.value(1);
+  let entries = ctx.dict(0)?.iter().map(|(DictKey(key), value)| (*key, *value)).collect::<Vec<_>>();
+  // `f` can remove entries, their keys and values must stay alive until visited.
+  let entries = entries.into_iter().map(|(key, value)| (ctx.handle(key), ctx.handle(value)));
+  for (key, value) in entries.collect::<Vec<_>>() {
+    ctx.scope(|ctx| ctx.call_value(function, &[key, value]))?;
+  }
+  Ok(None)
+}
+
+/// Store `items` in the new array `array`, of the same length.
+fn fill(ctx: &mut NativeCtx, array: Value, items: Vec<Value>) -> Result<()> {
+  for (index, item) in items.into_iter().enumerate() {
+    ctx.gc().write_barrier(array, item);
+    Gc::array_set(array.reference(), index as i32, item)?;
+  }
+  Ok(())
+}
+
+pub fn module() -> Module {
+  ModuleBuilder::new()
+    .with_name("dict")
+    .with_function(Function::native("len", 1, len))
+    .with_function(Function::native("has", 2, has))
+    .with_function(Function::native("get", 2, get))
+    .with_function(Function::native("remove", 2, remove))
+    .with_function(Function::native("keys", 1, keys))
+    .with_function(Function::native("values", 1, values))
+    .with_function(Function::native("each", 2, each))
+    .build()
+}
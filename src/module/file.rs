@@ -2,36 +2,21 @@ use std::{fs, io::Read};
 
 use crate::{
   function::{Function, NativeRet},
-  gc::{Gc, ObjString},
-  local::Local,
-  runtime::Error,
-  value::Reference,
+  runtime::{native::NativeCtx, Error},
 };
 
 use super::{builder::ModuleBuilder, Module};
 
-fn read_to_string(local: &mut Local, heap: &mut Gc) -> NativeRet {
-  let file_string: Reference = local.load(0).into();
-  let path = file_string as *mut ObjString;
-  unsafe {
-    let path = &(*path).contents;
-    let mut file = fs::File::open(path).map_err(Error::other)?;
-    let mut s = String::new();
-    file.read_to_string(&mut s).map_err(Error::other)?;
-    Ok(Some(heap.alloc_string(s)))
-  }
+fn read_to_string(ctx: &mut NativeCtx) -> NativeRet {
+  let mut file = fs::File::open(ctx.string(0)?).map_err(Error::other)?;
+  let mut s = String::new();
+  file.read_to_string(&mut s).map_err(Error::other)?;
+  Ok(Some(ctx.alloc_string(s)?))
 }
 
-fn read_to_bytes(_local: &mut Local, _heap: &mut Gc) -> NativeRet {
-  todo!()
-  // let file_string: Reference = local.load(0).into();
-  // let ObjectType::String(ObjString { contents: path }) = &*heap.get(file_string).value else {
-  //   panic!();
-  // };
-  // let mut file = fs::File::open(path).map_err(Error::other)?;
-  // let mut buf = Vec::new();
-  // file.read_to_end(&mut buf).map_err(Error::other)?;
-  // Ok(Some(heap.new_bytes(buf)))
+fn read_to_bytes(ctx: &mut NativeCtx) -> NativeRet {
+  let bytes = fs::read(ctx.string(0)?).map_err(Error::other)?;
+  Ok(Some(ctx.alloc_bytes(bytes)?))
 }
 
 pub fn module() -> Module {
@@ -0,0 +1,74 @@
+use std::time::Duration;
+
+use crate::{
+  function::{Function, NativeRet},
+  gc::Gc,
+  runtime::{native::NativeCtx, Result},
+  value::Value,
+};
+
+use super::{builder::ModuleBuilder, Module};
+
+fn collect(ctx: &mut NativeCtx) -> NativeRet {
+  ctx.collect()?;
+  Ok(None)
+}
+
+fn stats(ctx: &mut NativeCtx) -> NativeRet {
+  let stats = ctx.gc().stats().clone();
+  let live = ctx.alloc_dict()?;
+  for (kind, count) in &stats.live {
+    set(ctx, live, kind, Value::mk_integer(saturate(*count)))?;
+  }
+
+  let dict = ctx.alloc_dict()?;
+  set(ctx, dict, "minor_collections", Value::mk_integer(saturate(stats.minor_collections)))?;
+  set(ctx, dict, "major_collections", Value::mk_integer(saturate(stats.major_collections)))?;
+  set(ctx, dict, "slices", Value::mk_integer(saturate(stats.slices)))?;
+  set(ctx, dict, "objects_freed", Value::mk_integer(saturate(stats.objects_freed)))?;
+  set(ctx, dict, "bytes_freed", Value::mk_integer(saturate(stats.bytes_freed)))?;
+  set(ctx, dict, "heap_bytes", Value::mk_integer(saturate(stats.heap_bytes)))?;
+  set(ctx, dict, "last_pause_us", Value::mk_integer(micros(stats.last_pause)))?;
+  set(ctx, dict, "max_pause_us", Value::mk_integer(micros(stats.max_pause)))?;
+  set(ctx, dict, "total_pause_us", Value::mk_integer(micros(stats.total_pause)))?;
+  set(ctx, dict, "live", live)?;
+  Ok(Some(dict))
+}
+
+fn snapshot(ctx: &mut NativeCtx) -> NativeRet {
+  ctx.heap_snapshot(ctx.string(0)?)?;
+  Ok(None)
+}
+
+fn set_threshold(ctx: &mut NativeCtx) -> NativeRet {
+  let bytes = ctx.arg::<i32>(0)?.max(0) as usize;
+  ctx.gc().set_threshold(bytes);
+  Ok(None)
+}
+
+/// Set `dict[key] = value` for a dict allocated by the native.
+fn set(ctx: &mut NativeCtx, dict: Value, key: &str, value: Value) -> Result<()> {
+  let key = ctx.alloc_string(key.to_string())?;
+  ctx.gc().write_barrier(dict, key);
+  ctx.gc().write_barrier(dict, value);
+  Gc::set_dict(dict.reference(), key, value);
+  ctx.gc().resize(dict)
+}
+
+fn saturate(n: usize) -> i32 {
+  n.try_into().unwrap_or(i32::MAX)
+}
+
+fn micros(duration: Duration) -> i32 {
+  duration.as_micros().try_into().unwrap_or(i32::MAX)
+}
+
+pub fn module() -> Module {
+  ModuleBuilder::new()
+    .with_name("gc")
+    .with_function(Function::native("collect", 0, collect))
+    .with_function(Function::native("stats", 0, stats))
+    .with_function(Function::native("set_threshold", 1, set_threshold))
+    .with_function(Function::native("snapshot", 1, snapshot))
+    .build()
+}
@@ -1,7 +1,7 @@
 use std::collections::BTreeMap;
 
 use super::{Class, Module};
-use crate::PoolEntry;
+use crate::pool_entry::PoolEntry;
 use crate::{function::Function, read_bytes::ReadBytes};
 
 impl Module {
@@ -11,6 +11,13 @@ impl Module {
     if magic != Self::MAGIC {
       return Err(std::io::Error::other("Is not a grape file"));
     }
+    let version = rd.read_u32()?;
+    if version != Self::VERSION {
+      return Err(std::io::Error::other(format!(
+        "Unsupported grape file version {version}, expected {}",
+        Self::VERSION
+      )));
+    }
 
     let name = rd.read_rc_str()?;
 
@@ -1,29 +1,31 @@
 use crate::{
   formatting,
   function::{Function, NativeRet},
-  gc::Gc,
-  local::Local,
+  runtime::native::NativeCtx,
 };
 
 use super::{builder::ModuleBuilder, Module};
 
-fn println(local: &mut Local, heap: &mut Gc) -> NativeRet {
-  println!("{}", formatting::display_value(&local.load(0), heap));
+fn println(ctx: &mut NativeCtx) -> NativeRet {
+  let value = ctx.value(0);
+  println!("{}", formatting::display_value(&value, ctx.gc()));
   Ok(None)
 }
 
-fn print(local: &mut Local, heap: &mut Gc) -> NativeRet {
-  print!("{}", formatting::display_value(&local.load(0), heap));
+fn print(ctx: &mut NativeCtx) -> NativeRet {
+  let value = ctx.value(0);
+  print!("{}", formatting::display_value(&value, ctx.gc()));
   Ok(None)
 }
 
-fn debug(local: &mut Local, _: &mut Gc) -> NativeRet {
-  println!("{:?}", local.load(0));
+fn debug(ctx: &mut NativeCtx) -> NativeRet {
+  println!("{:?}", ctx.value(0));
   Ok(None)
 }
 
-fn eprintln(local: &mut Local, heap: &mut Gc) -> NativeRet {
-  eprintln!("{}", formatting::display_value(&local.load(0), heap));
+fn eprintln(ctx: &mut NativeCtx) -> NativeRet {
+  let value = ctx.value(0);
+  eprintln!("{}", formatting::display_value(&value, ctx.gc()));
   Ok(None)
 }
 
@@ -0,0 +1,18 @@
+use crate::{
+  function::{Function, NativeRet},
+  runtime::native::NativeCtx,
+};
+
+use super::{builder::ModuleBuilder, Module};
+
+fn intern(ctx: &mut NativeCtx) -> NativeRet {
+  let s = ctx.string(0)?.to_string();
+  Ok(Some(ctx.intern(&s)?))
+}
+
+pub fn module() -> Module {
+  ModuleBuilder::new()
+    .with_name("string")
+    .with_function(Function::native("intern", 1, intern))
+    .build()
+}
@@ -1,100 +1,131 @@
-// use std::{
-//   io::{Read, Write},
-//   net::{TcpListener, TcpStream},
-// };
+use std::{
+  io::{Read, Write},
+  net::{Shutdown, TcpListener, TcpStream},
+  time::Duration,
+};
 
 use crate::{
   function::{Function, NativeRet},
-  gc::{Gc, ObjString},
-  local::Local,
-  runtime::Error,
-  value::Reference,
+  runtime::{native::NativeCtx, Error},
 };
 
 use super::{builder::ModuleBuilder, Module};
 
-fn new_listener(local: &mut Local, _heap: &mut Gc) -> NativeRet {
-  let addr: Reference = local.load(0).into();
+const LISTENER: &str = "TcpListener";
+const STREAM: &str = "TcpStream";
+
+/// Size of the `recv_string` buffer.
+const RECV_SIZE: usize = 4096;
+/// Most bytes read by one `recv_bytes`, whatever `max` it is given.
+const RECV_BYTES_MAX: usize = 1 << 16;
+
+fn new_listener(ctx: &mut NativeCtx) -> NativeRet {
+  let listener = TcpListener::bind(ctx.string(0)?).map_err(Error::other)?;
+  Ok(Some(ctx.alloc_native(LISTENER, listener)?))
+}
+
+fn connect(ctx: &mut NativeCtx) -> NativeRet {
+  let stream = TcpStream::connect(ctx.string(0)?).map_err(Error::other)?;
+  Ok(Some(ctx.alloc_native(STREAM, stream)?))
+}
+
+fn destroy(ctx: &mut NativeCtx) -> NativeRet {
+  ctx.release(0)?;
+  Ok(None)
+}
+
+fn accept(ctx: &mut NativeCtx) -> NativeRet {
+  let (stream, _) = ctx.native::<TcpListener>(0)?.accept().map_err(Error::other)?;
+  Ok(Some(ctx.alloc_native(STREAM, stream)?))
+}
 
-  unsafe {
-    let addr = addr as *mut ObjString;
-    let addr = &(*addr).contents;
-    // let ObjectType::String(ObjString { contents: addr }) = &*heap.get(addr).value else {
-    //   panic!();
-    // };
+fn local_addr(ctx: &mut NativeCtx) -> NativeRet {
+  let addr = match ctx.native::<TcpListener>(0) {
+    Ok(listener) => listener.local_addr(),
+    Err(Error::Conversion { .. }) => ctx.native::<TcpStream>(0)?.local_addr(),
+    Err(e) => return Err(e),
+  };
+  let addr = addr.map_err(Error::other)?.to_string();
+  Ok(Some(ctx.alloc_string(addr)?))
+}
 
-    let listener = std::net::TcpListener::bind(addr).map_err(Error::other)?;
-    // let listener = heap.alloc(Object::new(ObjectType::Native(Box::new(listener))));
-    let listener = todo!();
+fn peer_addr(ctx: &mut NativeCtx) -> NativeRet {
+  let addr = ctx.native::<TcpStream>(0)?.peer_addr().map_err(Error::other)?.to_string();
+  Ok(Some(ctx.alloc_string(addr)?))
+}
+
+fn recv_string(ctx: &mut NativeCtx) -> NativeRet {
+  let stream = ctx.native::<TcpStream>(0)?;
+  let mut buf = vec![0; RECV_SIZE];
+  let len = stream.read(&mut buf).map_err(Error::other)?;
+  buf.truncate(len);
+  // A read can end in the middle of a character, the rest of it is read before decoding.
+  let s = loop {
+    match String::from_utf8(buf) {
+      Ok(s) => break s,
+      Err(e) if e.utf8_error().error_len().is_none() => {
+        buf = e.into_bytes();
+        let mut byte = [0];
+        stream.read_exact(&mut byte).map_err(Error::other)?;
+        buf.push(byte[0]);
+      }
+      Err(e) => return Err(Error::other(e)),
+    }
+  };
+  Ok(Some(ctx.alloc_string(s)?))
+}
 
-    Ok(Some(listener))
-  }
+fn send_string(ctx: &mut NativeCtx) -> NativeRet {
+  let s = ctx.string(1)?.to_string();
+  let stream = ctx.native::<TcpStream>(0)?;
+  stream.write_all(s.as_bytes()).map_err(Error::other)?;
+  stream.flush().map_err(Error::other)?;
+  Ok(None)
 }
 
-fn destroy(local: &mut Local, _heap: &mut Gc) -> NativeRet {
-  // let listener: Reference = local.load(0).into();
-  // heap.free(listener);
-  // Ok(None)
-  todo!()
+fn recv_bytes(ctx: &mut NativeCtx) -> NativeRet {
+  let max = (ctx.arg::<i32>(1)?.max(0) as usize).min(RECV_BYTES_MAX);
+  let mut buf = vec![0; max];
+  let len = ctx.native::<TcpStream>(0)?.read(&mut buf).map_err(Error::other)?;
+  Ok(Some(ctx.alloc_bytes(buf[..len].to_vec())?))
 }
 
-fn accept(local: &mut Local, _heap: &mut Gc) -> NativeRet {
-  let listener: Reference = local.load(0).into();
-
-  todo!()
-  // let ObjectType::Native(nat) = &*heap.get(listener).value else { panic!() };
-  // match nat.downcast_ref::<TcpListener>() {
-  //   Some(listener) => {
-  //     let (tcp_stream, _) = listener.accept().map_err(Error::other)?;
-  //     // let tcp_stream = heap.alloc(Object::new(ObjectType::Native(Box::new(tcp_stream))));
-  //     let tcp_stream = todo!();
-  //     Ok(Some(tcp_stream))
-  //   }
-  //   None => todo!(),
-  // }
+fn send_bytes(ctx: &mut NativeCtx) -> NativeRet {
+  let bytes = ctx.bytes(1)?.clone();
+  let stream = ctx.native::<TcpStream>(0)?;
+  stream.write_all(&bytes).map_err(Error::other)?;
+  stream.flush().map_err(Error::other)?;
+  Ok(None)
 }
 
-fn recv_string(local: &mut Local, _heap: &mut Gc) -> NativeRet {
-  let stream: Reference = local.load(0).into();
-
-  todo!()
-  // let ObjectType::Native(nat) = &*heap.get(stream).value else { panic!() };
-  // match nat.downcast_ref::<TcpStream>() {
-  //   Some(mut stream) => {
-  //     let mut buf = [0; 2048];
-  //     stream.read(&mut buf).map_err(Error::other)?;
-  //     let s = String::from_utf8(buf.to_vec()).map_err(Error::other)?;
-  //     Ok(Some(heap.alloc_string(s)))
-  //   }
-  //   None => todo!(),
-  // }
+fn set_timeout(ctx: &mut NativeCtx) -> NativeRet {
+  let millis = ctx.arg::<i32>(1)?;
+  let timeout = (millis > 0).then(|| Duration::from_millis(millis as u64));
+  let stream = ctx.native::<TcpStream>(0)?;
+  stream.set_read_timeout(timeout).map_err(Error::other)?;
+  stream.set_write_timeout(timeout).map_err(Error::other)?;
+  Ok(None)
 }
 
-fn send_string(local: &mut Local, _heap: &mut Gc) -> NativeRet {
-  let stream: Reference = local.load(0).into();
-  let string: Reference = local.load(1).into();
-
-  todo!()
-  // let ObjectType::Native(stream) = &*heap.get(stream).value else { panic!() };
-  // let ObjectType::String(ObjString { contents: send }) = &*heap.get(string).value else { panic!() };
-
-  // match stream.downcast_ref::<TcpStream>() {
-  //   Some(mut stream) => {
-  //     stream.write_all(send.as_bytes()).map_err(Error::other)?;
-  //     stream.flush().map_err(Error::other)?;
-  //     Ok(None)
-  //   }
-  //   None => todo!(),
-  // }
+fn shutdown(ctx: &mut NativeCtx) -> NativeRet {
+  ctx.native::<TcpStream>(0)?.shutdown(Shutdown::Both).map_err(Error::other)?;
+  Ok(None)
 }
 
 pub fn module() -> Module {
   ModuleBuilder::new()
     .with_name("tcp")
     .with_function(Function::native("new_listener", 1, new_listener))
+    .with_function(Function::native("connect", 1, connect))
     .with_function(Function::native("destroy", 1, destroy))
     .with_function(Function::native("accept", 1, accept))
+    .with_function(Function::native("local_addr", 1, local_addr))
+    .with_function(Function::native("peer_addr", 1, peer_addr))
     .with_function(Function::native("recv_string", 1, recv_string))
     .with_function(Function::native("send_string", 2, send_string))
+    .with_function(Function::native("recv_bytes", 2, recv_bytes))
+    .with_function(Function::native("send_bytes", 2, send_bytes))
+    .with_function(Function::native("set_timeout", 2, set_timeout))
+    .with_function(Function::native("shutdown", 1, shutdown))
     .build()
 }
@@ -0,0 +1,29 @@
+use crate::{
+  function::{Function, NativeRet},
+  gc::Gc,
+  runtime::{native::NativeCtx, Error},
+  value::Value,
+};
+
+use super::{builder::ModuleBuilder, Module};
+
+fn new(ctx: &mut NativeCtx) -> NativeRet {
+  let weak = ctx.alloc_weak(ctx.value(0))?;
+  Ok(Some(weak))
+}
+
+fn get(ctx: &mut NativeCtx) -> NativeRet {
+  let weak = ctx.value(0);
+  match weak.tag() {
+    Value::TAG_WEAK => Ok(Some(Gc::get_weak(weak.reference()))),
+    _ => Err(Error::Conversion { expected: "weak", found: weak.type_name() }),
+  }
+}
+
+pub fn module() -> Module {
+  ModuleBuilder::new()
+    .with_name("weak")
+    .with_function(Function::native("new", 1, new))
+    .with_function(Function::native("get", 1, get))
+    .build()
+}
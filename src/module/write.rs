@@ -4,6 +4,7 @@ use crate::write_bytes::WriteBytes;
 impl Module {
   pub fn write<W: std::io::Write>(&self, wr: &mut W) -> std::io::Result<()> {
     wr.write_u32(Self::MAGIC)?;
+    wr.write_u32(Self::VERSION)?;
     wr.write_str(&self.name)?;
 
     wr.write_u16(self.constants.len() as u16)?;
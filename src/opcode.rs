@@ -232,7 +232,7 @@ pub const BYTES_PUSH: u8 = 0x4C;
 /// Create new class object.
 pub const NEW: u8 = 0x4D;
 
-/// Call method from class object.
+/// Call method from class object, with the argument count as operand.
 pub const CALL_METHOD: u8 = 0x4E;
 
 /// Set field to class object.
@@ -241,6 +241,198 @@ pub const SET_FIELD: u8 = 0x4F;
 /// Get field from class object.
 pub const GET_FIELD: u8 = 0x50;
 
+/// Throw exception.
+pub const THROW: u8 = 0x51;
+
+/// Create closure capturing stack values.
+pub const CLOSURE: u8 = 0x52;
+
+/// Call function value.
+pub const CALL_VALUE: u8 = 0x53;
+
+/// Convert integer to long.
+pub const I2L: u8 = 0x54;
+
+/// Convert long to integer.
+pub const L2I: u8 = 0x55;
+
+/// Convert float to double.
+pub const F2D: u8 = 0x56;
+
+/// Convert double to float.
+pub const D2F: u8 = 0x57;
+
+/// Convert long to double.
+pub const L2D: u8 = 0x58;
+
+/// Convert double to long.
+pub const D2L: u8 = 0x59;
+
+/// Add long.
+pub const LADD: u8 = 0x5A;
+
+/// Subtract long.
+pub const LSUB: u8 = 0x5B;
+
+/// Multiply long.
+pub const LMUL: u8 = 0x5C;
+
+/// Divide long.
+pub const LDIV: u8 = 0x5D;
+
+/// Remainder of long.
+pub const LREM: u8 = 0x5E;
+
+/// Long bit AND.
+pub const LAND: u8 = 0x5F;
+
+/// Long bit OR.
+pub const LOR: u8 = 0x60;
+
+/// Long bit XOR.
+pub const LXOR: u8 = 0x61;
+
+/// Long bit shift left.
+pub const LSHL: u8 = 0x62;
+
+/// Long bit shift right.
+pub const LSHR: u8 = 0x63;
+
+/// Long logical bit shift right.
+pub const LUSHR: u8 = 0x64;
+
+/// Negate long.
+pub const LNEG: u8 = 0x65;
+
+/// Add double.
+pub const DADD: u8 = 0x66;
+
+/// Subtract double.
+pub const DSUB: u8 = 0x67;
+
+/// Multiply double.
+pub const DMUL: u8 = 0x68;
+
+/// Divide double.
+pub const DDIV: u8 = 0x69;
+
+/// Remainder of double.
+pub const DREM: u8 = 0x6A;
+
+/// Negate double.
+pub const DNEG: u8 = 0x6B;
+
+/// Branch if long is equal.
+pub const L_IFEQ: u8 = 0x6C;
+
+/// Branch if long is not equal.
+pub const L_IFNEQ: u8 = 0x6D;
+
+/// Branch if long is greater than.
+pub const L_IFGT: u8 = 0x6E;
+
+/// Branch if long is greater or equal.
+pub const L_IFGE: u8 = 0x6F;
+
+/// Branch if long is less than.
+pub const L_IFLT: u8 = 0x70;
+
+/// Branch if long is less or equal.
+pub const L_IFLE: u8 = 0x71;
+
+/// Branch if double is equal.
+pub const D_IFEQ: u8 = 0x72;
+
+/// Branch if double is not equal.
+pub const D_IFNEQ: u8 = 0x73;
+
+/// Branch if double is greater than.
+pub const D_IFGT: u8 = 0x74;
+
+/// Branch if double is greater or equal.
+pub const D_IFGE: u8 = 0x75;
+
+/// Branch if double is less than.
+pub const D_IFLT: u8 = 0x76;
+
+/// Branch if double is less or equal.
+pub const D_IFLE: u8 = 0x77;
+
+/// Push true constant.
+pub const TRUE: u8 = 0x78;
+
+/// Push false constant.
+pub const FALSE: u8 = 0x79;
+
+/// Negate bool.
+pub const NOT: u8 = 0x7A;
+
+/// Branch if true.
+pub const IF_TRUE: u8 = 0x7B;
+
+/// Branch if false.
+pub const IF_FALSE: u8 = 0x7C;
+
+/// Convert integer to char.
+pub const I2C: u8 = 0x7D;
+
+/// Convert char to integer.
+pub const C2I: u8 = 0x7E;
+
+/// Get char at index from string.
+pub const STRING_GET: u8 = 0x7F;
+
+/// Branch if float is equal.
+pub const F_IFEQ: u8 = 0x80;
+
+/// Branch if float is not equal.
+pub const F_IFNEQ: u8 = 0x81;
+
+/// Branch if float is greater than.
+pub const F_IFGT: u8 = 0x82;
+
+/// Branch if float is greater or equal.
+pub const F_IFGE: u8 = 0x83;
+
+/// Branch if float is less than.
+pub const F_IFLT: u8 = 0x84;
+
+/// Branch if float is less or equal.
+pub const F_IFLE: u8 = 0x85;
+
+/// Branch if byte is equal.
+pub const B_IFEQ: u8 = 0x86;
+
+/// Branch if byte is not equal.
+pub const B_IFNEQ: u8 = 0x87;
+
+/// Branch if byte is greater than.
+pub const B_IFGT: u8 = 0x88;
+
+/// Branch if byte is greater or equal.
+pub const B_IFGE: u8 = 0x89;
+
+/// Branch if byte is less than.
+pub const B_IFLT: u8 = 0x8A;
+
+/// Branch if byte is less or equal.
+pub const B_IFLE: u8 = 0x8B;
+
+/// Branch if references are the same object.
+pub const IF_REF_EQ: u8 = 0x8C;
+
+/// Branch if references are different objects.
+pub const IF_REF_NE: u8 = 0x8D;
+
+/// Compare numbers, push integer -1, 0 or 1.
+pub const CMP: u8 = 0x8E;
+
+/// Jump through a table indexed by an integer in `low..=high`.
+pub const TABLESWITCH: u8 = 0x8F;
+
+/// Jump to the target of an integer key among sorted pairs.
+pub const LOOKUPSWITCH: u8 = 0x90;
+
 /// Opcode repr table.
 pub const TO_STR: &[&str] = &[
   "HALT",
@@ -260,18 +452,18 @@ pub const TO_STR: &[&str] = &[
   "GOTO",
   "CALL",
   "LOADCONST",
-  "NEW_OBJECT",
-  "SET_FIELD",
-  "GET_FIELD",
+  "NEW_DICT",
+  "SET_DICT",
+  "GET_DICT",
   "I_PUSH_BYTE",
   "I_PUSH_SHORT",
   "POP",
-  "IFEQ",
-  "IFNEQ",
-  "IFGT",
-  "IFGE",
-  "IFLT",
-  "IFLE",
+  "I_IFEQ",
+  "I_IFNEQ",
+  "I_IFGT",
+  "I_IFGE",
+  "I_IFLT",
+  "I_IFLE",
   "IADD",
   "ISUB",
   "IMUL",
@@ -298,7 +490,7 @@ pub const TO_STR: &[&str] = &[
   "IFNOT_NULL",
   "CONST_NULL",
   "IEXP",
-  "ISZERO",
+  "IS_ZERO",
   "TAILCALL",
   "FADD",
   "FSUB",
@@ -324,4 +516,178 @@ pub const TO_STR: &[&str] = &[
   "CALL_METHOD",
   "SET_FIELD",
   "GET_FIELD",
+  "THROW",
+  "CLOSURE",
+  "CALL_VALUE",
+  "I2L",
+  "L2I",
+  "F2D",
+  "D2F",
+  "L2D",
+  "D2L",
+  "LADD",
+  "LSUB",
+  "LMUL",
+  "LDIV",
+  "LREM",
+  "LAND",
+  "LOR",
+  "LXOR",
+  "LSHL",
+  "LSHR",
+  "LUSHR",
+  "LNEG",
+  "DADD",
+  "DSUB",
+  "DMUL",
+  "DDIV",
+  "DREM",
+  "DNEG",
+  "L_IFEQ",
+  "L_IFNEQ",
+  "L_IFGT",
+  "L_IFGE",
+  "L_IFLT",
+  "L_IFLE",
+  "D_IFEQ",
+  "D_IFNEQ",
+  "D_IFGT",
+  "D_IFGE",
+  "D_IFLT",
+  "D_IFLE",
+  "TRUE",
+  "FALSE",
+  "NOT",
+  "IF_TRUE",
+  "IF_FALSE",
+  "I2C",
+  "C2I",
+  "STRING_GET",
+  "F_IFEQ",
+  "F_IFNEQ",
+  "F_IFGT",
+  "F_IFGE",
+  "F_IFLT",
+  "F_IFLE",
+  "B_IFEQ",
+  "B_IFNEQ",
+  "B_IFGT",
+  "B_IFGE",
+  "B_IFLT",
+  "B_IFLE",
+  "IF_REF_EQ",
+  "IF_REF_NE",
+  "CMP",
+  "TABLESWITCH",
+  "LOOKUPSWITCH",
 ];
+
+/// Instruction operand kinds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operand {
+  /// 1 byte immediate.
+  Byte,
+  /// 2 bytes immediate.
+  Short,
+  /// 1 byte local variable index.
+  Local,
+  /// 2 bytes branch target.
+  Label,
+  /// 1 byte constant pool index.
+  Constant,
+  /// 2 bytes module entry index.
+  Module,
+  /// 2 bytes function entry index.
+  Function,
+  /// 2 bytes class entry index.
+  Class,
+  /// 2 bytes field entry index.
+  Field,
+  /// 4 bytes signed immediate.
+  Int,
+}
+
+impl Operand {
+  /// The operand width in bytes.
+  pub const fn width(&self) -> usize {
+    match self {
+      Operand::Byte | Operand::Local | Operand::Constant => 1,
+      Operand::Short
+      | Operand::Label
+      | Operand::Module
+      | Operand::Function
+      | Operand::Class
+      | Operand::Field => 2,
+      Operand::Int => 4,
+    }
+  }
+}
+
+/// Operands of `opcode`, in encoding order.
+pub const fn operands(opcode: u8) -> &'static [Operand] {
+  use Operand::*;
+  match opcode {
+    LOAD | STORE => &[Local],
+    GOTO => &[Label],
+    CALL => &[Module, Function],
+    LOADCONST => &[Constant],
+    I_PUSH_BYTE | PUSH_BYTE => &[Byte],
+    I_PUSH_SHORT | NEW_BYTES => &[Short],
+    I_IFEQ | I_IFNEQ | I_IFGT | I_IFGE | I_IFLT | I_IFLE => &[Label],
+    L_IFEQ | L_IFNEQ | L_IFGT | L_IFGE | L_IFLT | L_IFLE => &[Label],
+    D_IFEQ | D_IFNEQ | D_IFGT | D_IFGE | D_IFLT | D_IFLE => &[Label],
+    F_IFEQ | F_IFNEQ | F_IFGT | F_IFGE | F_IFLT | F_IFLE => &[Label],
+    B_IFEQ | B_IFNEQ | B_IFGT | B_IFGE | B_IFLT | B_IFLE => &[Label],
+    IF_REF_EQ | IF_REF_NE => &[Label],
+    IINC => &[Local, Byte],
+    IF_NULL | IFNOT_NULL | IF_TRUE | IF_FALSE => &[Label],
+    NEW => &[Class],
+    CALL_METHOD => &[Function, Byte],
+    SET_FIELD | GET_FIELD => &[Field],
+    CLOSURE => &[Module, Function, Byte],
+    CALL_VALUE => &[Byte],
+    TABLESWITCH => &[Label, Int, Int],
+    LOOKUPSWITCH => &[Label, Short],
+    _ => &[],
+  }
+}
+
+/// Operands repeated after the fixed [`operands`] of a switch, see [`repeats`].
+pub const fn repeated_operands(opcode: u8) -> &'static [Operand] {
+  use Operand::*;
+  match opcode {
+    TABLESWITCH => &[Label],
+    LOOKUPSWITCH => &[Int, Label],
+    _ => &[],
+  }
+}
+
+/// How many times the [`repeated_operands`] follow the fixed operand `values` of `opcode`, one
+/// target per key of a `TABLESWITCH` and one key and target per pair of a `LOOKUPSWITCH`.
+pub fn repeats(opcode: u8, values: &[usize]) -> usize {
+  match opcode {
+    TABLESWITCH => {
+      let (low, high) = (values[1] as u32 as i32, values[2] as u32 as i32);
+      (high as i64 - low as i64 + 1).max(0) as usize
+    }
+    LOOKUPSWITCH => values[1],
+    _ => 0,
+  }
+}
+
+/// Instruction length in bytes, including the opcode, without the repeated operands of a switch.
+pub const fn length(opcode: u8) -> usize {
+  let operands = operands(opcode);
+  let mut length = 1;
+  let mut i = 0;
+  while i < operands.len() {
+    length += operands[i].width();
+    i += 1;
+  }
+  length
+}
+
+/// Opcode from its repr, see [`TO_STR`].
+pub fn from_str(name: &str) -> Option<u8> {
+  TO_STR.iter().position(|s| *s == name).map(|opcode| opcode as u8)
+}
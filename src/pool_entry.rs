@@ -1,7 +1,7 @@
 pub mod read;
 pub mod write;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum PoolEntry {
   String(String),
   Integer(i32),
@@ -10,6 +10,8 @@ pub enum PoolEntry {
   Function(String),
   Class(String),
   Field(String),
+  Long(i64),
+  Double(f64),
 }
 
 impl PoolEntry {
@@ -20,4 +22,6 @@ impl PoolEntry {
   pub const TAG_FUNCTION: u8 = 0x5;
   pub const TAG_CLASS: u8 = 0x6;
   pub const TAG_FIELD: u8 = 0x7;
+  pub const TAG_LONG: u8 = 0x8;
+  pub const TAG_DOUBLE: u8 = 0x9;
 }
@@ -14,6 +14,8 @@ impl PoolEntry {
       PoolEntry::TAG_FUNCTION => PoolEntry::Function(rd.read_string()?),
       PoolEntry::TAG_CLASS => PoolEntry::Class(rd.read_string()?),
       PoolEntry::TAG_FIELD => PoolEntry::Field(rd.read_string()?),
+      PoolEntry::TAG_LONG => PoolEntry::Long(rd.read_u64()? as i64),
+      PoolEntry::TAG_DOUBLE => PoolEntry::Double(rd.read_f64()?),
       _ => unreachable!(),
     };
     Ok(result)
@@ -12,6 +12,14 @@ impl PoolEntry {
         wr.write_u8(PoolEntry::TAG_FLOAT)?;
         wr.write_all(&f.to_be_bytes())?;
       }
+      PoolEntry::Long(l) => {
+        wr.write_u8(PoolEntry::TAG_LONG)?;
+        wr.write_all(&l.to_be_bytes())?;
+      }
+      PoolEntry::Double(d) => {
+        wr.write_u8(PoolEntry::TAG_DOUBLE)?;
+        wr.write_all(&d.to_be_bytes())?;
+      }
       PoolEntry::String(s)
       | PoolEntry::Module(s)
       | PoolEntry::Function(s)
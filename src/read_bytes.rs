@@ -7,8 +7,12 @@ pub trait ReadBytes {
 
   fn read_u32(&mut self) -> Result<u32>;
 
+  fn read_u64(&mut self) -> Result<u64>;
+
   fn read_f32(&mut self) -> Result<f32>;
 
+  fn read_f64(&mut self) -> Result<f64>;
+
   fn read_box_str(&mut self) -> Result<Box<str>>;
 
   fn read_rc_str(&mut self) -> Result<Rc<str>>;
@@ -38,12 +42,24 @@ where
     Ok(u32::from_be_bytes(buf))
   }
 
+  fn read_u64(&mut self) -> Result<u64> {
+    let mut buf = [0; 8];
+    self.read_exact(&mut buf)?;
+    Ok(u64::from_be_bytes(buf))
+  }
+
   fn read_f32(&mut self) -> Result<f32> {
     let mut buf = [0; 4];
     self.read_exact(&mut buf)?;
     Ok(f32::from_be_bytes(buf))
   }
 
+  fn read_f64(&mut self) -> Result<f64> {
+    let mut buf = [0; 8];
+    self.read_exact(&mut buf)?;
+    Ok(f64::from_be_bytes(buf))
+  }
+
   fn read_box_str(&mut self) -> Result<Box<str>> {
     let length = self.read_u16()?;
 
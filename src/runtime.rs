@@ -1,32 +1,49 @@
 pub mod gc;
+pub mod heap_snapshot;
+pub mod native;
 pub mod stack_trace;
 
 use core::fmt;
-use std::cell::RefCell;
+use std::{cell::RefCell, collections::HashMap};
 
 use crate::{
-  class::Class,
+  class::{self, Class},
   context::Context,
+  formatting,
   function::{Code, Function},
-  gc::Gc,
+  gc::{roots::RootSet, Collector, DictKey, Gc, ObjClass, ObjFunction},
   local::Local,
   module::Module,
   opcode,
   pool_entry::PoolEntry,
   stack::Stack,
-  value::{self, Int32, Reference, Value},
+  value::{self, Byte8, Float32, Float64, Int32, Int64, TypeMismatch, Value},
+  verifier::{VerifyError, MAX_STACK},
 };
 
+use native::NativeCtx;
+
 pub struct Runtime<'c> {
   ip: RefCell<usize>,
-  ctx: &'c mut Context<'c>,
+  ctx: Context<'c>,
   local: Local,
   current: Current,
   function: &'c Function,
   gc: Gc,
   stack: Stack<STACK_SIZE>,
+  stack_base: usize,
   call_stack: Vec<Frame<'c>>,
-  tick: RefCell<usize>,
+  /// Call stack depth of the innermost host call, frames below it belong to the host.
+  barrier: usize,
+  /// Values rooted by the running native functions, see [`NativeCtx::handle`].
+  handles: Vec<Value>,
+  /// Whether finalizers are running, they do not nest.
+  finalizing: bool,
+  /// Address of the instruction being executed, to locate its errors.
+  instruction: usize,
+  /// The string, long and double constants of each constant pool, by entry index. Strings are
+  /// interned and the longs and doubles too large for a value are boxed once.
+  constants: HashMap<*const PoolEntry, Box<[Value]>>,
 }
 
 #[derive(Clone, Copy, PartialEq, Eq)]
@@ -42,6 +59,7 @@ pub trait RuntimeVisitor {
 struct Frame<'c> {
   return_address: RefCell<usize>,
   local_frame: usize,
+  stack_base: usize,
   current: Current,
   function: &'c Function,
 }
@@ -56,19 +74,23 @@ impl fmt::Debug for Frame<'_> {
 }
 
 const STACK_SIZE: usize = 0x800;
+const MAX_FRAMES: usize = 0x10000;
 const MAIN: &str = "main";
 const IP_INIT: usize = 0;
-const GC_TICK: usize = 100_000_000;
 
 pub struct BootOptions<'c> {
   pub entrypoint_module: Option<String>,
-  pub context: &'c mut Context<'c>,
+  pub context: Context<'c>,
+  /// Heap size limit in bytes, unlimited when `None`.
+  pub max_heap: Option<usize>,
+  /// The collection strategy, generational by default.
+  pub collector: Collector,
 }
 
 impl<'c> Runtime<'c> {
   #[inline(always)]
-  fn new(
-    ctx: &'c mut Context<'c>,
+  pub(crate) fn new(
+    ctx: Context<'c>,
     local: Local,
     module: &'c Module,
     function: &'c Function,
@@ -81,13 +103,18 @@ impl<'c> Runtime<'c> {
       current: Current::Module(module),
       gc: Gc::new(),
       stack: Stack::<STACK_SIZE>::new(),
+      stack_base: 0,
       call_stack: Vec::new(),
-      tick: RefCell::new(0),
+      barrier: 0,
+      handles: Vec::new(),
+      finalizing: false,
+      instruction: 0,
+      constants: HashMap::new(),
     }
   }
 
   #[inline(always)]
-  pub fn boot(opts: BootOptions<'c>) -> Result<Runtime<'c>> {
+  pub fn boot(mut opts: BootOptions<'c>) -> Result<Runtime<'c>> {
     let module = if let Some(entrypoint_module) = opts.entrypoint_module {
       opts.context.fetch_module(&entrypoint_module)?
     } else {
@@ -98,7 +125,11 @@ impl<'c> Runtime<'c> {
 
     let local = Local::new(function.locals as usize);
 
-    Ok(Runtime::new(opts.context, local, module, function))
+    let mut runtime = Runtime::new(opts.context, local, module, function);
+    runtime.gc = Gc::with_collector(opts.collector);
+    runtime.gc.set_max_heap(opts.max_heap);
+    runtime.intern_constants()?;
+    Ok(runtime)
   }
 
   #[inline(always)]
@@ -116,6 +147,7 @@ impl<'c> Runtime<'c> {
       }
     }
 
+    self.check_overflow()?;
     let frame = self.local.push_frame(function.locals as usize);
 
     self.stack.check_underflow(function.arguments as usize)?;
@@ -128,6 +160,35 @@ impl<'c> Runtime<'c> {
     Ok(())
   }
 
+  /// Call the function value `function_ref` with its captures followed by `arguments` stack values.
+  fn call_value(&mut self, function_ref: value::Function, arguments: usize) -> Result<()> {
+    let closure = unsafe { &*(function_ref as *const ObjFunction) };
+    let (module, function) = unsafe { (&*closure.module, &*closure.function) };
+
+    let captures = closure.captures.len();
+    if captures + arguments != function.arguments as usize {
+      return Err(Error::Arity {
+        function: format!("{}:{}", module.name, function.name),
+        expected: function.arguments as usize - captures.min(function.arguments as usize),
+        found: arguments,
+      });
+    }
+
+    self.stack.check_underflow(arguments)?;
+    self.check_overflow()?;
+    let frame = self.local.push_frame(function.locals as usize);
+    for (index, value) in closure.captures.iter().enumerate() {
+      self.local.store(index, *value);
+    }
+    for index in (captures..captures + arguments).rev() {
+      self.local.store(index, self.stack.pop_unchecked());
+    }
+
+    self.push_frame(frame, Current::Module(module), function);
+
+    Ok(())
+  }
+
   #[inline(always)]
   pub fn fetch_constant(&self, entry_index: usize) -> &'c PoolEntry {
     match self.current {
@@ -136,30 +197,183 @@ impl<'c> Runtime<'c> {
     }
   }
 
-  // #[inline(always)]
+  /// Intern the string constants of the loaded modules and classes, see [`Gc::intern`], and box
+  /// their large long and double constants.
+  pub(crate) fn intern_constants(&mut self) -> Result<()> {
+    let modules = self.ctx.modules.values().map(|module| &module.constants[..]);
+    let pools = modules.chain(self.ctx.classes.values().map(|class| &class.constants[..]));
+    for pool in pools.collect::<Vec<_>>() {
+      self.intern_pool(pool)?;
+    }
+    Ok(())
+  }
+
+  fn intern_pool(&mut self, pool: &'c [PoolEntry]) -> Result<()> {
+    if self.constants.contains_key(&pool.as_ptr()) {
+      return Ok(());
+    }
+    let mut constants = vec![Value::NULL; pool.len()];
+    for (constant, entry) in constants.iter_mut().zip(pool) {
+      *constant = match entry {
+        PoolEntry::String(s) => self.gc.intern(s)?,
+        PoolEntry::Long(l) => Value::try_mk_long(*l).map_or_else(|| self.gc.alloc_long(*l), Ok)?,
+        PoolEntry::Double(d) => {
+          Value::try_mk_double(*d).map_or_else(|| self.gc.alloc_double(*d), Ok)?
+        }
+        _ => continue,
+      };
+    }
+    self.constants.insert(pool.as_ptr(), constants.into());
+    Ok(())
+  }
+
+  /// The string, long or double constant at `entry_index`, preparing the pool of a module loaded
+  /// after the runtime was created.
+  fn constant(&mut self, entry_index: usize) -> Result<Value> {
+    let pool = match self.current {
+      Current::Module(module) => &unsafe { &*module }.constants[..],
+      Current::Class(class) => &unsafe { &*class }.constants[..],
+    };
+    self.intern_pool(pool)?;
+    Ok(self.constants[&pool.as_ptr()][entry_index])
+  }
+
   pub fn run(&mut self) -> Result<()> {
+    self.execute().map_err(|error| self.uncaught(error))
+  }
+
+  /// Run until `HALT` or the return of the innermost host call, leaving uncaught exceptions as
+  /// [`Error::Throw`].
+  fn execute(&mut self) -> Result<()> {
     loop {
-      let tick = self.tick.get_mut();
-      *tick += 1;
-      if *tick == GC_TICK {
-        *tick = 0;
-        self.gc.mark_sweep(&self.local, &self.stack);
+      match self.dispatch() {
+        Ok(()) => break Ok(()),
+        Err(error) => self.throw_error(error)?,
       }
-      match self.function.code {
+    }
+  }
+
+  /// Call `module:function` with `args` and run it to completion, returning its result.
+  ///
+  /// Exceptions thrown by the callee only reach handlers of frames above the call, an uncaught
+  /// exception or error unwinds back to the caller state and is returned.
+  pub fn call_function(
+    &mut self,
+    module_name: &str,
+    function_name: &str,
+    args: &[Value],
+  ) -> Result<Option<Value>> {
+    self.invoke(module_name, function_name, args).map_err(|error| self.uncaught(error))
+  }
+
+  /// Call the function value `function` with `args` and run it to completion, like
+  /// [`Runtime::call_function`].
+  pub fn call_closure(&mut self, function: Value, args: &[Value]) -> Result<Option<Value>> {
+    self.invoke_value(function, args).map_err(|error| self.uncaught(error))
+  }
+
+  /// [`Runtime::call_function`] keeping uncaught exceptions as [`Error::Throw`], so a native
+  /// returning the error rethrows the exception to its own callers.
+  pub(crate) fn invoke(
+    &mut self,
+    module_name: &str,
+    function_name: &str,
+    args: &[Value],
+  ) -> Result<Option<Value>> {
+    let function = self.ctx.fetch_module(module_name)?.fetch_function_with_name(function_name)?;
+    if function.arguments as usize != args.len() {
+      return Err(Error::Arity {
+        function: format!("{module_name}:{function_name}"),
+        expected: function.arguments as usize,
+        found: args.len(),
+      });
+    }
+
+    self.host_call(args, |rt| rt.call(module_name, function_name))
+  }
+
+  pub(crate) fn invoke_value(&mut self, function: Value, args: &[Value]) -> Result<Option<Value>> {
+    if function.tag() != Value::TAG_FUNCTION {
+      return Err(Error::NotCallable(formatting::display_value(&function, &self.gc).to_string()));
+    }
+    self.host_call(args, |rt| rt.call_value(function.reference(), args.len()))
+  }
+
+  fn host_call(
+    &mut self,
+    args: &[Value],
+    enter: impl FnOnce(&mut Self) -> Result<()>,
+  ) -> Result<Option<Value>> {
+    self.stack.check_overflow(args.len())?;
+    let sp = self.stack.sp();
+    for arg in args {
+      self.stack.push(*arg);
+    }
+    let barrier = std::mem::replace(&mut self.barrier, self.call_stack.len() + 1);
+    let result = enter(self).and_then(|()| self.execute());
+    let result = result.map(|()| (self.stack.sp() > sp).then(|| self.stack.pop_unchecked()));
+
+    // Drop the frames left by `HALT` or an error.
+    while self.call_stack.len() >= self.barrier {
+      self.pop_frame();
+    }
+    self.stack.truncate(sp);
+    self.barrier = barrier;
+    result
+  }
+
+  /// The loaded modules and classes.
+  pub fn context(&self) -> &Context<'c> {
+    &self.ctx
+  }
+
+  pub(crate) fn context_mut(&mut self) -> &mut Context<'c> {
+    &mut self.ctx
+  }
+
+  pub fn gc(&self) -> &Gc {
+    &self.gc
+  }
+
+  pub fn gc_mut(&mut self) -> &mut Gc {
+    &mut self.gc
+  }
+
+  // #[inline(always)]
+  fn dispatch(&mut self) -> Result<()> {
+    loop {
+      let function = self.function;
+      match function.code {
         Code::Native(ref native) => {
-          if let Some(value) = native(&mut self.local, &mut self.gc)? {
-            self.stack.push(value);
-          }
+          let handles = self.handles.len();
+          let result = native(&mut NativeCtx::new(self));
+          self.handles.truncate(handles);
+          self.stack.push(result?.unwrap_or(Value::NULL));
           self.pop_frame();
+          if self.call_stack.len() < self.barrier {
+            break Ok(());
+          }
         }
         Code::Bytecode(ref program) => {
+          if self.gc.has_finalizers() && !self.finalizing {
+            self.run_finalizers();
+          }
+          self.instruction = *self.ip.get_mut();
           let instruction = self.fetch(program);
 
           // println!("{}", opcode::TO_STR[instruction as usize]);
           match instruction {
             opcode::HALT => break Ok(()),
 
-            opcode::RETURN => self.pop_frame(),
+            opcode::RETURN => {
+              if self.stack.sp() == self.stack_base {
+                self.stack.push(Value::NULL);
+              }
+              self.pop_frame();
+              if self.call_stack.len() < self.barrier {
+                break Ok(());
+              }
+            }
 
             opcode::ICONST_0 => self.stack.iconst_0(),
             opcode::ICONST_1 => self.stack.iconst_1(),
@@ -189,12 +403,119 @@ impl<'c> Runtime<'c> {
             opcode::I2F => self.stack.i2f()?,
             opcode::F2I => self.stack.f2i()?,
 
+            opcode::I2L => {
+              let value: Int32 = self.stack.pop()?.try_into()?;
+              self.push_long(value as Int64)?;
+            }
+            opcode::L2I => {
+              let value: Int64 = self.stack.pop()?.try_into()?;
+              self.stack.push(Value::mk_integer(value as Int32));
+            }
+            opcode::F2D => {
+              let value: Float32 = self.stack.pop()?.try_into()?;
+              self.push_double(value as Float64)?;
+            }
+            opcode::D2F => {
+              let value: Float64 = self.stack.pop()?.try_into()?;
+              self.stack.push(Value::mk_float(value as Float32));
+            }
+            opcode::L2D => {
+              let value: Int64 = self.stack.pop()?.try_into()?;
+              self.push_double(value as Float64)?;
+            }
+            opcode::D2L => {
+              let value: Float64 = self.stack.pop()?.try_into()?;
+              self.push_long(value as Int64)?;
+            }
+
+            opcode::LADD => self.long_binary(Int64::wrapping_add)?,
+            opcode::LSUB => self.long_binary(Int64::wrapping_sub)?,
+            opcode::LMUL => self.long_binary(Int64::wrapping_mul)?,
+            opcode::LDIV => self.long_division(Int64::wrapping_div)?,
+            opcode::LREM => self.long_division(Int64::wrapping_rem)?,
+            opcode::LAND => self.long_binary(|value1, value2| value1 & value2)?,
+            opcode::LOR => self.long_binary(|value1, value2| value1 | value2)?,
+            opcode::LXOR => self.long_binary(|value1, value2| value1 ^ value2)?,
+            opcode::LSHL => self.long_shift(Int64::wrapping_shl)?,
+            opcode::LSHR => self.long_shift(Int64::wrapping_shr)?,
+            opcode::LUSHR => {
+              self.long_shift(|value, shift| (value as u64).wrapping_shr(shift) as Int64)?
+            }
+            opcode::LNEG => {
+              let value: Int64 = self.stack.pop()?.try_into()?;
+              self.push_long(value.wrapping_neg())?;
+            }
+
+            opcode::DADD => self.double_binary(|value1, value2| value1 + value2)?,
+            opcode::DSUB => self.double_binary(|value1, value2| value1 - value2)?,
+            opcode::DMUL => self.double_binary(|value1, value2| value1 * value2)?,
+            opcode::DDIV => self.double_binary(|value1, value2| value1 / value2)?,
+            opcode::DREM => self.double_binary(|value1, value2| value1 % value2)?,
+            opcode::DNEG => {
+              let value: Float64 = self.stack.pop()?.try_into()?;
+              self.push_double(-value)?;
+            }
+
+            opcode::L_IFEQ => self.compare_branch(program, Int64::eq)?,
+            opcode::L_IFNEQ => self.compare_branch(program, Int64::ne)?,
+            opcode::L_IFGT => self.compare_branch(program, Int64::gt)?,
+            opcode::L_IFGE => self.compare_branch(program, Int64::ge)?,
+            opcode::L_IFLT => self.compare_branch(program, Int64::lt)?,
+            opcode::L_IFLE => self.compare_branch(program, Int64::le)?,
+
+            opcode::D_IFEQ => self.compare_branch(program, Float64::eq)?,
+            opcode::D_IFNEQ => self.compare_branch(program, Float64::ne)?,
+            opcode::D_IFGT => self.compare_branch(program, Float64::gt)?,
+            opcode::D_IFGE => self.compare_branch(program, Float64::ge)?,
+            opcode::D_IFLT => self.compare_branch(program, Float64::lt)?,
+            opcode::D_IFLE => self.compare_branch(program, Float64::le)?,
+
+            opcode::F_IFEQ => self.compare_branch(program, Float32::eq)?,
+            opcode::F_IFNEQ => self.compare_branch(program, Float32::ne)?,
+            opcode::F_IFGT => self.compare_branch(program, Float32::gt)?,
+            opcode::F_IFGE => self.compare_branch(program, Float32::ge)?,
+            opcode::F_IFLT => self.compare_branch(program, Float32::lt)?,
+            opcode::F_IFLE => self.compare_branch(program, Float32::le)?,
+
+            opcode::B_IFEQ => self.compare_branch(program, Byte8::eq)?,
+            opcode::B_IFNEQ => self.compare_branch(program, Byte8::ne)?,
+            opcode::B_IFGT => self.compare_branch(program, Byte8::gt)?,
+            opcode::B_IFGE => self.compare_branch(program, Byte8::ge)?,
+            opcode::B_IFLT => self.compare_branch(program, Byte8::lt)?,
+            opcode::B_IFLE => self.compare_branch(program, Byte8::le)?,
+
+            opcode::IF_REF_EQ => self.reference_branch(program, Value::eq)?,
+            opcode::IF_REF_NE => self.reference_branch(program, Value::ne)?,
+
+            opcode::CMP => self.stack.cmp()?,
+
+            opcode::TABLESWITCH => {
+              let key: Int32 = self.stack.pop()?.try_into()?;
+              let default = self.fetch_2(program) as usize;
+              let low = self.fetch_4(program) as Int32;
+              let high = self.fetch_4(program) as Int32;
+              *self.ip.get_mut() = match (low..=high).contains(&key) {
+                true => {
+                  let target = *self.ip.get_mut() + 2 * (key as i64 - low as i64) as usize;
+                  u16::from_be_bytes([program[target], program[target + 1]]) as usize
+                }
+                false => default,
+              };
+            }
+            opcode::LOOKUPSWITCH => {
+              let key: Int32 = self.stack.pop()?.try_into()?;
+              let default = self.fetch_2(program) as usize;
+              let pairs = self.fetch_2(program) as usize;
+              let ip = *self.ip.get_mut();
+              *self.ip.get_mut() = lookup(&program[ip..ip + 6 * pairs], key).unwrap_or(default);
+            }
+
             opcode::GOTO => *self.ip.get_mut() = self.fetch_2(program) as usize,
 
             opcode::CALL => {
               let indexes = self.fetch_4(program);
               let module_index = indexes >> 16;
-              let function_index = indexes & 0xFF;
+              let function_index = indexes & 0xFFFF;
 
               if let PoolEntry::Module(module_name) = self.fetch_constant(module_index) {
                 if let PoolEntry::Function(function_name) = self.fetch_constant(function_index) {
@@ -210,27 +531,47 @@ impl<'c> Runtime<'c> {
             opcode::LOADCONST => {
               let entry_index = self.fetch(program) as usize;
               match self.fetch_constant(entry_index) {
-                PoolEntry::String(s) => self.stack.push(self.gc.alloc_string(s.clone())),
+                PoolEntry::String(_) | PoolEntry::Long(_) | PoolEntry::Double(_) => {
+                  let value = self.constant(entry_index)?;
+                  self.stack.push(value)
+                }
                 PoolEntry::Integer(i) => self.stack.push(Value::mk_integer(*i)),
                 PoolEntry::Float(f) => self.stack.push(Value::mk_float(*f)),
                 _ => Err(Error::InvalidEntry(entry_index))?,
               }
             }
 
-            opcode::NEW_DICT => self.stack.push(self.gc.alloc_dict()),
+            opcode::NEW_DICT => {
+              self.safepoint(0)?;
+              self.stack.push(self.gc.alloc_dict()?)
+            }
             opcode::SET_DICT => {
               self.stack.check_underflow(3)?;
+              self.safepoint(size_of::<[Value; 2]>())?;
               let value = self.stack.pop_unchecked();
               let field = self.stack.pop_unchecked();
-              let obj_ref: value::Dict = self.stack.pop_unchecked().into();
-
-              Gc::set_dict(obj_ref, field, value);
+              let object = self.stack.pop_unchecked();
+              let obj_ref: value::Dict = object.object(Value::TAG_DICT)?;
+
+              self.gc.write_barrier(object, field);
+              self.gc.write_barrier(object, value);
+              let old = Gc::set_dict(obj_ref, field, value);
+              if let Err(e) = self.gc.resize(object) {
+                // Only a new key grows the dict.
+                if old.is_none() {
+                  Gc::get_dict_fields(obj_ref).remove(&DictKey(field));
+                }
+                Err(e)?
+              }
             }
             opcode::GET_DICT => {
               self.stack.check_underflow(2)?;
               let field = self.stack.pop_unchecked();
-              let obj_ref: value::Dict = self.stack.pop_unchecked().into();
-              self.stack.push(Gc::get_dict(obj_ref, field));
+              let obj_ref: value::Dict = self.stack.pop_unchecked().object(Value::TAG_DICT)?;
+              let value = Gc::get_dict(obj_ref, field).ok_or_else(|| {
+                Error::KeyNotFound(formatting::display_value(&field, &self.gc).to_string())
+              })?;
+              self.stack.push(value);
             }
 
             opcode::I_PUSH_BYTE => {
@@ -306,38 +647,42 @@ impl<'c> Runtime<'c> {
 
             opcode::NEW_ARRAY => {
               self.stack.check_underflow(1)?;
-              let size: Int32 = self.stack.pop_unchecked().into();
-              self.stack.push(self.gc.alloc_array(size));
+              let size: Int32 = self.stack.pop_unchecked().try_into()?;
+              if size < 0 {
+                return Err(Error::NegativeArraySize(size));
+              }
+              self.safepoint((size as usize).saturating_mul(size_of::<Value>()))?;
+              self.stack.push(self.gc.alloc_array(size)?);
             }
 
             opcode::ARRAY_GET => {
               self.stack.check_underflow(2)?;
-              let index: Int32 = self.stack.pop_unchecked().into();
-              let array_ref: value::Array = self.stack.pop_unchecked().into();
+              let index: Int32 = self.stack.pop_unchecked().try_into()?;
+              let array_ref: value::Array = self.stack.pop_unchecked().object(Value::TAG_ARRAY)?;
 
-              self.stack.push(Gc::array_get(array_ref, index));
+              self.stack.push(Gc::array_get(array_ref, index)?);
             }
 
             opcode::ARRAY_SET => {
               self.stack.check_underflow(3)?;
               let value = self.stack.pop_unchecked();
-              let index: Int32 = self.stack.pop_unchecked().into();
-              let array_ref: value::Array = self.stack.pop_unchecked().into();
+              let index: Int32 = self.stack.pop_unchecked().try_into()?;
+              let array = self.stack.pop_unchecked();
+              let array_ref: value::Array = array.object(Value::TAG_ARRAY)?;
 
-              Gc::array_set(array_ref, index, value);
+              self.gc.write_barrier(array, value);
+              Gc::array_set(array_ref, index, value)?;
             }
 
             opcode::IINC => {
               let index = self.fetch(program) as usize;
               let inc = self.fetch(program) as i32;
-              self.local.iinc(index, inc);
+              self.local.iinc(index, inc)?;
             }
 
             opcode::IF_NULL => {
               self.stack.check_underflow(1)?;
-              let r#ref: Reference = self.stack.pop_unchecked().into();
-
-              if r#ref == 0 {
+              if !self.stack.pop_unchecked().is_not_null() {
                 *self.ip.get_mut() = self.fetch_2(program) as usize;
               } else {
                 *self.ip.get_mut() += 2;
@@ -346,9 +691,7 @@ impl<'c> Runtime<'c> {
 
             opcode::IFNOT_NULL => {
               self.stack.check_underflow(1)?;
-              let r#ref: Reference = self.stack.pop_unchecked().into();
-
-              if r#ref != 0 {
+              if self.stack.pop_unchecked().is_not_null() {
                 *self.ip.get_mut() = self.fetch_2(program) as usize;
               } else {
                 *self.ip.get_mut() += 2;
@@ -357,6 +700,40 @@ impl<'c> Runtime<'c> {
 
             opcode::CONST_NULL => self.stack.push(Value::NULL),
 
+            opcode::TRUE => self.stack.push(Value::mk_bool(true)),
+            opcode::FALSE => self.stack.push(Value::mk_bool(false)),
+            opcode::NOT => self.stack.not()?,
+            opcode::IF_TRUE => {
+              let value: bool = self.stack.pop()?.try_into()?;
+              self.branch(program, value);
+            }
+            opcode::IF_FALSE => {
+              let value: bool = self.stack.pop()?.try_into()?;
+              self.branch(program, !value);
+            }
+
+            opcode::I2C => {
+              let value: Int32 = self.stack.pop()?.try_into()?;
+              let char = char::from_u32(value as u32)
+                .ok_or(Error::Conversion { expected: "char", found: "integer" })?;
+              self.stack.push(Value::mk_char(char));
+            }
+            opcode::C2I => self.stack.c2i()?,
+
+            opcode::STRING_GET => {
+              self.stack.check_underflow(2)?;
+              let index: Int32 = self.stack.pop_unchecked().try_into()?;
+              let string_ref: value::String =
+                self.stack.pop_unchecked().object(Value::TAG_STRING)?;
+              let string = Gc::get_string(string_ref);
+              let char = usize::try_from(index).ok().and_then(|index| string.chars().nth(index));
+              let char = char.ok_or_else(|| Error::IndexOutOfBounds {
+                index: index as i64,
+                len: string.chars().count(),
+              })?;
+              self.stack.push(Value::mk_char(char));
+            }
+
             opcode::IEXP => self.stack.iexp()?,
 
             opcode::IS_ZERO => self.stack.is_zero()?,
@@ -393,9 +770,31 @@ impl<'c> Runtime<'c> {
             opcode::BSHR => self.stack.bshr()?,
             opcode::BNEG => self.stack.bneg()?,
 
-            // todo: remove
-            opcode::NEW_BYTES => panic!(),
-            opcode::BYTES_PUSH => panic!(),
+            opcode::NEW_BYTES => {
+              let len = self.fetch_2(program) as usize;
+              self.stack.check_underflow(len)?;
+              self.safepoint(len)?;
+              let mut bytes = vec![0; len];
+              for byte in bytes.iter_mut().rev() {
+                *byte = self.stack.pop_unchecked().try_into()?;
+              }
+              self.stack.push(self.gc.alloc_bytes(bytes)?);
+            }
+            opcode::BYTES_PUSH => {
+              self.stack.check_underflow(2)?;
+              self.safepoint(1)?;
+              let byte = self.stack.pop_unchecked().try_into()?;
+              let bytes = self.stack.pop_unchecked();
+              let bytes_ref: value::Bytes = bytes.object(Value::TAG_BYTES)?;
+              let contents = Gc::get_bytes(bytes_ref);
+              let capacity = contents.capacity();
+              contents.push(byte);
+              if let Err(e) = self.gc.resize(bytes) {
+                contents.pop();
+                contents.shrink_to(capacity);
+                Err(e)?
+              }
+            }
 
             opcode::NEW => {
               let class_index = self.fetch_2(program) as usize;
@@ -403,10 +802,12 @@ impl<'c> Runtime<'c> {
                 let class = self.ctx.fetch_class(class_name)?;
                 let fields = class.fields.len();
 
-                let class_ref = self.gc.class(fields, class);
+                self.safepoint(fields * size_of::<Value>())?;
+                let class_ref = self.gc.class(fields, class)?;
 
                 let constructor = class.fetch_function_with_name_unchecked("new");
 
+                self.check_overflow()?;
                 let frame = self.local.push_frame(constructor.locals as usize);
                 self.local.store(0, class_ref);
 
@@ -420,21 +821,31 @@ impl<'c> Runtime<'c> {
             }
             opcode::CALL_METHOD => {
               let method_index = self.fetch_2(program) as usize;
+              let arguments = self.fetch(program);
 
               if let PoolEntry::Function(function_name) = self.fetch_constant(method_index) {
-                let class_ref: value::Class = self.stack.pop()?.into();
+                let class_ref: value::Class = self.stack.pop()?.object(Value::TAG_CLASS)?;
 
                 let (class, function) = Gc::call_method(class_ref, function_name)?;
+                let function = unsafe { &*function };
+                if function.arguments != arguments {
+                  Err(Error::Arity {
+                    function: format!("{}:{}", unsafe { &*class }.name, function.name),
+                    expected: function.arguments as usize,
+                    found: arguments as usize,
+                  })?
+                }
 
-                let frame = self.local.push_frame(unsafe { (*function).locals as usize });
+                self.stack.check_underflow(function.arguments as usize)?;
+                self.check_overflow()?;
+                let frame = self.local.push_frame(function.locals as usize);
                 self.local.store(0, Value::new(Value::TAG_CLASS, class_ref as u64));
 
-                self.stack.check_underflow(unsafe { (*function).arguments as usize })?;
-                for index in (1..unsafe { (*function).arguments + 1 }).rev() {
+                for index in (1..function.arguments + 1).rev() {
                   self.local.store(index as usize, self.stack.pop_unchecked());
                 }
 
-                self.push_frame(frame, Current::Class(class), unsafe { &*function });
+                self.push_frame(frame, Current::Class(class), function);
               } else {
                 Err(Error::InvalidEntry(method_index))?
               }
@@ -446,23 +857,63 @@ impl<'c> Runtime<'c> {
               if let PoolEntry::Field(field_name) = self.fetch_constant(field_index) {
                 self.stack.check_underflow(2)?;
                 let value = self.stack.pop_unchecked();
-                let class_ref: value::Class = self.stack.pop_unchecked().into();
+                let object = self.stack.pop_unchecked();
+                let class_ref: value::Class = object.object(Value::TAG_CLASS)?;
 
+                self.gc.write_barrier(object, value);
                 Gc::set_field2(class_ref, field_name, value);
               }
             }
 
+            opcode::THROW => {
+              let exception = self.stack.pop()?;
+              self.throw(exception)?;
+            }
+
             opcode::GET_FIELD => {
               let field_index = self.fetch_2(program) as usize;
 
               if let PoolEntry::Field(field_name) = self.fetch_constant(field_index) {
-                let class_ref: value::Class = self.stack.pop()?.into();
+                let class_ref: value::Class = self.stack.pop()?.object(Value::TAG_CLASS)?;
                 self.stack.push(Gc::get_field2(class_ref, field_name));
               } else {
                 Err(Error::InvalidEntry(field_index))?
               }
             }
 
+            opcode::CLOSURE => {
+              let indexes = self.fetch_4(program);
+              let module_index = indexes >> 16;
+              let function_index = indexes & 0xFFFF;
+              let captures = self.fetch(program) as usize;
+
+              let PoolEntry::Module(module_name) = self.fetch_constant(module_index) else {
+                Err(Error::InvalidEntry(module_index))?
+              };
+              let PoolEntry::Function(function_name) = self.fetch_constant(function_index) else {
+                Err(Error::InvalidEntry(function_index))?
+              };
+              let module = self.ctx.fetch_module(module_name)?;
+              let function = module.fetch_function_with_name(function_name)?;
+
+              self.stack.check_underflow(captures)?;
+              self.safepoint(captures * size_of::<Value>())?;
+              let mut values = vec![Value::NULL; captures].into_boxed_slice();
+              for value in values.iter_mut().rev() {
+                *value = self.stack.pop_unchecked();
+              }
+              self.stack.push(self.gc.alloc_function(module, function, values)?);
+            }
+
+            opcode::CALL_VALUE => {
+              let arguments = self.fetch(program) as usize;
+              let value = self.stack.pop()?;
+              if value.tag() != Value::TAG_FUNCTION {
+                Err(Error::NotCallable(formatting::display_value(&value, &self.gc).to_string()))?
+              }
+              self.call_value(value.reference(), arguments)?;
+            }
+
             opcode => unreachable!("Reached unknown opcode {opcode:X?}"),
           }
         }
@@ -470,22 +921,336 @@ impl<'c> Runtime<'c> {
     }
   }
 
+  /// Push `long`, boxing it when it does not fit in a value.
+  fn push_long(&mut self, long: Int64) -> Result<()> {
+    let value = match Value::try_mk_long(long) {
+      Some(value) => value,
+      None => {
+        self.safepoint(size_of::<Int64>())?;
+        self.gc.alloc_long(long)?
+      }
+    };
+    self.stack.push(value);
+    Ok(())
+  }
+
+  /// Push `double`, boxing it when it does not fit in a value.
+  fn push_double(&mut self, double: Float64) -> Result<()> {
+    let value = match Value::try_mk_double(double) {
+      Some(value) => value,
+      None => {
+        self.safepoint(size_of::<Float64>())?;
+        self.gc.alloc_double(double)?
+      }
+    };
+    self.stack.push(value);
+    Ok(())
+  }
+
+  fn long_binary(&mut self, op: impl FnOnce(Int64, Int64) -> Int64) -> Result<()> {
+    self.stack.check_underflow(2)?;
+    let value2: Int64 = self.stack.pop_unchecked().try_into()?;
+    let value1: Int64 = self.stack.pop_unchecked().try_into()?;
+    self.push_long(op(value1, value2))
+  }
+
+  /// Divide longs, failing on a zero divisor.
+  fn long_division(&mut self, op: impl FnOnce(Int64, Int64) -> Int64) -> Result<()> {
+    self.stack.check_underflow(2)?;
+    let value2: Int64 = self.stack.pop_unchecked().try_into()?;
+    let value1: Int64 = self.stack.pop_unchecked().try_into()?;
+    if value2 == 0 {
+      return Err(Error::DivisionByZero);
+    }
+    self.push_long(op(value1, value2))
+  }
+
+  /// Shift a long by an integer count, taken modulo 64.
+  fn long_shift(&mut self, op: impl FnOnce(Int64, u32) -> Int64) -> Result<()> {
+    self.stack.check_underflow(2)?;
+    let shift: Int32 = self.stack.pop_unchecked().try_into()?;
+    let value: Int64 = self.stack.pop_unchecked().try_into()?;
+    self.push_long(op(value, shift as u32))
+  }
+
+  fn double_binary(&mut self, op: impl FnOnce(Float64, Float64) -> Float64) -> Result<()> {
+    self.stack.check_underflow(2)?;
+    let value2: Float64 = self.stack.pop_unchecked().try_into()?;
+    let value1: Float64 = self.stack.pop_unchecked().try_into()?;
+    self.push_double(op(value1, value2))
+  }
+
+  /// Compare the two operands of type `T` and branch on the result.
+  fn compare_branch<T>(&mut self, program: &[u8], op: impl FnOnce(&T, &T) -> bool) -> Result<()>
+  where
+    T: TryFrom<Value, Error = TypeMismatch>,
+  {
+    self.stack.check_underflow(2)?;
+    let value2 = T::try_from(self.stack.pop_unchecked())?;
+    let value1 = T::try_from(self.stack.pop_unchecked())?;
+    self.branch(program, op(&value1, &value2));
+    Ok(())
+  }
+
+  /// Compare two references or `null` by identity and branch on the result.
+  fn reference_branch(
+    &mut self,
+    program: &[u8],
+    op: impl FnOnce(&Value, &Value) -> bool,
+  ) -> Result<()> {
+    self.stack.check_underflow(2)?;
+    let value2 = self.stack.pop_unchecked();
+    let value1 = self.stack.pop_unchecked();
+    for value in [value1, value2] {
+      if value.is_not_null() && (!value.is_reference() || value.is_boxed()) {
+        Err(TypeMismatch { expected: "reference", found: value.type_name() })?
+      }
+    }
+    self.branch(program, op(&value1, &value2));
+    Ok(())
+  }
+
+  /// Jump to the label operand when `taken`, skip it otherwise.
+  fn branch(&mut self, program: &[u8], taken: bool) {
+    if taken {
+      *self.ip.get_mut() = self.fetch_2(program) as usize;
+    } else {
+      *self.ip.get_mut() += 2;
+    }
+  }
+
+  /// Throw `exception` to the closest handler that catches it.
+  fn throw(&mut self, exception: Value) -> Result<()> {
+    let class = unsafe { Self::class_name(exception) };
+    match self.find_handler(class) {
+      Some((depth, handler)) => {
+        self.unwind(depth, handler, exception);
+        Ok(())
+      }
+      None => Err(Error::Throw(exception)),
+    }
+  }
+
+  fn uncaught(&self, error: Error) -> Error {
+    match error {
+      Error::Throw(exception) => Error::Uncaught(self.describe_exception(exception)),
+      error => error,
+    }
+  }
+
+  /// The current function and instruction, as printed in stack traces.
+  fn site(&self) -> String {
+    let name = match self.current {
+      Current::Module(module) => &unsafe { &*module }.name,
+      Current::Class(class) => &unsafe { &*class }.name,
+    };
+    format!("{name}:{}%{}", self.function.name, self.ip.borrow())
+  }
+
+  /// Collect before allocating `size` bytes when the nursery is full or the allocation does not fit
+  /// in the heap limit, every live reference must be rooted.
+  #[inline(always)]
+  pub(crate) fn safepoint(&mut self, size: usize) -> Result<()> {
+    if self.gc.should_collect() {
+      self.collect(false)?;
+    }
+    if !self.gc.fits(size) {
+      self.collect(true)?;
+    }
+    Ok(())
+  }
+
+  /// Run a collection from the root set, collecting both generations when `major`, and update the
+  /// roots a copying collection moved.
+  pub(crate) fn collect(&mut self, major: bool) -> Result<()> {
+    let roots = self.roots();
+    match major {
+      true => self.gc.collect_major(&roots)?,
+      false => self.gc.collect(&roots)?,
+    }
+    let roots = self.stack.iter_mut().chain(self.local.iter_mut()).chain(self.handles.iter_mut());
+    let constants = self.constants.values_mut().flat_map(|constants| constants.iter_mut());
+    self.gc.relocate(roots.chain(constants));
+    Ok(())
+  }
+
+  /// Run the finalizers of the objects found unreachable by the last collections.
+  ///
+  /// Errors thrown by a finalizer cannot reach the interrupted code, they are reported to stderr.
+  fn run_finalizers(&mut self) {
+    self.finalizing = true;
+    while let Some(object) = self.gc.next_finalizer() {
+      // The finalizer can move the object.
+      let class = unsafe { Self::class_name(object) }.unwrap_or_default();
+      let result = self.host_call(&[object], |rt| {
+        let (class, function) = Gc::call_method(object.reference(), Class::FINALIZER)?;
+        let function = unsafe { &*function };
+        let frame = rt.local.push_frame(function.locals as usize);
+        rt.local.store(0, rt.stack.pop_unchecked());
+        rt.push_frame(frame, Current::Class(class), function);
+        Ok(())
+      });
+      if let Err(error) = result {
+        eprintln!("Error in finalizer {class}:{}: {}", Class::FINALIZER, self.uncaught(error));
+      }
+    }
+    self.finalizing = false;
+  }
+
+  /// The live operand stack, the locals of every frame and the native handles. The handles and
+  /// the arguments of native functions are pinned, as natives keep copies of them.
+  fn roots(&self) -> RootSet {
+    let mut roots = RootSet::new();
+    roots.extend(self.stack.iter());
+    roots.extend(self.local.iter());
+    roots.pin(&self.handles);
+    roots.extend(self.gc.interned());
+    roots.extend(self.constants.values().flat_map(|constants| constants.iter()));
+
+    let frames = self.call_stack.iter().map(|frame| (frame.function, frame.local_frame));
+    let mut frames = frames.chain([(self.function, self.local.base())]).peekable();
+    while let Some((function, base)) = frames.next() {
+      let end = frames.peek().map_or(self.local.local.len(), |(_, base)| *base);
+      if let Code::Native(..) = function.code {
+        roots.pin(&self.local.local[base..end]);
+      }
+    }
+    roots
+  }
+
+  /// The opcode and address of the bytecode instruction being executed.
+  fn instruction(&self) -> Option<(u8, usize)> {
+    match self.function.code {
+      Code::Bytecode(ref program) => Some((*program.get(self.instruction)?, self.instruction)),
+      Code::Native(..) => None,
+    }
+  }
+
+  /// Throw `error` as an instance of the builtin `Error` class, or give it back if uncaught.
+  fn throw_error(&mut self, error: Error) -> Result<()> {
+    let error = match error {
+      Error::Uncaught(..) => return Err(error),
+      Error::Throw(exception) => return self.throw(exception),
+      Error::OutOfMemory { kind, size, site: None } => {
+        Error::OutOfMemory { kind, size, site: Some(self.site()) }
+      }
+      Error::TypeMismatch { expected, found, opcode: None, ip: None } => {
+        let (opcode, ip) = self.instruction().unzip();
+        Error::TypeMismatch { expected, found, opcode, ip }
+      }
+      Error::NullReference { expected, opcode: None, ip: None } => {
+        let (opcode, ip) = self.instruction().unzip();
+        Error::NullReference { expected, opcode, ip }
+      }
+      error => error,
+    };
+    let Some((depth, handler)) = self.find_handler(Some(class::error::NAME)) else {
+      return Err(error);
+    };
+    let class = self.ctx.fetch_class(class::error::NAME)?;
+    let (message, exception) = self.gc.without_limit(|gc| {
+      Ok::<_, Error>((gc.alloc_string(error.to_string())?, gc.class(class.fields.len(), class)?))
+    })?;
+    Gc::set_field2(exception.reference(), class::error::MESSAGE, message);
+    self.unwind(depth, handler, exception);
+    Ok(())
+  }
+
+  /// Find the handler of an exception of `class`, as the number of frames to unwind and the
+  /// handler address.
+  fn find_handler(&self, class: Option<&str>) -> Option<(usize, usize)> {
+    let current = std::iter::once((self.current, self.function, *self.ip.borrow()));
+    let frames = self.call_stack[self.barrier..]
+      .iter()
+      .rev()
+      .map(|frame| (frame.current, frame.function, *frame.return_address.borrow()));
+
+    for (depth, (current, function, ip)) in current.chain(frames).enumerate() {
+      for handler in function.handlers.iter().filter(|handler| handler.covers(ip)) {
+        let catches = match handler.class {
+          None => true,
+          Some(index) => {
+            let constants = match current {
+              Current::Module(module) => unsafe { &(*module).constants },
+              Current::Class(class) => unsafe { &(*class).constants },
+            };
+            matches!(
+              (constants.get(index as usize), class),
+              (Some(PoolEntry::Class(name)), Some(class)) if name == class
+            )
+          }
+        };
+        if catches {
+          return Some((depth, handler.handler as usize));
+        }
+      }
+    }
+
+    None
+  }
+
+  #[inline(always)]
+  fn unwind(&mut self, depth: usize, handler: usize, exception: Value) {
+    for _ in 0..depth {
+      self.pop_frame();
+    }
+    self.stack.truncate(self.stack_base);
+    self.stack.push(exception);
+    *self.ip.get_mut() = handler;
+  }
+
+  unsafe fn class_name<'a>(value: Value) -> Option<&'a str> {
+    if value.tag() == Value::TAG_CLASS {
+      let object = value.reference() as *const ObjClass;
+      Some(&(*(*object).class_ref).name)
+    } else {
+      None
+    }
+  }
+
+  fn describe_exception(&self, exception: Value) -> String {
+    match unsafe { Self::class_name(exception) } {
+      Some(name) => {
+        let object = exception.reference() as *const ObjClass;
+        let class = unsafe { &*(*object).class_ref };
+        match class.fields.get(class::error::MESSAGE) {
+          Some(field) => {
+            let message = unsafe { (&(*object).fields)[field.offset as usize] };
+            format!("{name}: {}", formatting::display_value(&message, &self.gc))
+          }
+          None => name.to_string(),
+        }
+      }
+      None => formatting::display_value(&exception, &self.gc).to_string(),
+    }
+  }
+
   #[inline(always)]
   fn push_frame(&mut self, frame: usize, current: Current, function: &'c Function) {
     self.call_stack.push(Frame {
       return_address: std::mem::replace(&mut self.ip, RefCell::new(IP_INIT)),
       local_frame: frame,
+      stack_base: std::mem::replace(&mut self.stack_base, self.stack.sp()),
       current: std::mem::replace(&mut self.current, current),
       function: std::mem::replace(&mut self.function, function),
     });
   }
 
+  /// Check there is room for one more frame and its operand stack.
+  fn check_overflow(&self) -> Result<()> {
+    if self.call_stack.len() >= MAX_FRAMES {
+      return Err(Error::StackOverflow);
+    }
+    self.stack.check_overflow(MAX_STACK)
+  }
+
   #[inline(always)]
   fn pop_frame(&mut self) {
     if let Some(frame) = self.call_stack.pop() {
       self.ip = frame.return_address;
       self.current = frame.current;
       self.function = frame.function;
+      self.stack_base = frame.stack_base;
       self.local.pop_frame(frame.local_frame);
     }
   }
@@ -528,6 +1293,8 @@ impl Runtime<'_> {
 /// A runtime error.
 pub enum Error {
   StackUnderflow,
+  /// Too many nested calls for the operand stack or the call stack.
+  StackOverflow,
   FieldAccessError,
   ModuleNotFound(String),
   ModuleAlreadyExists(String),
@@ -535,6 +1302,46 @@ pub enum Error {
   ClassNotFound(String),
   ClassAlreadyExists(String),
   InvalidEntry(usize),
+  NotCallable(String),
+  Arity {
+    function: String,
+    expected: usize,
+    found: usize,
+  },
+  Conversion {
+    expected: &'static str,
+    found: &'static str,
+  },
+  Released(&'static str),
+  IndexOutOfBounds {
+    index: i64,
+    len: usize,
+  },
+  NegativeArraySize(i32),
+  KeyNotFound(String),
+  /// An operand of the wrong type, located at the `opcode` instruction at `ip` once thrown.
+  TypeMismatch {
+    expected: &'static str,
+    found: &'static str,
+    opcode: Option<u8>,
+    ip: Option<usize>,
+  },
+  /// A null operand where a value of type `expected` was needed.
+  NullReference {
+    expected: &'static str,
+    opcode: Option<u8>,
+    ip: Option<usize>,
+  },
+  DivisionByZero,
+  /// An allocation of `size` bytes past the heap limit, `site` is the allocating instruction.
+  OutOfMemory {
+    kind: &'static str,
+    size: usize,
+    site: Option<String>,
+  },
+  Verify(VerifyError),
+  Uncaught(String),
+  Throw(Value),
   Other(Box<dyn std::error::Error + 'static>),
 }
 
@@ -544,12 +1351,30 @@ impl Error {
   }
 }
 
+/// The target of `key` in the sorted `LOOKUPSWITCH` pairs of a 4 bytes key and a 2 bytes target.
+fn lookup(pairs: &[u8], key: Int32) -> Option<usize> {
+  let (pairs, _) = pairs.as_chunks::<6>();
+  let index = pairs
+    .binary_search_by_key(&key, |pair| Int32::from_be_bytes([pair[0], pair[1], pair[2], pair[3]]));
+  index.ok().map(|index| u16::from_be_bytes([pairs[index][4], pairs[index][5]]) as usize)
+}
+
+impl From<TypeMismatch> for Error {
+  fn from(TypeMismatch { expected, found }: TypeMismatch) -> Self {
+    match found {
+      "null" => Error::NullReference { expected, opcode: None, ip: None },
+      found => Error::TypeMismatch { expected, found, opcode: None, ip: None },
+    }
+  }
+}
+
 pub type Result<T> = std::result::Result<T, Error>;
 
 impl fmt::Display for Error {
   fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
     match self {
       Error::StackUnderflow => write!(f, "Stack Underflow"),
+      Error::StackOverflow => write!(f, "Stack Overflow"),
       Error::FieldAccessError => write!(f, "Field Access Error"),
       Error::ModuleNotFound(name) => write!(f, "Module '{name}' not found."),
       Error::ModuleAlreadyExists(name) => write!(f, "Module '{name}' already exists."),
@@ -557,6 +1382,41 @@ impl fmt::Display for Error {
       Error::ClassNotFound(name) => write!(f, "Class '{name}' not found."),
       Error::ClassAlreadyExists(name) => write!(f, "Class '{name}' already exists."),
       Error::InvalidEntry(index) => write!(f, "Invalid constant pool entry '{index}'."),
+      Error::NotCallable(value) => write!(f, "Value '{value}' is not callable."),
+      Error::Arity { function, expected, found } => {
+        write!(f, "Function '{function}' expects {expected} argument(s), found {found}.")
+      }
+      Error::Conversion { expected, found } => {
+        write!(f, "Cannot convert {found} value to {expected}.")
+      }
+      Error::IndexOutOfBounds { index, len } => {
+        write!(f, "Index {index} out of bounds for length {len}.")
+      }
+      Error::KeyNotFound(key) => write!(f, "Key '{key}' not found."),
+      Error::TypeMismatch { expected, found, opcode: Some(opcode), ip: Some(ip) } => {
+        let opcode = opcode::TO_STR[*opcode as usize];
+        write!(f, "Expected {expected}, found {found} in {opcode} at {ip}.")
+      }
+      Error::TypeMismatch { expected, found, .. } => {
+        write!(f, "Expected {expected}, found {found}.")
+      }
+      Error::NullReference { expected, opcode: Some(opcode), ip: Some(ip) } => {
+        let opcode = opcode::TO_STR[*opcode as usize];
+        write!(f, "Null reference, expected {expected} in {opcode} at {ip}.")
+      }
+      Error::NullReference { expected, .. } => write!(f, "Null reference, expected {expected}."),
+      Error::DivisionByZero => write!(f, "Division by zero."),
+      Error::NegativeArraySize(size) => write!(f, "Negative array size {size}."),
+      Error::OutOfMemory { kind, size, site: Some(site) } => {
+        write!(f, "Out of memory allocating {size} bytes for {kind} at {site}.")
+      }
+      Error::OutOfMemory { kind, size, site: None } => {
+        write!(f, "Out of memory allocating {size} bytes for {kind}.")
+      }
+      Error::Released(name) => write!(f, "Native object {name} was released."),
+      Error::Verify(e) => write!(f, "{e}"),
+      Error::Uncaught(exception) => write!(f, "Uncaught exception {exception}."),
+      Error::Throw(exception) => write!(f, "Thrown exception {exception:?}."),
       Error::Other(e) => write!(f, "{e}"),
     }
   }
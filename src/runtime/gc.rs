@@ -1,3 +1,5 @@
+use crate::gc::roots::RootSet;
+
 use super::{Runtime, RuntimeVisitor};
 
 pub struct CleanGc;
@@ -6,6 +8,9 @@ impl RuntimeVisitor for CleanGc {
   fn visit(&self, rt: &mut Runtime) {
     rt.local.local.clear();
     rt.stack.clear();
-    rt.gc.mark_sweep(&rt.local, &rt.stack);
+    rt.constants.clear();
+    rt.gc.clear_interned();
+    // Nothing is left to copy, a failure only leaves the heap to be freed with the runtime.
+    let _ = rt.gc.collect_major(&RootSet::new());
   }
 }
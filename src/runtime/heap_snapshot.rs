@@ -0,0 +1,29 @@
+use std::{
+  fs::File,
+  io::{self, BufWriter, Write},
+  path::{Path, PathBuf},
+};
+
+use crate::gc::snapshot::Snapshot;
+
+use super::{Runtime, RuntimeVisitor};
+
+/// Write a [`Snapshot`] of the heap to `path`.
+pub struct HeapSnapshot {
+  pub path: PathBuf,
+}
+
+impl RuntimeVisitor for HeapSnapshot {
+  fn visit(&self, rt: &mut Runtime) {
+    if let Err(e) = write(rt, &self.path) {
+      eprintln!("Cannot write heap snapshot {}: {e}", self.path.display());
+    }
+  }
+}
+
+pub(crate) fn write(rt: &Runtime, path: &Path) -> io::Result<()> {
+  let snapshot = Snapshot::capture(&rt.gc, &rt.roots());
+  let mut file = BufWriter::new(File::create(path)?);
+  snapshot.write(&mut file)?;
+  file.flush()
+}
@@ -0,0 +1,234 @@
+use std::{
+  any::{type_name, Any},
+  collections::BTreeMap,
+  mem::size_of,
+};
+
+use crate::{
+  class,
+  context::Context,
+  gc::{DictKey, Gc, ObjNative, ObjString},
+  local::Local,
+  value::Value,
+  vm::FromValue,
+};
+
+use super::{Error, Result, Runtime};
+
+/// Handle given to native functions, with access to the arguments and the runtime.
+///
+/// Collection can run at any allocation. The arguments are rooted, as are the values allocated or
+/// returned by calls through the `NativeCtx`. They stay rooted until the native function returns.
+/// Other values kept across allocations must be rooted with [`NativeCtx::handle`].
+pub struct NativeCtx<'r, 'c> {
+  runtime: &'r mut Runtime<'c>,
+}
+
+impl<'r, 'c> NativeCtx<'r, 'c> {
+  pub(crate) fn new(runtime: &'r mut Runtime<'c>) -> Self {
+    Self { runtime }
+  }
+
+  /// The argument at `index`.
+  #[inline(always)]
+  pub fn value(&self, index: usize) -> Value {
+    self.runtime.local.load(index)
+  }
+
+  /// The argument at `index` converted to `T`.
+  pub fn arg<T: FromValue>(&self, index: usize) -> Result<T> {
+    T::from_value(self.value(index), &self.runtime.gc)
+  }
+
+  /// The string argument at `index`, without copying it.
+  pub fn string(&self, index: usize) -> Result<&str> {
+    let value = self.value(index);
+    match value.tag() {
+      Value::TAG_STRING => {
+        let ptr = value.reference() as *const ObjString;
+        Ok(unsafe { &(*ptr).contents })
+      }
+      _ => Err(Error::Conversion { expected: "String", found: value.type_name() }),
+    }
+  }
+
+  /// The bytes argument at `index`.
+  pub fn bytes(&mut self, index: usize) -> Result<&mut Vec<u8>> {
+    let value = self.value(index);
+    match value.tag() {
+      Value::TAG_BYTES => Ok(Gc::get_bytes(value.reference())),
+      _ => Err(Error::Conversion { expected: "bytes", found: value.type_name() }),
+    }
+  }
+
+  /// The entries of the dict argument at `index`.
+  pub fn dict(&mut self, index: usize) -> Result<&mut BTreeMap<DictKey, Value>> {
+    let value = self.value(index);
+    match value.tag() {
+      Value::TAG_DICT => Ok(Gc::get_dict_fields(value.reference())),
+      _ => Err(Error::Conversion { expected: "dict", found: value.type_name() }),
+    }
+  }
+
+  pub fn local(&mut self) -> &mut Local {
+    &mut self.runtime.local
+  }
+
+  pub fn gc(&mut self) -> &mut Gc {
+    &mut self.runtime.gc
+  }
+
+  /// The loaded modules and classes.
+  pub fn context(&self) -> &Context<'c> {
+    &self.runtime.ctx
+  }
+
+  /// Root `value` until the native function returns or the enclosing [`NativeCtx::scope`] ends.
+  pub fn handle(&mut self, value: Value) -> Value {
+    self.runtime.handles.push(value);
+    value
+  }
+
+  /// Run `f`, releasing the handles it created at the end, like the allocations of a loop
+  /// iteration. A value returned by `f` must be handled again to stay rooted.
+  pub fn scope<T>(&mut self, f: impl FnOnce(&mut Self) -> T) -> T {
+    let handles = self.runtime.handles.len();
+    let result = f(self);
+    self.runtime.handles.truncate(handles);
+    result
+  }
+
+  /// Run a major collection now.
+  pub fn collect(&mut self) -> Result<()> {
+    self.runtime.collect(true)
+  }
+
+  /// Write a heap snapshot to `path`, see [`Snapshot`](crate::gc::snapshot::Snapshot).
+  pub fn heap_snapshot(&self, path: &str) -> Result<()> {
+    super::heap_snapshot::write(self.runtime, path.as_ref()).map_err(Error::other)
+  }
+
+  pub fn alloc_string(&mut self, s: String) -> Result<Value> {
+    self.runtime.safepoint(s.len())?;
+    let value = self.runtime.gc.alloc_string(s)?;
+    Ok(self.handle(value))
+  }
+
+  /// The interned string with contents `s`, see [`Gc::intern`].
+  pub fn intern(&mut self, s: &str) -> Result<Value> {
+    self.runtime.safepoint(s.len())?;
+    let value = self.runtime.gc.intern(s)?;
+    Ok(self.handle(value))
+  }
+
+  pub fn alloc_bytes(&mut self, bytes: Vec<u8>) -> Result<Value> {
+    self.runtime.safepoint(bytes.len())?;
+    let value = self.runtime.gc.alloc_bytes(bytes)?;
+    Ok(self.handle(value))
+  }
+
+  pub fn alloc_array(&mut self, size: i32) -> Result<Value> {
+    self.runtime.safepoint((size as usize).saturating_mul(size_of::<Value>()))?;
+    let value = self.runtime.gc.alloc_array(size)?;
+    Ok(self.handle(value))
+  }
+
+  pub fn alloc_dict(&mut self) -> Result<Value> {
+    self.runtime.safepoint(0)?;
+    let value = self.runtime.gc.alloc_dict()?;
+    Ok(self.handle(value))
+  }
+
+  /// Allocate a weak reference to `target`, see [`Gc::alloc_weak`].
+  pub fn alloc_weak(&mut self, target: Value) -> Result<Value> {
+    let target = self.handle(target);
+    self.runtime.safepoint(0)?;
+    let value = self.runtime.gc.alloc_weak(target)?;
+    Ok(self.handle(value))
+  }
+
+  /// Allocate a native object owning `data`, see [`Gc::alloc_native`].
+  pub fn alloc_native<T: Any>(&mut self, type_name: &'static str, data: T) -> Result<Value> {
+    self.runtime.safepoint(0)?;
+    let value = self.runtime.gc.alloc_native(type_name, data)?;
+    Ok(self.handle(value))
+  }
+
+  /// The native object argument at `index`.
+  pub fn native<T: Any>(&mut self, index: usize) -> Result<&mut T> {
+    let value = self.value(index);
+    if value.tag() != Value::TAG_NATIVE {
+      return Err(Error::Conversion { expected: type_name::<T>(), found: value.type_name() });
+    }
+    let ptr = value.reference() as *const ObjNative;
+    match unsafe { &(*ptr).data } {
+      None => Err(Error::Released(unsafe { (*ptr).type_name })),
+      Some(_) => Gc::get_native(value.reference()).ok_or(Error::Conversion {
+        expected: type_name::<T>(),
+        found: unsafe { (*ptr).type_name },
+      }),
+    }
+  }
+
+  /// Release the native object argument at `index`, see [`Gc::release_native`].
+  pub fn release(&self, index: usize) -> Result<()> {
+    let value = self.value(index);
+    if value.tag() != Value::TAG_NATIVE {
+      return Err(Error::Conversion { expected: "native", found: value.type_name() });
+    }
+    let ptr = value.reference() as *const ObjNative;
+    match Gc::release_native(value.reference()) {
+      true => Ok(()),
+      false => Err(Error::Released(unsafe { (*ptr).type_name })),
+    }
+  }
+
+  /// Allocate an instance of `class` with null fields, without running its constructor.
+  pub fn alloc_object(&mut self, class: &str) -> Result<Value> {
+    let class = self.runtime.ctx.fetch_class(class)?;
+    self.runtime.safepoint(class.fields.len() * size_of::<Value>())?;
+    let value = self.runtime.gc.class(class.fields.len(), class)?;
+    Ok(self.handle(value))
+  }
+
+  /// Call `module:function`, see [`Runtime::call_function`].
+  ///
+  /// An uncaught exception is returned as [`Error::Throw`], returning it from the native rethrows
+  /// it.
+  pub fn call(&mut self, module: &str, function: &str, args: &[Value]) -> Result<Option<Value>> {
+    let result = self.runtime.invoke(module, function, args)?;
+    Ok(result.map(|value| self.handle(value)))
+  }
+
+  /// Call the function value `function`, like [`NativeCtx::call`].
+  pub fn call_value(&mut self, function: Value, args: &[Value]) -> Result<Option<Value>> {
+    let result = self.runtime.invoke_value(function, args)?;
+    Ok(result.map(|value| self.handle(value)))
+  }
+
+  /// An error throwing `exception` from the native function.
+  pub fn throw(&self, exception: Value) -> Error {
+    Error::Throw(exception)
+  }
+
+  /// An error throwing a new instance of `class`, with `message` in its `message` field if it has
+  /// one.
+  pub fn raise(&mut self, class: &str, message: impl Into<String>) -> Error {
+    let class = match self.runtime.ctx.fetch_class(class) {
+      Ok(class) => class,
+      Err(e) => return e,
+    };
+    let exception = self.runtime.gc.without_limit(|gc| {
+      let exception = gc.class(class.fields.len(), class)?;
+      if let Some(field) = class.fields.get(class::error::MESSAGE) {
+        let message = gc.alloc_string(message.into())?;
+        Gc::set_field_with_offset(exception.reference(), field.offset, message);
+      }
+      Ok(exception)
+    });
+    match exception {
+      Ok(exception) => Error::Throw(exception),
+      Err(e) => e,
+    }
+  }
+}
@@ -1,8 +1,11 @@
-use std::{ops::Neg, slice::Iter};
+use std::{
+  ops::Neg,
+  slice::{Iter, IterMut},
+};
 
 use crate::{
   runtime::{Error, Result},
-  value::{Byte8, Float32, Int32, Value},
+  value::{Byte8, Float32, Float64, Int32, Int64, TypeMismatch, Value},
 };
 
 #[derive(Debug)]
@@ -27,8 +30,23 @@ impl<const SIZE: usize> Stack<SIZE> {
     self.memory = [Value::NULL; SIZE];
   }
 
-  pub(crate) fn iter(&self) -> Iter<Value> {
-    self.memory.iter()
+  /// The live values, up to `sp`.
+  pub(crate) fn iter(&self) -> Iter<'_, Value> {
+    self.memory[..self.sp].iter()
+  }
+
+  pub(crate) fn iter_mut(&mut self) -> IterMut<'_, Value> {
+    self.memory[..self.sp].iter_mut()
+  }
+
+  #[inline(always)]
+  pub fn sp(&self) -> usize {
+    self.sp
+  }
+
+  #[inline(always)]
+  pub fn truncate(&mut self, sp: usize) {
+    self.sp = self.sp.min(sp);
   }
 
   #[inline(always)]
@@ -62,6 +80,15 @@ impl<const SIZE: usize> Stack<SIZE> {
     }
   }
 
+  #[inline(always)]
+  pub fn check_overflow(&self, len: usize) -> Result<()> {
+    if self.sp + len > SIZE {
+      Err(Error::StackOverflow)
+    } else {
+      Ok(())
+    }
+  }
+
   #[inline(always)]
   pub fn dup(&mut self) -> Result<()> {
     self.check_underflow(1)?;
@@ -104,53 +131,59 @@ impl<const SIZE: usize> Stack<SIZE> {
   #[inline(always)]
   pub fn iadd(&mut self) -> Result<()> {
     self.check_underflow(2)?;
-    let value2: Int32 = self.pop_unchecked().into();
-    let value1: Int32 = self.pop_unchecked().into();
-    self.push(Value::mk_integer(value1 + value2));
+    let value2: Int32 = self.pop_unchecked().try_into()?;
+    let value1: Int32 = self.pop_unchecked().try_into()?;
+    self.push(Value::mk_integer(value1.wrapping_add(value2)));
     Ok(())
   }
 
   #[inline(always)]
   pub fn isub(&mut self) -> Result<()> {
     self.check_underflow(2)?;
-    let value2: Int32 = self.pop_unchecked().into();
-    let value1: Int32 = self.pop_unchecked().into();
-    self.push(Value::mk_integer(value1 - value2));
+    let value2: Int32 = self.pop_unchecked().try_into()?;
+    let value1: Int32 = self.pop_unchecked().try_into()?;
+    self.push(Value::mk_integer(value1.wrapping_sub(value2)));
     Ok(())
   }
 
   #[inline(always)]
   pub fn imul(&mut self) -> Result<()> {
     self.check_underflow(2)?;
-    let value2: Int32 = self.pop_unchecked().into();
-    let value1: Int32 = self.pop_unchecked().into();
-    self.push(Value::mk_integer(value1 * value2));
+    let value2: Int32 = self.pop_unchecked().try_into()?;
+    let value1: Int32 = self.pop_unchecked().try_into()?;
+    self.push(Value::mk_integer(value1.wrapping_mul(value2)));
     Ok(())
   }
 
   #[inline(always)]
   pub fn idiv(&mut self) -> Result<()> {
     self.check_underflow(2)?;
-    let value2: Int32 = self.pop_unchecked().into();
-    let value1: Int32 = self.pop_unchecked().into();
-    self.push(Value::mk_integer(value1 / value2));
+    let value2: Int32 = self.pop_unchecked().try_into()?;
+    let value1: Int32 = self.pop_unchecked().try_into()?;
+    if value2 == 0 {
+      return Err(Error::DivisionByZero);
+    }
+    self.push(Value::mk_integer(value1.wrapping_div(value2)));
     Ok(())
   }
 
   #[inline(always)]
   pub fn irem(&mut self) -> Result<()> {
     self.check_underflow(2)?;
-    let value2: Int32 = self.pop_unchecked().into();
-    let value1: Int32 = self.pop_unchecked().into();
-    self.push(Value::mk_integer(value1 % value2));
+    let value2: Int32 = self.pop_unchecked().try_into()?;
+    let value1: Int32 = self.pop_unchecked().try_into()?;
+    if value2 == 0 {
+      return Err(Error::DivisionByZero);
+    }
+    self.push(Value::mk_integer(value1.wrapping_rem(value2)));
     Ok(())
   }
 
   #[inline(always)]
   pub fn iand(&mut self) -> Result<()> {
     self.check_underflow(2)?;
-    let value2: Int32 = self.pop_unchecked().into();
-    let value1: Int32 = self.pop_unchecked().into();
+    let value2: Int32 = self.pop_unchecked().try_into()?;
+    let value1: Int32 = self.pop_unchecked().try_into()?;
     self.push(Value::mk_integer(value1 & value2));
     Ok(())
   }
@@ -158,8 +191,8 @@ impl<const SIZE: usize> Stack<SIZE> {
   #[inline(always)]
   pub fn ior(&mut self) -> Result<()> {
     self.check_underflow(2)?;
-    let value2: Int32 = self.pop_unchecked().into();
-    let value1: Int32 = self.pop_unchecked().into();
+    let value2: Int32 = self.pop_unchecked().try_into()?;
+    let value1: Int32 = self.pop_unchecked().try_into()?;
     self.push(Value::mk_integer(value1 | value2));
     Ok(())
   }
@@ -167,8 +200,8 @@ impl<const SIZE: usize> Stack<SIZE> {
   #[inline(always)]
   pub fn ixor(&mut self) -> Result<()> {
     self.check_underflow(2)?;
-    let value2: Int32 = self.pop_unchecked().into();
-    let value1: Int32 = self.pop_unchecked().into();
+    let value2: Int32 = self.pop_unchecked().try_into()?;
+    let value1: Int32 = self.pop_unchecked().try_into()?;
     self.push(Value::mk_integer(value1 ^ value2));
     Ok(())
   }
@@ -176,34 +209,34 @@ impl<const SIZE: usize> Stack<SIZE> {
   #[inline(always)]
   pub fn ishl(&mut self) -> Result<()> {
     self.check_underflow(2)?;
-    let value2: Int32 = self.pop_unchecked().into();
-    let value1: Int32 = self.pop_unchecked().into();
-    self.push(Value::mk_integer(value1 << value2));
+    let value2: Int32 = self.pop_unchecked().try_into()?;
+    let value1: Int32 = self.pop_unchecked().try_into()?;
+    self.push(Value::mk_integer(value1.wrapping_shl(value2 as u32)));
     Ok(())
   }
 
   #[inline(always)]
   pub fn ishr(&mut self) -> Result<()> {
     self.check_underflow(2)?;
-    let value2: Int32 = self.pop_unchecked().into();
-    let value1: Int32 = self.pop_unchecked().into();
-    self.push(Value::mk_integer(value1 >> value2));
+    let value2: Int32 = self.pop_unchecked().try_into()?;
+    let value1: Int32 = self.pop_unchecked().try_into()?;
+    self.push(Value::mk_integer(value1.wrapping_shr(value2 as u32)));
     Ok(())
   }
 
   #[inline(always)]
   pub fn iushr(&mut self) -> Result<()> {
     self.check_underflow(2)?;
-    let rhs = Int32::from(self.pop_unchecked()) as u32;
-    let lhs = Int32::from(self.pop_unchecked()) as u32;
-    self.push(Value::mk_integer((lhs >> rhs) as i32));
+    let rhs = Int32::try_from(self.pop_unchecked())? as u32;
+    let lhs = Int32::try_from(self.pop_unchecked())? as u32;
+    self.push(Value::mk_integer(lhs.wrapping_shr(rhs) as i32));
     Ok(())
   }
 
   #[inline(always)]
   pub fn ineg(&mut self) -> Result<()> {
     self.check_underflow(1)?;
-    let value: Int32 = self.pop_unchecked().into();
+    let value: Int32 = self.pop_unchecked().try_into()?;
     self.push(Value::mk_integer(value.wrapping_neg()));
     Ok(())
   }
@@ -211,7 +244,7 @@ impl<const SIZE: usize> Stack<SIZE> {
   #[inline(always)]
   pub fn i2f(&mut self) -> Result<()> {
     self.check_underflow(1)?;
-    let value: Int32 = self.pop_unchecked().into();
+    let value: Int32 = self.pop_unchecked().try_into()?;
     self.push(Value::mk_float(value as f32));
     Ok(())
   }
@@ -219,7 +252,7 @@ impl<const SIZE: usize> Stack<SIZE> {
   #[inline(always)]
   pub fn f2i(&mut self) -> Result<()> {
     self.check_underflow(1)?;
-    let value: Float32 = self.pop_unchecked().into();
+    let value: Float32 = self.pop_unchecked().try_into()?;
     self.push(Value::mk_integer(value as Int32));
     Ok(())
   }
@@ -227,15 +260,15 @@ impl<const SIZE: usize> Stack<SIZE> {
   #[inline(always)]
   pub fn iexp(&mut self) -> Result<()> {
     self.check_underflow(2)?;
-    let mut value2: Int32 = self.pop_unchecked().into();
-    let mut value1: Int32 = self.pop_unchecked().into();
+    let mut value2: Int32 = self.pop_unchecked().try_into()?;
+    let mut value1: Int32 = self.pop_unchecked().try_into()?;
     let mut result: Int32 = 1;
     while value2 != 0 {
       if value2 & 1 == 1 {
-        result *= value1;
+        result = result.wrapping_mul(value1);
       }
       value2 >>= 1;
-      value1 *= value1;
+      value1 = value1.wrapping_mul(value1);
     }
     self.push(Value::mk_integer(result));
     Ok(())
@@ -245,73 +278,105 @@ impl<const SIZE: usize> Stack<SIZE> {
   pub fn is_zero(&mut self) -> Result<()> {
     self.check_underflow(1)?;
     let value = self.pop_unchecked();
-    match value.tag() {
-      Value::TAG_BYTE => self.push(Value::mk_byte(if value.byte() == 0 { 1 } else { 0 })),
-      Value::TAG_INTEGER => self.push(Value::mk_integer(if value.integer() == 0 { 1 } else { 0 })),
-      Value::TAG_FLOAT => self.push(Value::mk_float(if value.float() == 0. { 1. } else { 0. })),
-      Value::TAG_NULL
-      | Value::TAG_STRING
-      | Value::TAG_DICT
-      | Value::TAG_ARRAY
-      | Value::TAG_CLASS => panic!("Invalid argument"),
-      _ => unreachable!(),
-    }
+    let zero = match value.tag() {
+      Value::TAG_BYTE => value.byte() == 0,
+      Value::TAG_INTEGER => value.integer() == 0,
+      Value::TAG_FLOAT => value.float() == 0.,
+      Value::TAG_LONG => value.long() == 0,
+      Value::TAG_DOUBLE => value.double() == 0.,
+      _ => Err(TypeMismatch { expected: "number", found: value.type_name() })?,
+    };
+    self.push(Value::mk_bool(zero));
+    Ok(())
+  }
+
+  #[inline(always)]
+  pub fn not(&mut self) -> Result<()> {
+    self.check_underflow(1)?;
+    let value: bool = self.pop_unchecked().try_into()?;
+    self.push(Value::mk_bool(!value));
+    Ok(())
+  }
+
+  #[inline(always)]
+  pub fn c2i(&mut self) -> Result<()> {
+    self.check_underflow(1)?;
+    let value: char = self.pop_unchecked().try_into()?;
+    self.push(Value::mk_integer(value as Int32));
+    Ok(())
+  }
+
+  /// Compare two numbers of the same type, NaN compares less.
+  #[inline(always)]
+  pub fn cmp(&mut self) -> Result<()> {
+    self.check_underflow(2)?;
+    let value2 = self.pop_unchecked();
+    let value1 = self.pop_unchecked();
+    let ordering = match value1.tag() {
+      Value::TAG_BYTE => value1.byte().partial_cmp(&Byte8::try_from(value2)?),
+      Value::TAG_INTEGER => value1.integer().partial_cmp(&Int32::try_from(value2)?),
+      Value::TAG_FLOAT => value1.float().partial_cmp(&Float32::try_from(value2)?),
+      Value::TAG_LONG => value1.long().partial_cmp(&Int64::try_from(value2)?),
+      Value::TAG_DOUBLE => value1.double().partial_cmp(&Float64::try_from(value2)?),
+      _ => Err(TypeMismatch { expected: "number", found: value1.type_name() })?,
+    };
+    self.push(Value::mk_integer(ordering.map_or(-1, |ordering| ordering as Int32)));
     Ok(())
   }
 
   #[inline(always)]
   pub fn ifeq(&mut self) -> Result<bool> {
     self.check_underflow(2)?;
-    let value2: Int32 = self.pop_unchecked().into();
-    let value1: Int32 = self.pop_unchecked().into();
+    let value2: Int32 = self.pop_unchecked().try_into()?;
+    let value1: Int32 = self.pop_unchecked().try_into()?;
     Ok(value1 == value2)
   }
 
   #[inline(always)]
   pub fn ifneq(&mut self) -> Result<bool> {
     self.check_underflow(2)?;
-    let value2: Int32 = self.pop_unchecked().into();
-    let value1: Int32 = self.pop_unchecked().into();
+    let value2: Int32 = self.pop_unchecked().try_into()?;
+    let value1: Int32 = self.pop_unchecked().try_into()?;
     Ok(value1 != value2)
   }
 
   #[inline(always)]
   pub fn ifgt(&mut self) -> Result<bool> {
     self.check_underflow(2)?;
-    let value2: Int32 = self.pop_unchecked().into();
-    let value1: Int32 = self.pop_unchecked().into();
+    let value2: Int32 = self.pop_unchecked().try_into()?;
+    let value1: Int32 = self.pop_unchecked().try_into()?;
     Ok(value1 > value2)
   }
 
   #[inline(always)]
   pub fn ifge(&mut self) -> Result<bool> {
     self.check_underflow(2)?;
-    let value2: Int32 = self.pop_unchecked().into();
-    let value1: Int32 = self.pop_unchecked().into();
+    let value2: Int32 = self.pop_unchecked().try_into()?;
+    let value1: Int32 = self.pop_unchecked().try_into()?;
     Ok(value1 >= value2)
   }
 
   #[inline(always)]
   pub fn iflt(&mut self) -> Result<bool> {
     self.check_underflow(2)?;
-    let value2: Int32 = self.pop_unchecked().into();
-    let value1: Int32 = self.pop_unchecked().into();
+    let value2: Int32 = self.pop_unchecked().try_into()?;
+    let value1: Int32 = self.pop_unchecked().try_into()?;
     Ok(value1 < value2)
   }
 
   #[inline(always)]
   pub fn ifle(&mut self) -> Result<bool> {
     self.check_underflow(2)?;
-    let value2: Int32 = self.pop_unchecked().into();
-    let value1: Int32 = self.pop_unchecked().into();
+    let value2: Int32 = self.pop_unchecked().try_into()?;
+    let value1: Int32 = self.pop_unchecked().try_into()?;
     Ok(value1 <= value2)
   }
 
   #[inline(always)]
   pub fn fadd(&mut self) -> Result<()> {
     self.check_underflow(2)?;
-    let value2: Float32 = self.pop_unchecked().into();
-    let value1: Float32 = self.pop_unchecked().into();
+    let value2: Float32 = self.pop_unchecked().try_into()?;
+    let value1: Float32 = self.pop_unchecked().try_into()?;
     self.push(Value::mk_float(value1 + value2));
     Ok(())
   }
@@ -319,8 +384,8 @@ impl<const SIZE: usize> Stack<SIZE> {
   #[inline(always)]
   pub fn fsub(&mut self) -> Result<()> {
     self.check_underflow(2)?;
-    let value2: Float32 = self.pop_unchecked().into();
-    let value1: Float32 = self.pop_unchecked().into();
+    let value2: Float32 = self.pop_unchecked().try_into()?;
+    let value1: Float32 = self.pop_unchecked().try_into()?;
     self.push(Value::mk_float(value1 - value2));
     Ok(())
   }
@@ -328,8 +393,8 @@ impl<const SIZE: usize> Stack<SIZE> {
   #[inline(always)]
   pub fn fmul(&mut self) -> Result<()> {
     self.check_underflow(2)?;
-    let value2: Float32 = self.pop_unchecked().into();
-    let value1: Float32 = self.pop_unchecked().into();
+    let value2: Float32 = self.pop_unchecked().try_into()?;
+    let value1: Float32 = self.pop_unchecked().try_into()?;
     self.push(Value::mk_float(value1 * value2));
     Ok(())
   }
@@ -337,8 +402,8 @@ impl<const SIZE: usize> Stack<SIZE> {
   #[inline(always)]
   pub fn fdiv(&mut self) -> Result<()> {
     self.check_underflow(2)?;
-    let value2: Float32 = self.pop_unchecked().into();
-    let value1: Float32 = self.pop_unchecked().into();
+    let value2: Float32 = self.pop_unchecked().try_into()?;
+    let value1: Float32 = self.pop_unchecked().try_into()?;
     self.push(Value::mk_float(value1 / value2));
     Ok(())
   }
@@ -346,8 +411,8 @@ impl<const SIZE: usize> Stack<SIZE> {
   #[inline(always)]
   pub fn frem(&mut self) -> Result<()> {
     self.check_underflow(2)?;
-    let value2: Float32 = self.pop_unchecked().into();
-    let value1: Float32 = self.pop_unchecked().into();
+    let value2: Float32 = self.pop_unchecked().try_into()?;
+    let value1: Float32 = self.pop_unchecked().try_into()?;
     self.push(Value::mk_float(value1 % value2));
     Ok(())
   }
@@ -355,7 +420,7 @@ impl<const SIZE: usize> Stack<SIZE> {
   #[inline(always)]
   pub fn fneg(&mut self) -> Result<()> {
     self.check_underflow(1)?;
-    let value: Float32 = self.pop_unchecked().into();
+    let value: Float32 = self.pop_unchecked().try_into()?;
     self.push(Value::mk_float(value.neg()));
     Ok(())
   }
@@ -363,53 +428,59 @@ impl<const SIZE: usize> Stack<SIZE> {
   #[inline(always)]
   pub fn badd(&mut self) -> Result<()> {
     self.check_underflow(2)?;
-    let value2: Byte8 = self.pop_unchecked().into();
-    let value1: Byte8 = self.pop_unchecked().into();
-    self.push(Value::mk_byte(value1 + value2));
+    let value2: Byte8 = self.pop_unchecked().try_into()?;
+    let value1: Byte8 = self.pop_unchecked().try_into()?;
+    self.push(Value::mk_byte(value1.wrapping_add(value2)));
     Ok(())
   }
 
   #[inline(always)]
   pub fn bsub(&mut self) -> Result<()> {
     self.check_underflow(2)?;
-    let value2: Byte8 = self.pop_unchecked().into();
-    let value1: Byte8 = self.pop_unchecked().into();
-    self.push(Value::mk_byte(value1 - value2));
+    let value2: Byte8 = self.pop_unchecked().try_into()?;
+    let value1: Byte8 = self.pop_unchecked().try_into()?;
+    self.push(Value::mk_byte(value1.wrapping_sub(value2)));
     Ok(())
   }
 
   #[inline(always)]
   pub fn bmul(&mut self) -> Result<()> {
     self.check_underflow(2)?;
-    let value2: Byte8 = self.pop_unchecked().into();
-    let value1: Byte8 = self.pop_unchecked().into();
-    self.push(Value::mk_byte(value1 * value2));
+    let value2: Byte8 = self.pop_unchecked().try_into()?;
+    let value1: Byte8 = self.pop_unchecked().try_into()?;
+    self.push(Value::mk_byte(value1.wrapping_mul(value2)));
     Ok(())
   }
 
   #[inline(always)]
   pub fn bdiv(&mut self) -> Result<()> {
     self.check_underflow(2)?;
-    let value2: Byte8 = self.pop_unchecked().into();
-    let value1: Byte8 = self.pop_unchecked().into();
-    self.push(Value::mk_byte(value1 / value2));
+    let value2: Byte8 = self.pop_unchecked().try_into()?;
+    let value1: Byte8 = self.pop_unchecked().try_into()?;
+    if value2 == 0 {
+      return Err(Error::DivisionByZero);
+    }
+    self.push(Value::mk_byte(value1.wrapping_div(value2)));
     Ok(())
   }
 
   #[inline(always)]
   pub fn brem(&mut self) -> Result<()> {
     self.check_underflow(2)?;
-    let value2: Byte8 = self.pop_unchecked().into();
-    let value1: Byte8 = self.pop_unchecked().into();
-    self.push(Value::mk_byte(value1 % value2));
+    let value2: Byte8 = self.pop_unchecked().try_into()?;
+    let value1: Byte8 = self.pop_unchecked().try_into()?;
+    if value2 == 0 {
+      return Err(Error::DivisionByZero);
+    }
+    self.push(Value::mk_byte(value1.wrapping_rem(value2)));
     Ok(())
   }
 
   #[inline(always)]
   pub fn band(&mut self) -> Result<()> {
     self.check_underflow(2)?;
-    let value2: Byte8 = self.pop_unchecked().into();
-    let value1: Byte8 = self.pop_unchecked().into();
+    let value2: Byte8 = self.pop_unchecked().try_into()?;
+    let value1: Byte8 = self.pop_unchecked().try_into()?;
     self.push(Value::mk_byte(value1 & value2));
     Ok(())
   }
@@ -417,8 +488,8 @@ impl<const SIZE: usize> Stack<SIZE> {
   #[inline(always)]
   pub fn bor(&mut self) -> Result<()> {
     self.check_underflow(2)?;
-    let value2: Byte8 = self.pop_unchecked().into();
-    let value1: Byte8 = self.pop_unchecked().into();
+    let value2: Byte8 = self.pop_unchecked().try_into()?;
+    let value1: Byte8 = self.pop_unchecked().try_into()?;
     self.push(Value::mk_byte(value1 | value2));
     Ok(())
   }
@@ -426,34 +497,34 @@ impl<const SIZE: usize> Stack<SIZE> {
   #[inline(always)]
   pub fn bxor(&mut self) -> Result<()> {
     self.check_underflow(2)?;
-    let value2: Byte8 = self.pop_unchecked().into();
-    let value1: Byte8 = self.pop_unchecked().into();
-    self.push(Value::mk_byte(value1 | value2));
+    let value2: Byte8 = self.pop_unchecked().try_into()?;
+    let value1: Byte8 = self.pop_unchecked().try_into()?;
+    self.push(Value::mk_byte(value1 ^ value2));
     Ok(())
   }
 
   #[inline(always)]
   pub fn bshl(&mut self) -> Result<()> {
     self.check_underflow(2)?;
-    let value2: Byte8 = self.pop_unchecked().into();
-    let value1: Byte8 = self.pop_unchecked().into();
-    self.push(Value::mk_byte(value1 << value2));
+    let value2: Byte8 = self.pop_unchecked().try_into()?;
+    let value1: Byte8 = self.pop_unchecked().try_into()?;
+    self.push(Value::mk_byte(value1.wrapping_shl(value2 as u32)));
     Ok(())
   }
 
   #[inline(always)]
   pub fn bshr(&mut self) -> Result<()> {
     self.check_underflow(2)?;
-    let value2: Byte8 = self.pop_unchecked().into();
-    let value1: Byte8 = self.pop_unchecked().into();
-    self.push(Value::mk_byte(value1 >> value2));
+    let value2: Byte8 = self.pop_unchecked().try_into()?;
+    let value1: Byte8 = self.pop_unchecked().try_into()?;
+    self.push(Value::mk_byte(value1.wrapping_shr(value2 as u32)));
     Ok(())
   }
 
   #[inline(always)]
   pub fn bneg(&mut self) -> Result<()> {
     self.check_underflow(1)?;
-    let value: Byte8 = self.pop_unchecked().into();
+    let value: Byte8 = self.pop_unchecked().try_into()?;
     self.push(Value::mk_byte(value.wrapping_neg()));
     Ok(())
   }
@@ -6,6 +6,10 @@ pub type Byte8 = u8;
 pub type Int32 = i32;
 /// Grape float type.
 pub type Float32 = f32;
+/// Grape long type.
+pub type Int64 = i64;
+/// Grape double type.
+pub type Float64 = f64;
 /// Grape reference type.
 pub type Reference = usize;
 /// Grape string reference.
@@ -16,6 +20,12 @@ pub type Dict = usize;
 pub type Array = usize;
 /// Grape class reference.
 pub type Class = usize;
+/// Grape function reference.
+pub type Function = usize;
+/// Grape native object reference.
+pub type Native = usize;
+/// Grape bytes reference.
+pub type Bytes = usize;
 
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[repr(transparent)]
@@ -26,6 +36,10 @@ pub(crate) const TAG_BITS: u64 = 4;
 pub(crate) const TAG_DISPLACER: u64 = 64 - TAG_BITS;
 pub(crate) const VALUE_MASK: u64 = (1 << (64 - TAG_BITS)) - 1;
 pub(crate) const TAG_MASK: u64 = !VALUE_MASK;
+/// Payload bit of the longs and doubles boxed on the heap, as objects are aligned.
+pub(crate) const BOXED: u64 = 1;
+/// Low bits of the doubles stored in a value, which must be zero.
+const DOUBLE_LOW_BITS: u32 = TAG_BITS as u32 + 1;
 
 impl Value {
   pub const TAG_NULL: u64 = 0x0;
@@ -36,6 +50,14 @@ impl Value {
   pub const TAG_DICT: u64 = 0x5;
   pub const TAG_ARRAY: u64 = 0x6;
   pub const TAG_CLASS: u64 = 0x7;
+  pub const TAG_FUNCTION: u64 = 0x8;
+  pub const TAG_NATIVE: u64 = 0x9;
+  pub const TAG_BYTES: u64 = 0xA;
+  pub const TAG_WEAK: u64 = 0xB;
+  pub const TAG_LONG: u64 = 0xC;
+  pub const TAG_DOUBLE: u64 = 0xD;
+  pub const TAG_BOOL: u64 = 0xE;
+  pub const TAG_CHAR: u64 = 0xF;
 
   pub const NULL: Value = Self(Self::TAG_NULL);
 
@@ -46,7 +68,29 @@ impl Value {
 
   #[inline(always)]
   pub const fn is_not_null(&self) -> bool {
-    self.tag() == Self::TAG_NULL
+    self.tag() != Self::TAG_NULL
+  }
+
+  /// Whether the value points to a heap object.
+  #[inline(always)]
+  pub const fn is_reference(&self) -> bool {
+    matches!(
+      self.tag(),
+      Self::TAG_STRING
+        | Self::TAG_DICT
+        | Self::TAG_ARRAY
+        | Self::TAG_CLASS
+        | Self::TAG_FUNCTION
+        | Self::TAG_NATIVE
+        | Self::TAG_BYTES
+        | Self::TAG_WEAK
+    ) || self.is_boxed()
+  }
+
+  /// Whether the value is a long or double boxed on the heap.
+  #[inline(always)]
+  pub const fn is_boxed(&self) -> bool {
+    matches!(self.tag(), Self::TAG_LONG | Self::TAG_DOUBLE) && self.0 & BOXED != 0
   }
 
   #[inline(always)]
@@ -66,7 +110,7 @@ impl Value {
 
   #[inline(always)]
   pub const fn mk_integer(integer: i32) -> Self {
-    Self::new(Self::TAG_INTEGER, integer as u64)
+    Self::new(Self::TAG_INTEGER, integer as u32 as u64)
   }
 
   #[inline(always)]
@@ -74,9 +118,39 @@ impl Value {
     Self::new(Self::TAG_FLOAT, float.to_bits() as u64)
   }
 
+  #[inline(always)]
+  pub const fn mk_bool(bool: bool) -> Self {
+    Self::new(Self::TAG_BOOL, bool as u64)
+  }
+
+  #[inline(always)]
+  pub const fn mk_char(char: char) -> Self {
+    Self::new(Self::TAG_CHAR, char as u64)
+  }
+
+  /// The long `long`, `None` when it needs boxing, longs are stored shifted left by one bit.
+  #[inline(always)]
+  pub const fn try_mk_long(long: i64) -> Option<Self> {
+    let unused = TAG_BITS + 1;
+    match (long << unused) >> unused == long {
+      true => Some(Self::new(Self::TAG_LONG, (long << 1) as u64 & VALUE_MASK)),
+      false => None,
+    }
+  }
+
+  /// The double `double`, `None` when it needs boxing, doubles are stored without their low bits.
+  #[inline(always)]
+  pub fn try_mk_double(double: f64) -> Option<Self> {
+    let bits = double.to_bits();
+    match bits.trailing_zeros() >= DOUBLE_LOW_BITS {
+      true => Some(Self::new(Self::TAG_DOUBLE, bits >> TAG_BITS)),
+      false => None,
+    }
+  }
+
   #[inline(always)]
   pub const fn byte(&self) -> u8 {
-    (self.0 & 0xF) as u8
+    (self.0 & 0xFF) as u8
   }
 
   #[inline(always)]
@@ -95,10 +169,83 @@ impl Value {
     f32::from_bits((self.0 & 0xFFFF_FFFF) as u32)
   }
 
+  #[inline(always)]
+  pub const fn bool(&self) -> bool {
+    self.0 & 1 != 0
+  }
+
+  #[inline(always)]
+  pub fn char(&self) -> char {
+    char::from_u32((self.0 & 0xFFFF_FFFF) as u32).unwrap_or(char::REPLACEMENT_CHARACTER)
+  }
+
+  #[inline(always)]
+  pub fn long(&self) -> i64 {
+    match self.is_boxed() {
+      true => unsafe { *(self.reference() as *const i64) },
+      false => ((self.0 << TAG_BITS) as i64) >> (TAG_BITS + 1),
+    }
+  }
+
+  #[inline(always)]
+  pub fn double(&self) -> f64 {
+    match self.is_boxed() {
+      true => unsafe { *(self.reference() as *const f64) },
+      false => f64::from_bits(self.0 << TAG_BITS),
+    }
+  }
+
   #[inline(always)]
   pub fn reference(&self) -> Reference {
-    (self.0 & !TAG_MASK) as Reference
+    (self.0 & !TAG_MASK & !BOXED) as Reference
   }
+
+  /// The value itself if tagged `tag`.
+  #[inline(always)]
+  pub const fn expect(self, tag: u64) -> Result<Self, TypeMismatch> {
+    match self.tag() == tag {
+      true => Ok(self),
+      false => {
+        Err(TypeMismatch { expected: Self::new(tag, 0).type_name(), found: self.type_name() })
+      }
+    }
+  }
+
+  /// The reference of the object if tagged `tag`.
+  #[inline(always)]
+  pub fn object(&self, tag: u64) -> Result<Reference, TypeMismatch> {
+    self.expect(tag).map(|value| value.reference())
+  }
+
+  /// The name of the value type.
+  pub const fn type_name(&self) -> &'static str {
+    match self.tag() {
+      Self::TAG_NULL => "null",
+      Self::TAG_BYTE => "byte",
+      Self::TAG_INTEGER => "integer",
+      Self::TAG_FLOAT => "float",
+      Self::TAG_STRING => "string",
+      Self::TAG_DICT => "dict",
+      Self::TAG_ARRAY => "array",
+      Self::TAG_CLASS => "class",
+      Self::TAG_FUNCTION => "function",
+      Self::TAG_NATIVE => "native",
+      Self::TAG_BYTES => "bytes",
+      Self::TAG_WEAK => "weak",
+      Self::TAG_LONG => "long",
+      Self::TAG_DOUBLE => "double",
+      Self::TAG_BOOL => "bool",
+      Self::TAG_CHAR => "char",
+      _ => "unknown",
+    }
+  }
+}
+
+/// A value of another type than the expected one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TypeMismatch {
+  pub expected: &'static str,
+  pub found: &'static str,
 }
 
 impl fmt::Debug for Value {
@@ -108,43 +255,69 @@ impl fmt::Debug for Value {
       Self::TAG_BYTE => write!(f, "{}", self.byte()),
       Self::TAG_INTEGER => write!(f, "{}", self.integer()),
       Self::TAG_FLOAT => write!(f, "{}", self.float()),
-      Self::TAG_STRING | Self::TAG_DICT | Self::TAG_ARRAY | Self::TAG_CLASS => {
-        write!(f, "@{:012x}", self.reference())
-      }
+      Self::TAG_LONG => write!(f, "{}", self.long()),
+      Self::TAG_DOUBLE => write!(f, "{}", self.double()),
+      Self::TAG_BOOL => write!(f, "{}", self.bool()),
+      Self::TAG_CHAR => write!(f, "{:?}", self.char()),
+      Self::TAG_STRING
+      | Self::TAG_DICT
+      | Self::TAG_ARRAY
+      | Self::TAG_CLASS
+      | Self::TAG_FUNCTION
+      | Self::TAG_NATIVE
+      | Self::TAG_BYTES
+      | Self::TAG_WEAK => write!(f, "@{:012x}", self.reference()),
       _ => unreachable!(),
     }
   }
 }
 
-impl From<Value> for Byte8 {
-  fn from(value: Value) -> Self {
-    assert!(value.tag() == Value::TAG_BYTE);
-    value.byte()
+impl From<Byte8> for Value {
+  fn from(byte: Byte8) -> Self {
+    Value::mk_byte(byte)
+  }
+}
+
+impl From<Int32> for Value {
+  fn from(integer: Int32) -> Self {
+    Value::mk_integer(integer)
   }
 }
 
-impl From<Value> for Int32 {
-  fn from(value: Value) -> Self {
-    assert!(value.tag() == Value::TAG_INTEGER);
-    value.integer()
+impl From<Float32> for Value {
+  fn from(float: Float32) -> Self {
+    Value::mk_float(float)
   }
 }
 
-impl From<Value> for Float32 {
-  fn from(value: Value) -> Self {
-    assert!(value.tag() == Value::TAG_FLOAT);
-    value.float()
+impl From<bool> for Value {
+  fn from(bool: bool) -> Self {
+    Value::mk_bool(bool)
   }
 }
 
-impl From<Value> for Reference {
-  fn from(value: Value) -> Self {
-    assert!(
-      value.tag() == Value::TAG_STRING
-        || value.tag() == Value::TAG_DICT
-        || value.tag() == Value::TAG_ARRAY
-        || value.tag() == Value::TAG_CLASS
-    );
-    value.reference()
+impl From<char> for Value {
+  fn from(char: char) -> Self {
+    Value::mk_char(char)
   }
 }
+
+macro_rules! try_from_value {
+  ($type:ty, $tag:ident, $value:ident) => {
+    impl TryFrom<Value> for $type {
+      type Error = TypeMismatch;
+
+      fn try_from(value: Value) -> Result<Self, TypeMismatch> {
+        value.expect(Value::$tag).map(|value| value.$value())
+      }
+    }
+  };
+}
+
+try_from_value!(Byte8, TAG_BYTE, byte);
+try_from_value!(Int32, TAG_INTEGER, integer);
+try_from_value!(Float32, TAG_FLOAT, float);
+try_from_value!(Int64, TAG_LONG, long);
+try_from_value!(Float64, TAG_DOUBLE, double);
+try_from_value!(bool, TAG_BOOL, bool);
+try_from_value!(char, TAG_CHAR, char);
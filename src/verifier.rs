@@ -0,0 +1,698 @@
+use core::fmt;
+use std::{collections::BTreeMap, rc::Rc};
+
+use crate::{
+  class::Class,
+  function::{Code, Function},
+  instruction::{Instruction, Instructions},
+  module::Module,
+  opcode::{self, Operand},
+  pool_entry::PoolEntry,
+  runtime::{Error, Result},
+};
+
+/// A bytecode verification error.
+#[derive(Debug)]
+pub struct VerifyError {
+  /// The function path, `module:function`.
+  pub function: String,
+  /// The faulty instruction address.
+  pub ip: usize,
+  /// The error description.
+  pub reason: String,
+}
+
+impl fmt::Display for VerifyError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "Invalid bytecode at {}%{}: {}", self.function, self.ip, self.reason)
+  }
+}
+
+/// The deepest operand stack of a verified function, the runtime keeps this many slots free for
+/// each frame it enters.
+pub const MAX_STACK: usize = 0x100;
+
+/// Static bytecode verifier.
+///
+/// Checks opcodes, operands, branch targets, constant pool entries, local indexes and the operand
+/// stack depth of every instruction, so a verified function can't crash the runtime. Every call
+/// leaves exactly one value, so the depth is known at each instruction.
+pub struct Verifier<'a> {
+  modules: &'a BTreeMap<Rc<str>, &'a Module>,
+  classes: &'a BTreeMap<Rc<str>, &'a Class>,
+}
+
+impl<'a> Verifier<'a> {
+  pub fn new(
+    modules: &'a BTreeMap<Rc<str>, &'a Module>,
+    classes: &'a BTreeMap<Rc<str>, &'a Class>,
+  ) -> Self {
+    Self { modules, classes }
+  }
+
+  pub fn verify_module(&mut self, module: &Module) -> Result<()> {
+    for function in module.functions.values() {
+      self.verify(&module.name, &module.constants, function, false)?;
+    }
+    Ok(())
+  }
+
+  pub fn verify_class(&mut self, class: &Class) -> Result<()> {
+    for method in class.methods.values() {
+      self.verify(&class.name, &class.constants, method, true)?;
+    }
+    Ok(())
+  }
+
+  /// Verify `function`, a `method` keeps `this` in local 0 before its arguments.
+  fn verify(
+    &self,
+    owner: &str,
+    constants: &[PoolEntry],
+    function: &Function,
+    method: bool,
+  ) -> Result<()> {
+    let Code::Bytecode(code) = &function.code else {
+      return Ok(());
+    };
+    let fail = |ip: usize, reason: String| {
+      Error::Verify(VerifyError { function: format!("{owner}:{}", function.name), ip, reason })
+    };
+
+    let (arguments, locals) = (function.arguments, function.locals);
+    if locals < arguments as u16 + method as u16 {
+      return Err(fail(
+        0,
+        match method {
+          true => format!("Method has {arguments} arguments and 'this' but only {locals} locals."),
+          false => format!("Function has {arguments} arguments but only {locals} locals."),
+        },
+      ));
+    }
+
+    let instructions = Instructions::new(code)
+      .collect::<std::result::Result<Vec<_>, _>>()
+      .map_err(|e| fail(e.ip(), e.to_string()))?;
+    if instructions.is_empty() {
+      return Err(fail(0, "Empty function.".to_string()));
+    }
+
+    let mut boundaries = vec![None; code.len()];
+    for (index, instruction) in instructions.iter().enumerate() {
+      boundaries[instruction.ip] = Some(index);
+    }
+
+    let mut effects = Vec::with_capacity(instructions.len());
+    for instruction in instructions.iter() {
+      self
+        .check_operands(instruction, function, constants, &boundaries)
+        .map_err(|reason| fail(instruction.ip, reason))?;
+      let effect = self
+        .effect(instruction, function, constants)
+        .map_err(|reason| fail(instruction.ip, reason))?;
+      effects.push(effect);
+    }
+
+    let mut depths = vec![None; instructions.len()];
+    let mut worklist = vec![(0, 0)];
+
+    for handler in function.handlers.iter() {
+      let (start, end, target) =
+        (handler.start as usize, handler.end as usize, handler.handler as usize);
+      if start >= end || boundaries.get(start).copied().flatten().is_none() {
+        return Err(fail(start, format!("Invalid handler range @{start}..@{end}.")));
+      }
+      if end != code.len() && boundaries.get(end).copied().flatten().is_none() {
+        return Err(fail(end, format!("Handler range end @{end} is not an instruction.")));
+      }
+      let Some(index) = boundaries.get(target).copied().flatten() else {
+        return Err(fail(target, format!("Handler @{target} is not an instruction.")));
+      };
+      if let Some(class) = handler.class {
+        match constants.get(class as usize) {
+          Some(PoolEntry::Class(..)) => (),
+          entry => return Err(fail(target, invalid_entry(class as usize, "class", entry))),
+        }
+      }
+      // The handler starts with only the exception on the stack.
+      worklist.push((index, 1));
+    }
+
+    while let Some((index, depth)) = worklist.pop() {
+      let instruction = &instructions[index];
+      match depths[index] {
+        None => depths[index] = Some(depth),
+        Some(expected) if expected != depth => {
+          return Err(fail(
+            instruction.ip,
+            format!("Inconsistent stack depth, expected {expected} but found {depth}."),
+          ));
+        }
+        Some(_) => continue,
+      }
+
+      let (pops, pushes) = effects[index];
+      if depth < pops {
+        return Err(fail(
+          instruction.ip,
+          format!("Stack underflow, {} pops {pops} but depth is {depth}.", name(instruction)),
+        ));
+      }
+      let depth = depth - pops + pushes;
+      if depth > MAX_STACK {
+        return Err(fail(
+          instruction.ip,
+          format!("Stack depth {depth} exceeds the limit of {MAX_STACK}."),
+        ));
+      }
+
+      let next = |ip: usize| boundaries.get(ip).copied().flatten();
+      match instruction.opcode {
+        opcode::HALT | opcode::THROW => (),
+        opcode::RETURN => {
+          if depth > 1 {
+            return Err(fail(
+              instruction.ip,
+              format!("Returns {depth} values, at most 1 allowed."),
+            ));
+          }
+        }
+        opcode::TAILCALL => {
+          if depth > 0 {
+            return Err(fail(
+              instruction.ip,
+              format!("Tailcall leaves {depth} values on the stack."),
+            ));
+          }
+          worklist.push((0, 0));
+        }
+        opcode::GOTO => worklist.push((next(instruction.operands[0]).unwrap(), depth)),
+        opcode::TABLESWITCH | opcode::LOOKUPSWITCH => {
+          for (operand, target) in instruction.kinds().zip(instruction.operands.iter()) {
+            if operand == Operand::Label {
+              worklist.push((next(*target).unwrap(), depth));
+            }
+          }
+        }
+        opcode => {
+          if opcode::operands(opcode).contains(&Operand::Label) {
+            worklist.push((next(instruction.operands[0]).unwrap(), depth));
+          }
+          match next(instruction.next()) {
+            Some(index) => worklist.push((index, depth)),
+            None => return Err(fail(instruction.ip, "Falls off the end of the code.".to_string())),
+          }
+        }
+      }
+    }
+
+    Ok(())
+  }
+
+  fn check_operands(
+    &self,
+    instruction: &Instruction,
+    function: &Function,
+    constants: &[PoolEntry],
+    boundaries: &[Option<usize>],
+  ) -> std::result::Result<(), String> {
+    match instruction.opcode {
+      opcode::NEW_STRING => return Err("Reserved opcode NEW_STRING.".to_string()),
+      opcode::LOAD_0 | opcode::STORE_0 => check_local(0, function)?,
+      opcode::LOAD_1 | opcode::STORE_1 => check_local(1, function)?,
+      opcode::LOAD_2 | opcode::STORE_2 => check_local(2, function)?,
+      opcode::LOAD_3 | opcode::STORE_3 => check_local(3, function)?,
+      opcode::TABLESWITCH => {
+        let [low, high] = [1, 2].map(|index| instruction.operands[index] as u32 as i32);
+        if low > high {
+          return Err(format!("Empty table {low}..={high}."));
+        }
+      }
+      opcode::LOOKUPSWITCH => {
+        let keys = instruction.operands[2..].iter().step_by(2).map(|key| *key as u32 as i32);
+        if !keys.is_sorted_by(|key1, key2| key1 < key2) {
+          return Err("Lookup keys are not sorted or not unique.".to_string());
+        }
+      }
+      _ => (),
+    }
+
+    for (operand, value) in instruction.kinds().zip(instruction.operands.iter().copied()) {
+      match operand {
+        Operand::Byte | Operand::Short | Operand::Int => (),
+        Operand::Local => check_local(value, function)?,
+        Operand::Label => {
+          if boundaries.get(value).copied().flatten().is_none() {
+            return Err(format!("Branch target @{value} is not an instruction."));
+          }
+        }
+        Operand::Constant => match constants.get(value) {
+          Some(
+            PoolEntry::String(..)
+            | PoolEntry::Integer(..)
+            | PoolEntry::Float(..)
+            | PoolEntry::Long(..)
+            | PoolEntry::Double(..),
+          ) => (),
+          entry => return Err(invalid_entry(value, "constant", entry)),
+        },
+        Operand::Module => match constants.get(value) {
+          Some(PoolEntry::Module(..)) => (),
+          entry => return Err(invalid_entry(value, "module", entry)),
+        },
+        Operand::Function => match constants.get(value) {
+          Some(PoolEntry::Function(..)) => (),
+          entry => return Err(invalid_entry(value, "function", entry)),
+        },
+        Operand::Class => match constants.get(value) {
+          Some(PoolEntry::Class(..)) => (),
+          entry => return Err(invalid_entry(value, "class", entry)),
+        },
+        Operand::Field => match constants.get(value) {
+          Some(PoolEntry::Field(..)) => (),
+          entry => return Err(invalid_entry(value, "field", entry)),
+        },
+      }
+    }
+
+    Ok(())
+  }
+
+  /// Values popped and pushed by `instruction`, a call pops its arguments and pushes its result.
+  fn effect(
+    &self,
+    instruction: &Instruction,
+    function: &Function,
+    constants: &[PoolEntry],
+  ) -> std::result::Result<(usize, usize), String> {
+    let effect = match instruction.opcode {
+      opcode::HALT | opcode::RETURN | opcode::GOTO | opcode::IINC => (0, 0),
+
+      opcode::ICONST_0
+      | opcode::ICONST_1
+      | opcode::FCONST_0
+      | opcode::FCONST_1
+      | opcode::LOAD
+      | opcode::LOAD_0
+      | opcode::LOAD_1
+      | opcode::LOAD_2
+      | opcode::LOAD_3
+      | opcode::LOADCONST
+      | opcode::NEW_DICT
+      | opcode::I_PUSH_BYTE
+      | opcode::I_PUSH_SHORT
+      | opcode::CONST_NULL
+      | opcode::PUSH_BYTE
+      | opcode::TRUE
+      | opcode::FALSE => (0, 1),
+      opcode::STORE
+      | opcode::STORE_0
+      | opcode::STORE_1
+      | opcode::STORE_2
+      | opcode::STORE_3
+      | opcode::POP
+      | opcode::IF_NULL
+      | opcode::IFNOT_NULL
+      | opcode::IF_TRUE
+      | opcode::IF_FALSE
+      | opcode::TABLESWITCH
+      | opcode::LOOKUPSWITCH
+      | opcode::THROW => (1, 0),
+
+      opcode::I2F
+      | opcode::F2I
+      | opcode::I2L
+      | opcode::L2I
+      | opcode::F2D
+      | opcode::D2F
+      | opcode::L2D
+      | opcode::D2L
+      | opcode::LNEG
+      | opcode::DNEG
+      | opcode::INEG
+      | opcode::FNEG
+      | opcode::BNEG
+      | opcode::IS_ZERO
+      | opcode::NOT
+      | opcode::I2C
+      | opcode::C2I
+      | opcode::NEW_ARRAY
+      | opcode::GET_FIELD => (1, 1),
+
+      opcode::DUP => (1, 2),
+
+      opcode::I_IFEQ
+      | opcode::I_IFNEQ
+      | opcode::I_IFGT
+      | opcode::I_IFGE
+      | opcode::I_IFLT
+      | opcode::I_IFLE
+      | opcode::L_IFEQ
+      | opcode::L_IFNEQ
+      | opcode::L_IFGT
+      | opcode::L_IFGE
+      | opcode::L_IFLT
+      | opcode::L_IFLE
+      | opcode::D_IFEQ
+      | opcode::D_IFNEQ
+      | opcode::D_IFGT
+      | opcode::D_IFGE
+      | opcode::D_IFLT
+      | opcode::D_IFLE
+      | opcode::F_IFEQ
+      | opcode::F_IFNEQ
+      | opcode::F_IFGT
+      | opcode::F_IFGE
+      | opcode::F_IFLT
+      | opcode::F_IFLE
+      | opcode::B_IFEQ
+      | opcode::B_IFNEQ
+      | opcode::B_IFGT
+      | opcode::B_IFGE
+      | opcode::B_IFLT
+      | opcode::B_IFLE
+      | opcode::IF_REF_EQ
+      | opcode::IF_REF_NE
+      | opcode::SET_FIELD => (2, 0),
+
+      opcode::GET_DICT
+      | opcode::ARRAY_GET
+      | opcode::STRING_GET
+      | opcode::CMP
+      | opcode::IADD
+      | opcode::ISUB
+      | opcode::IMUL
+      | opcode::IDIV
+      | opcode::IREM
+      | opcode::IAND
+      | opcode::IOR
+      | opcode::IXOR
+      | opcode::ISHL
+      | opcode::ISHR
+      | opcode::IUSHR
+      | opcode::IEXP
+      | opcode::FADD
+      | opcode::FSUB
+      | opcode::FMUL
+      | opcode::FDIV
+      | opcode::FREM
+      | opcode::BADD
+      | opcode::BSUB
+      | opcode::BMUL
+      | opcode::BDIV
+      | opcode::BREM
+      | opcode::BAND
+      | opcode::BOR
+      | opcode::BXOR
+      | opcode::BSHL
+      | opcode::BSHR
+      | opcode::LADD
+      | opcode::LSUB
+      | opcode::LMUL
+      | opcode::LDIV
+      | opcode::LREM
+      | opcode::LAND
+      | opcode::LOR
+      | opcode::LXOR
+      | opcode::LSHL
+      | opcode::LSHR
+      | opcode::LUSHR
+      | opcode::DADD
+      | opcode::DSUB
+      | opcode::DMUL
+      | opcode::DDIV
+      | opcode::DREM => (2, 1),
+
+      opcode::SET_DICT | opcode::ARRAY_SET => (3, 0),
+
+      opcode::NEW_BYTES => (instruction.operands[0], 1),
+      opcode::BYTES_PUSH => (2, 0),
+
+      opcode::TAILCALL => (function.arguments as usize, 0),
+
+      opcode::CALL => {
+        let callee = self.function(constants, instruction.operands[0], instruction.operands[1])?;
+        (callee.arguments as usize, 1)
+      }
+
+      opcode::NEW => {
+        let Some(PoolEntry::Class(class)) = constants.get(instruction.operands[0]) else {
+          return Err(invalid_entry(instruction.operands[0], "class", None));
+        };
+        let class = *self
+          .classes
+          .get(class.as_str())
+          .ok_or(Error::ClassNotFound(class.clone()).to_string())?;
+        let constructor = class
+          .methods
+          .get("new")
+          .ok_or(Error::FunctionNotFound(format!("{}:new", class.name)).to_string())?;
+        (constructor.arguments as usize, 1)
+      }
+
+      opcode::CALL_METHOD => (instruction.operands[1] + 1, 1),
+
+      opcode::CLOSURE => {
+        let callee = self.function(constants, instruction.operands[0], instruction.operands[1])?;
+        let captures = instruction.operands[2];
+        if captures > callee.arguments as usize {
+          return Err(format!(
+            "Closure captures {captures} values, '{}' takes {} argument(s).",
+            callee.name, callee.arguments
+          ));
+        }
+        (captures, 1)
+      }
+
+      opcode::CALL_VALUE => (instruction.operands[0] + 1, 1),
+
+      opcode => return Err(format!("Unchecked opcode {opcode:#04X}.")),
+    };
+    Ok(effect)
+  }
+
+  /// The function of the `module` and `function` pool entries.
+  fn function(
+    &self,
+    constants: &[PoolEntry],
+    module: usize,
+    function: usize,
+  ) -> std::result::Result<&'a Function, String> {
+    let (Some(PoolEntry::Module(module)), Some(PoolEntry::Function(function))) =
+      (constants.get(module), constants.get(function))
+    else {
+      return Err(format!("Constants #{module} and #{function} are not a module and a function."));
+    };
+    let module = *self
+      .modules
+      .get(module.as_str())
+      .ok_or(Error::ModuleNotFound(module.clone()).to_string())?;
+    module.fetch_function_with_name(function).map_err(|e| e.to_string())
+  }
+}
+
+fn name(instruction: &Instruction) -> &'static str {
+  opcode::TO_STR[instruction.opcode as usize]
+}
+
+fn check_local(index: usize, function: &Function) -> std::result::Result<(), String> {
+  if index >= function.locals as usize {
+    Err(format!("Local {index} out of bounds, function has {} locals.", function.locals))
+  } else {
+    Ok(())
+  }
+}
+
+fn invalid_entry(index: usize, expected: &str, entry: Option<&PoolEntry>) -> String {
+  match entry {
+    Some(entry) => format!("Constant #{index} is {entry:?}, expected a {expected} entry."),
+    None => format!("Constant #{index} out of bounds."),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    asm,
+    class::builder::ClassBuilder,
+    function::{builder::FunctionBuilder, Handler},
+    module::builder::ModuleBuilder,
+  };
+
+  /// The rejection reason of `module`, verified alone.
+  fn reject(module: Module) -> String {
+    let modules = BTreeMap::from([(module.name.clone(), &module)]);
+    let classes = BTreeMap::new();
+    match Verifier::new(&modules, &classes).verify_module(&module) {
+      Err(Error::Verify(e)) => e.reason,
+      Err(e) => panic!("unexpected error {e}"),
+      Ok(()) => panic!("module verified"),
+    }
+  }
+
+  /// The rejection reason of a function of one argument and two locals running `body`.
+  fn reject_body(body: &str) -> String {
+    let source = format!(".module m\n.function f args=1 locals=2\n{body}\n.end\n.function g args=1\n  LOAD_0\n  RETURN\n.end");
+    reject(asm::assemble(&source).unwrap())
+  }
+
+  fn bytecode(code: &[u8], handler: Option<Handler>) -> Module {
+    let mut function = FunctionBuilder::new().with_name("f").with_locals(1).with_bytecode(code);
+    if let Some(handler) = handler {
+      function = function.with_handler(handler);
+    }
+    ModuleBuilder::new()
+      .with_name("m")
+      .with_constant(PoolEntry::Module("m".to_string()))
+      .with_function(function.build())
+      .build()
+  }
+
+  #[test]
+  fn accepts_recursion_and_dynamic_calls() {
+    let source = r#"
+.module m
+.function fib args=1 locals=1
+  LOAD_0
+  I_PUSH_BYTE 2
+  I_IFLT base
+  LOAD_0
+  ICONST_1
+  ISUB
+  CALL m:fib
+  LOAD_0
+  I_PUSH_BYTE 2
+  ISUB
+  CALL m:fib
+  IADD
+  RETURN
+base:
+  LOAD_0
+  RETURN
+.end
+.function apply args=2 locals=2
+  LOAD_1
+  LOAD_0
+  CALL_VALUE 1
+  RETURN
+.end
+"#;
+    let module = asm::assemble(source).unwrap();
+    let modules = BTreeMap::from([(module.name.clone(), &module)]);
+    let classes = BTreeMap::new();
+    Verifier::new(&modules, &classes).verify_module(&module).unwrap();
+  }
+
+  #[test]
+  fn rejects_undecodable_code() {
+    assert_eq!(reject(bytecode(&[0xFF], None)), "Unknown opcode 0xFF at @0.");
+    assert_eq!(reject(bytecode(&[opcode::GOTO, 0], None)), "Truncated GOTO operands at @0.");
+    assert_eq!(reject(bytecode(&[], None)), "Empty function.");
+    assert_eq!(reject(bytecode(&[opcode::NEW_STRING], None)), "Reserved opcode NEW_STRING.");
+  }
+
+  #[test]
+  fn rejects_bad_operands() {
+    assert_eq!(
+      reject_body("  I_PUSH_SHORT 1\n  GOTO @1"),
+      "Branch target @1 is not an instruction."
+    );
+    assert_eq!(reject_body("  LOAD 2\n  RETURN"), "Local 2 out of bounds, function has 2 locals.");
+    assert_eq!(reject_body("  LOAD_3\n  RETURN"), "Local 3 out of bounds, function has 2 locals.");
+    assert_eq!(reject_body("  LOADCONST #9\n  RETURN"), "Constant #9 out of bounds.");
+    assert_eq!(
+      reject_body("  CALL m:g\n  LOADCONST #0\n  RETURN"),
+      "Constant #0 is Module(\"m\"), expected a constant entry."
+    );
+    assert_eq!(reject_body("  TABLESWITCH end 2 1\nend:\n  RETURN"), "Empty table 2..=1.");
+    assert_eq!(
+      reject_body("  LOOKUPSWITCH end 2 1 end 1 end\nend:\n  RETURN"),
+      "Lookup keys are not sorted or not unique."
+    );
+  }
+
+  #[test]
+  fn rejects_missing_callees() {
+    assert_eq!(reject_body("  CALL nowhere:f\n  RETURN"), "Module 'nowhere' not found.");
+    assert_eq!(reject_body("  CALL m:nope\n  RETURN"), "Function 'nope' not found.");
+    assert_eq!(reject_body("  NEW Nope\n  RETURN"), "Class 'Nope' not found.");
+    assert_eq!(
+      reject_body("  LOAD_0\n  LOAD_0\n  CLOSURE m:g 2\n  RETURN"),
+      "Closure captures 2 values, 'g' takes 1 argument(s)."
+    );
+  }
+
+  #[test]
+  fn rejects_missing_argument_locals() {
+    let code = [opcode::RETURN];
+    let function = |arguments, locals| {
+      FunctionBuilder::new()
+        .with_name("put")
+        .with_arguments(arguments)
+        .with_locals(locals)
+        .with_bytecode(&code)
+        .build()
+    };
+    let module = ModuleBuilder::new().with_name("m").with_function(function(2, 1)).build();
+    assert_eq!(reject(module), "Function has 2 arguments but only 1 locals.");
+
+    let class = ClassBuilder::new().with_name("C").with_method(function(1, 1)).build();
+    let modules = BTreeMap::new();
+    let classes = BTreeMap::from([(class.name.clone(), &class)]);
+    let Err(Error::Verify(error)) = Verifier::new(&modules, &classes).verify_class(&class) else {
+      panic!("class verified")
+    };
+    assert_eq!(error.reason, "Method has 1 arguments and 'this' but only 1 locals.");
+  }
+
+  #[test]
+  fn rejects_bad_handlers() {
+    let code = [opcode::ICONST_0, opcode::I_PUSH_BYTE, 1, opcode::RETURN];
+    let handler = |start, end, handler, class| Handler { start, end, handler, class };
+    assert_eq!(
+      reject(bytecode(&code, Some(handler(1, 1, 3, None)))),
+      "Invalid handler range @1..@1."
+    );
+    assert_eq!(
+      reject(bytecode(&code, Some(handler(0, 2, 3, None)))),
+      "Handler range end @2 is not an instruction."
+    );
+    assert_eq!(
+      reject(bytecode(&code, Some(handler(0, 1, 2, None)))),
+      "Handler @2 is not an instruction."
+    );
+    assert_eq!(
+      reject(bytecode(&code, Some(handler(0, 1, 3, Some(0))))),
+      "Constant #0 is Module(\"m\"), expected a class entry."
+    );
+  }
+
+  #[test]
+  fn rejects_bad_stack_depths() {
+    assert_eq!(reject_body("  POP\n  RETURN"), "Stack underflow, POP pops 1 but depth is 0.");
+    assert_eq!(
+      reject_body("  LOAD_0\n  CALL_VALUE 0\n  POP\n  POP\n  RETURN"),
+      "Stack underflow, POP pops 1 but depth is 0."
+    );
+    assert_eq!(
+      reject_body("  LOAD_0\n  CALL_METHOD m 1\n  RETURN"),
+      "Stack underflow, CALL_METHOD pops 2 but depth is 1."
+    );
+    assert_eq!(
+      reject_body("loop:\n  LOAD_0\n  GOTO loop"),
+      "Inconsistent stack depth, expected 0 but found 1."
+    );
+    assert_eq!(reject_body("  LOAD_0"), "Falls off the end of the code.");
+    assert_eq!(reject_body("  LOAD_0\n  LOAD_0\n  RETURN"), "Returns 2 values, at most 1 allowed.");
+    assert_eq!(
+      reject_body("  LOAD_0\n  LOAD_0\n  TAILCALL"),
+      "Tailcall leaves 1 values on the stack."
+    );
+    let deep = "  LOAD_0\n".repeat(MAX_STACK + 1);
+    assert_eq!(
+      reject_body(&format!("{deep}  RETURN")),
+      format!("Stack depth {} exceeds the limit of {MAX_STACK}.", MAX_STACK + 1)
+    );
+  }
+}
@@ -0,0 +1,188 @@
+use crate::{
+  gc::{Collector, Gc, ObjString},
+  loader::{Loader, LoaderArena},
+  local::Local,
+  module::{builder::ModuleBuilder, Module},
+  runtime::{Error, Result, Runtime},
+  value::Value,
+};
+
+/// Name of the module and function at the bottom of the call stack of an embedded VM.
+const HOST: &str = "<host>";
+
+/// An embedded Grape Virtual Machine.
+///
+/// ```no_run
+/// use grape::{loader::LoaderArena, module::builder::ModuleBuilder, Value, Vm};
+///
+/// let arena = LoaderArena::default();
+/// let mut vm = Vm::new(&arena);
+/// let offset = 10;
+/// vm.register_module(
+///   ModuleBuilder::new()
+///     .with_name("host")
+///     .with_native("offset", 1, move |ctx| {
+///       Ok(Some(Value::from(ctx.arg::<i32>(0)? + offset)))
+///     })
+///     .build(),
+/// )?;
+/// vm.load_path("main")?;
+/// let result: i32 = vm.call("main", "compute", &[Value::from(32)])?;
+/// # Ok::<(), grape::Error>(())
+/// ```
+///
+/// Heap values returned by [`Vm::call`] or allocated by the host are not roots, they can be
+/// collected during the next call.
+pub struct Vm<'c> {
+  arena: &'c LoaderArena,
+  runtime: Runtime<'c>,
+}
+
+impl<'c> Vm<'c> {
+  /// A VM with the built-in modules.
+  pub fn new(arena: &'c LoaderArena) -> Self {
+    Self::with_collector(arena, Collector::default())
+  }
+
+  /// A VM with the built-in modules, collecting its heap with `collector`.
+  pub fn with_collector(arena: &'c LoaderArena, collector: Collector) -> Self {
+    let host = arena.alloc_module(
+      ModuleBuilder::new().with_name(HOST).with_native(HOST, 0, |_| Ok(None)).build(),
+    );
+    let function = host.fetch_function_with_name_unchecked(HOST);
+    let context = Loader::new(arena).to_context();
+    let mut runtime = Runtime::new(context, Local::new(0), host, function);
+    *runtime.gc_mut() = Gc::with_collector(collector);
+    Self { arena, runtime }
+  }
+
+  /// Register a module, usually a native module built with
+  /// [`ModuleBuilder::with_native`].
+  pub fn register_module(&mut self, module: Module) -> Result<()> {
+    self.load(|loader| loader.load_module(module))
+  }
+
+  /// Load a module from its binary representation, the modules it references are loaded from
+  /// their paths.
+  pub fn load_bytes(&mut self, bytes: &[u8]) -> Result<()> {
+    self.load(|loader| loader.load_bytes(bytes))
+  }
+
+  /// Load a module and the modules it references from their paths.
+  pub fn load_path(&mut self, module: &str) -> Result<()> {
+    self.load(|loader| loader.load_path(module))
+  }
+
+  /// Call `module:function` with `args` and convert its result, a function returning nothing
+  /// returns `null`.
+  pub fn call<R: FromValue>(&mut self, module: &str, function: &str, args: &[Value]) -> Result<R> {
+    let value = self.runtime.call_function(module, function, args)?;
+    R::from_value(value.unwrap_or(Value::NULL), self.runtime.gc())
+  }
+
+  /// Limit the heap to `max_heap` bytes, allocations past it fail with [`Error::OutOfMemory`].
+  pub fn set_max_heap(&mut self, max_heap: Option<usize>) {
+    self.runtime.gc_mut().set_max_heap(max_heap);
+  }
+
+  /// Allocate a string to pass as argument.
+  pub fn alloc_string(&mut self, s: &str) -> Result<Value> {
+    self.runtime.gc_mut().alloc_string(s.to_string())
+  }
+
+  /// Allocate bytes to pass as argument.
+  pub fn alloc_bytes(&mut self, bytes: &[u8]) -> Result<Value> {
+    self.runtime.gc_mut().alloc_bytes(bytes.to_vec())
+  }
+
+  /// A long to pass as argument, boxed when it does not fit in a value.
+  pub fn alloc_long(&mut self, long: i64) -> Result<Value> {
+    Value::try_mk_long(long).map_or_else(|| self.runtime.gc_mut().alloc_long(long), Ok)
+  }
+
+  /// A double to pass as argument, boxed when it does not fit in a value.
+  pub fn alloc_double(&mut self, double: f64) -> Result<Value> {
+    Value::try_mk_double(double).map_or_else(|| self.runtime.gc_mut().alloc_double(double), Ok)
+  }
+
+  pub fn runtime(&mut self) -> &mut Runtime<'c> {
+    &mut self.runtime
+  }
+
+  fn load(&mut self, f: impl FnOnce(&mut Loader<'c>) -> Result<()>) -> Result<()> {
+    let context = std::mem::take(self.runtime.context_mut());
+    let mut loader = Loader::with_context(self.arena, context);
+    let result = f(&mut loader);
+    *self.runtime.context_mut() = loader.to_context();
+    result?;
+    self.runtime.intern_constants()
+  }
+}
+
+/// Conversion of a Grape value into a Rust type.
+pub trait FromValue: Sized {
+  fn from_value(value: Value, gc: &Gc) -> Result<Self>;
+}
+
+impl FromValue for Value {
+  fn from_value(value: Value, _: &Gc) -> Result<Self> {
+    Ok(value)
+  }
+}
+
+impl FromValue for () {
+  fn from_value(_: Value, _: &Gc) -> Result<Self> {
+    Ok(())
+  }
+}
+
+impl<T: FromValue> FromValue for Option<T> {
+  fn from_value(value: Value, gc: &Gc) -> Result<Self> {
+    match value.tag() {
+      Value::TAG_NULL => Ok(None),
+      _ => T::from_value(value, gc).map(Some),
+    }
+  }
+}
+
+macro_rules! from_value {
+  ($type:ty, $tag:ident, $expected:literal, $value:ident => $convert:expr) => {
+    impl FromValue for $type {
+      fn from_value($value: Value, _: &Gc) -> Result<Self> {
+        match $value.tag() {
+          Value::$tag => Ok($convert),
+          _ => Err(Error::Conversion { expected: $expected, found: $value.type_name() }),
+        }
+      }
+    }
+  };
+}
+
+from_value!(u8, TAG_BYTE, "u8", value => value.byte());
+from_value!(i32, TAG_INTEGER, "i32", value => value.integer());
+from_value!(f32, TAG_FLOAT, "f32", value => value.float());
+from_value!(i64, TAG_LONG, "i64", value => value.long());
+from_value!(f64, TAG_DOUBLE, "f64", value => value.double());
+from_value!(bool, TAG_BOOL, "bool", value => value.bool());
+from_value!(char, TAG_CHAR, "char", value => value.char());
+
+impl FromValue for Vec<u8> {
+  fn from_value(value: Value, _: &Gc) -> Result<Self> {
+    match value.tag() {
+      Value::TAG_BYTES => Ok(Gc::get_bytes(value.reference()).clone()),
+      _ => Err(Error::Conversion { expected: "Vec<u8>", found: value.type_name() }),
+    }
+  }
+}
+
+impl FromValue for String {
+  fn from_value(value: Value, _: &Gc) -> Result<Self> {
+    match value.tag() {
+      Value::TAG_STRING => {
+        let ptr = value.reference() as *const ObjString;
+        Ok(unsafe { (*ptr).contents.clone() })
+      }
+      _ => Err(Error::Conversion { expected: "String", found: value.type_name() }),
+    }
+  }
+}
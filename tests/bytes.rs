@@ -0,0 +1,125 @@
+use grape::{formatting::display_value, gc::Collector, loader::LoaderArena, Error, Result, Value};
+
+use common::vm;
+
+mod common;
+
+const SOURCE: &str = r#"
+.module buf
+
+.function make args=0 locals=0
+  PUSH_BYTE 1
+  PUSH_BYTE 2
+  PUSH_BYTE 3
+  NEW_BYTES 3
+  RETURN
+.end
+
+.function empty args=0 locals=0
+  NEW_BYTES 0
+  RETURN
+.end
+
+.function get args=1 locals=1
+  CALL buf:make
+  LOAD_0
+  CALL bytes:get
+  RETURN
+.end
+
+.function set args=1 locals=2
+  CALL buf:make
+  STORE_1
+  LOAD_1
+  LOAD_0
+  PUSH_BYTE 9
+  CALL bytes:set
+  POP
+  LOAD_1
+  RETURN
+.end
+
+.function slice args=2 locals=2
+  CALL buf:make
+  LOAD_0
+  LOAD_1
+  CALL bytes:slice
+  RETURN
+.end
+
+.function append args=0 locals=1
+  CALL buf:make
+  STORE_0
+  LOAD_0
+  PUSH_BYTE 4
+  NEW_BYTES 1
+  CALL bytes:append
+  POP
+  LOAD_0
+  CALL buf:empty
+  CALL bytes:append
+  POP
+  LOAD_0
+  LOAD_0
+  CALL bytes:append
+  POP
+  LOAD_0
+  RETURN
+.end
+"#;
+
+#[test]
+fn indexes_in_range() -> Result<()> {
+  let arena = LoaderArena::default();
+  let mut vm = vm(&arena, Collector::default(), SOURCE)?;
+  assert_eq!(vm.call::<u8>("buf", "get", &[2.into()])?, 3);
+  assert_eq!(vm.call::<Vec<u8>>("buf", "set", &[0.into()])?, [9, 2, 3]);
+  assert_eq!(vm.call::<Vec<u8>>("buf", "slice", &[1.into(), 3.into()])?, [2, 3]);
+  assert_eq!(vm.call::<Vec<u8>>("buf", "slice", &[3.into(), 3.into()])?, []);
+  Ok(())
+}
+
+#[test]
+fn out_of_range_indexes_are_errors() -> Result<()> {
+  let arena = LoaderArena::default();
+  let mut vm = vm(&arena, Collector::default(), SOURCE)?;
+  let cases: [(&str, &[Value], i64); 6] = [
+    ("get", &[3.into()], 3),
+    ("get", &[(-1).into()], -1),
+    ("set", &[3.into()], 3),
+    ("slice", &[(-1).into(), 2.into()], -1),
+    ("slice", &[2.into(), 1.into()], 1),
+    ("slice", &[0.into(), 4.into()], 4),
+  ];
+  for (function, args, index) in cases {
+    let error = vm.call::<()>("buf", function, args).unwrap_err();
+    assert!(
+      matches!(error, Error::IndexOutOfBounds { index: found, len: 3 } if found == index),
+      "{function}{args:?}: {error}"
+    );
+  }
+  let error = vm.call::<()>("buf", "get", &[3.into()]).unwrap_err();
+  assert_eq!(error.to_string(), "Index 3 out of bounds for length 3.");
+  Ok(())
+}
+
+#[test]
+fn append_extends_in_place() -> Result<()> {
+  let arena = LoaderArena::default();
+  let mut vm = vm(&arena, Collector::default(), SOURCE)?;
+  let bytes: Vec<u8> = vm.call("buf", "append", &[])?;
+  assert_eq!(bytes, [1, 2, 3, 4, 1, 2, 3, 4]);
+  Ok(())
+}
+
+#[test]
+fn bytes_formatting() -> Result<()> {
+  let arena = LoaderArena::default();
+  let mut vm = vm(&arena, Collector::default(), SOURCE)?;
+  let bytes: Value = vm.call("buf", "make", &[])?;
+  let empty: Value = vm.call("buf", "empty", &[])?;
+  let gc = vm.runtime().gc();
+  assert_eq!(display_value(&bytes, gc).to_string(), "<< 1 2 3 >>");
+  assert_eq!(display_value(&empty, gc).to_string(), "<< >>");
+  Ok(())
+}
@@ -0,0 +1,141 @@
+use grape::{formatting::display_value, gc::Collector, loader::LoaderArena, Error, Result, Value};
+
+use common::vm;
+
+mod common;
+
+const SOURCE: &str = r#"
+.module chars
+
+.function index args=1 locals=1
+  LOADCONST "grâpe"
+  LOAD_0
+  STRING_GET
+  RETURN
+.end
+
+.function next args=0 locals=0
+  LOADCONST "a"
+  ICONST_0
+  STRING_GET
+  C2I
+  ICONST_1
+  IADD
+  I2C
+  RETURN
+.end
+
+.function empty args=0 locals=0
+  ICONST_0
+  IS_ZERO
+  IF_FALSE nonzero
+  FALSE
+  NOT
+  RETURN
+nonzero:
+  FALSE
+  RETURN
+.end
+
+.function code args=0 locals=0
+  CALL chars:next
+  C2I
+  RETURN
+.end
+
+.function integer_condition args=0 locals=0
+  ICONST_1
+  IF_TRUE done
+done:
+  RETURN
+.end
+
+.function surrogate args=0 locals=0
+  LOADCONST 55296
+  I2C
+  RETURN
+.end
+
+.function out_of_bounds args=0 locals=0
+try:
+  LOADCONST "a"
+  ICONST_1
+  STRING_GET
+  RETURN
+catch:
+  GET_FIELD message
+  RETURN
+  .catch try catch catch Error
+.end
+"#;
+
+#[test]
+fn strings_index_by_char() -> Result<()> {
+  let arena = LoaderArena::default();
+  let char: char = vm(&arena, Collector::default(), SOURCE)?.call("chars", "index", &[2.into()])?;
+  assert_eq!(char, 'â');
+  Ok(())
+}
+
+#[test]
+fn chars_convert_to_integers() -> Result<()> {
+  let arena = LoaderArena::default();
+  let char: char = vm(&arena, Collector::default(), SOURCE)?.call("chars", "next", &[])?;
+  assert_eq!(char, 'b');
+  Ok(())
+}
+
+#[test]
+fn is_zero_is_a_bool() -> Result<()> {
+  let arena = LoaderArena::default();
+  let value: bool = vm(&arena, Collector::default(), SOURCE)?.call("chars", "empty", &[])?;
+  assert!(value);
+  Ok(())
+}
+
+#[test]
+fn string_index_out_of_bounds() -> Result<()> {
+  let arena = LoaderArena::default();
+  let message: String =
+    vm(&arena, Collector::default(), SOURCE)?.call("chars", "out_of_bounds", &[])?;
+  assert_eq!(message, "Index 1 out of bounds for length 1.");
+  Ok(())
+}
+
+#[test]
+fn conditions_must_be_bools() -> Result<()> {
+  let arena = LoaderArena::default();
+  let error = vm(&arena, Collector::default(), SOURCE)?
+    .call::<()>("chars", "integer_condition", &[])
+    .unwrap_err();
+  assert!(
+    matches!(error, Error::TypeMismatch { expected: "bool", found: "integer", .. }),
+    "{error}"
+  );
+  Ok(())
+}
+
+#[test]
+fn invalid_code_points_are_not_chars() -> Result<()> {
+  let arena = LoaderArena::default();
+  let error =
+    vm(&arena, Collector::default(), SOURCE)?.call::<()>("chars", "surrogate", &[]).unwrap_err();
+  assert!(matches!(error, Error::Conversion { expected: "char", found: "integer" }), "{error}");
+  Ok(())
+}
+
+#[test]
+fn bools_and_chars_format_apart_from_integers() -> Result<()> {
+  let arena = LoaderArena::default();
+  let mut vm = vm(&arena, Collector::default(), SOURCE)?;
+  let values: [Value; 3] = [
+    vm.call("chars", "next", &[])?,
+    vm.call("chars", "code", &[])?,
+    vm.call("chars", "empty", &[])?,
+  ];
+  let gc = vm.runtime().gc();
+  let formatted = values.map(|value| display_value(&value, gc).to_string());
+  assert_eq!(formatted, ["b", "98", "true"]);
+  assert_eq!(values.map(|value| value.type_name()), ["char", "integer", "bool"]);
+  Ok(())
+}
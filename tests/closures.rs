@@ -0,0 +1,94 @@
+use grape::{gc::Collector, loader::LoaderArena, Error, Result};
+
+use common::{vm, COLLECTORS};
+
+mod common;
+
+const SOURCE: &str = r#"
+.module fun
+
+; Subtract, to tell the captured value from the argument.
+.function sub args=2 locals=2
+  LOAD_0
+  LOAD_1
+  ISUB
+  RETURN
+.end
+
+.function partial args=1 locals=1
+  LOAD_0
+  CLOSURE fun:sub 1
+  RETURN
+.end
+
+.function apply args=2 locals=2
+  LOAD_1
+  LOAD_0
+  CALL_VALUE 1
+  RETURN
+.end
+
+.function survives args=0 locals=1
+  LOADCONST "captured"
+  CLOSURE fun:identity 1
+  STORE_0
+  CALL gc:collect
+  POP
+  LOADCONST "garbage"
+  POP
+  CALL gc:collect
+  POP
+  LOAD_0
+  CALL_VALUE 0
+  RETURN
+.end
+
+.function identity args=1 locals=1
+  LOAD_0
+  RETURN
+.end
+
+.function not_callable args=0 locals=0
+  ICONST_1
+  CALL_VALUE 0
+  RETURN
+.end
+
+.function wrong_arity args=0 locals=0
+  LOADCONST 10
+  CLOSURE fun:sub 1
+  CALL_VALUE 0
+  RETURN
+.end
+"#;
+
+#[test]
+fn captures_fill_the_first_arguments() -> Result<()> {
+  let arena = LoaderArena::default();
+  let mut vm = vm(&arena, Collector::default(), SOURCE)?;
+  let ten_minus = vm.call("fun", "partial", &[10.into()])?;
+  let value: i32 = vm.call("fun", "apply", &[ten_minus, 3.into()])?;
+  assert_eq!(value, 7);
+  Ok(())
+}
+
+#[test]
+fn closures_survive_collections() -> Result<()> {
+  for collector in COLLECTORS {
+    let arena = LoaderArena::default();
+    let value: String = vm(&arena, collector, SOURCE)?.call("fun", "survives", &[])?;
+    assert_eq!(value, "captured");
+  }
+  Ok(())
+}
+
+#[test]
+fn calling_a_non_function_is_an_error() -> Result<()> {
+  let arena = LoaderArena::default();
+  let mut vm = vm(&arena, Collector::default(), SOURCE)?;
+  let error = vm.call::<i32>("fun", "not_callable", &[]).unwrap_err();
+  assert!(matches!(&error, Error::NotCallable(value) if value == "1"), "{error}");
+  let error = vm.call::<i32>("fun", "wrong_arity", &[]).unwrap_err();
+  assert!(matches!(error, Error::Arity { expected: 1, found: 0, .. }), "{error}");
+  Ok(())
+}
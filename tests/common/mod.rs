@@ -0,0 +1,39 @@
+//! Helpers shared by the integration tests, each test crate uses a part of them.
+#![allow(dead_code)]
+
+use grape::{asm, gc::Collector, loader::LoaderArena, module::Module, Result, Vm};
+
+/// Every collector, the incremental one scanning a single object or reference per slice.
+pub const COLLECTORS: [Collector; 3] =
+  [Collector::Generational, Collector::Copying, Collector::Incremental { budget: 1 }];
+
+/// A VM collecting with `collector`, with the module assembled from `source`.
+pub fn vm<'a>(arena: &'a LoaderArena, collector: Collector, source: &str) -> Result<Vm<'a>> {
+  vm_with(arena, collector, [], source)
+}
+
+/// A VM collecting with `collector`, with the `hosts` modules and the module assembled from
+/// `source`.
+pub fn vm_with<'a>(
+  arena: &'a LoaderArena,
+  collector: Collector,
+  hosts: impl IntoIterator<Item = Module>,
+  source: &str,
+) -> Result<Vm<'a>> {
+  let mut vm = Vm::with_collector(arena, collector);
+  for host in hosts {
+    vm.register_module(host)?;
+  }
+  vm.register_module(asm::assemble(source).unwrap())?;
+  Ok(vm)
+}
+
+/// A VM with the module assembled from `source` loaded from its bytecode, going through the
+/// module writer and reader.
+pub fn vm_from_bytes<'a>(arena: &'a LoaderArena, source: &str) -> Result<Vm<'a>> {
+  let mut bytes = Vec::new();
+  asm::assemble(source).unwrap().write(&mut bytes).unwrap();
+  let mut vm = Vm::new(arena);
+  vm.load_bytes(&bytes)?;
+  Ok(vm)
+}
@@ -0,0 +1,207 @@
+use grape::{
+  formatting::display_value,
+  gc::{Collector, Gc},
+  loader::LoaderArena,
+  vm::FromValue,
+  Result, Value,
+};
+
+use common::{vm, COLLECTORS};
+
+mod common;
+
+const SOURCE: &str = r#"
+.module map
+
+.function lookup args=0 locals=1
+  NEW_DICT
+  STORE_0
+  LOAD_0
+  LOADCONST "key"
+  LOADCONST 7
+  SET_DICT
+  CALL gc:collect
+  POP
+  LOAD_0
+  LOADCONST "key"
+  GET_DICT
+  RETURN
+.end
+
+.function missing args=0 locals=0
+try:
+  NEW_DICT
+  LOADCONST "key"
+  GET_DICT
+  RETURN
+catch:
+  GET_FIELD message
+  RETURN
+  .catch try catch catch Error
+.end
+
+.function remove args=0 locals=1
+  NEW_DICT
+  STORE_0
+  LOAD_0
+  LOADCONST "a"
+  ICONST_1
+  SET_DICT
+  LOAD_0
+  LOADCONST "b"
+  LOADCONST 2
+  SET_DICT
+  LOAD_0
+  LOADCONST "a"
+  CALL dict:remove
+  POP
+  LOAD_0
+  LOADCONST "a"
+  CALL dict:has
+  IF_TRUE present
+  LOAD_0
+  CALL dict:len
+  RETURN
+present:
+  ICONST_0
+  RETURN
+.end
+
+.function letters args=0 locals=1
+  NEW_DICT
+  STORE_0
+  LOAD_0
+  LOADCONST "c"
+  LOADCONST 3
+  SET_DICT
+  LOAD_0
+  LOADCONST "a"
+  ICONST_1
+  SET_DICT
+  LOAD_0
+  LOADCONST "b"
+  LOADCONST 2
+  SET_DICT
+  LOAD_0
+  RETURN
+.end
+
+.function entries args=0 locals=2
+  CALL map:letters
+  STORE_0
+  LOADCONST 4
+  NEW_ARRAY
+  STORE_1
+  LOAD_1
+  ICONST_0
+  LOAD_0
+  CALL dict:keys
+  ARRAY_SET
+  LOAD_1
+  ICONST_1
+  LOAD_0
+  CALL dict:values
+  ARRAY_SET
+  LOAD_1
+  LOADCONST 2
+  LOAD_0
+  LOADCONST "b"
+  CALL dict:get
+  ARRAY_SET
+  LOAD_1
+  LOADCONST 3
+  LOAD_0
+  LOADCONST "z"
+  CALL dict:get
+  ARRAY_SET
+  LOAD_1
+  RETURN
+.end
+
+; Add the value of an entry to the total in `sum[0]`.
+.function add args=3 locals=3
+  LOAD_0
+  ICONST_0
+  LOAD_0
+  ICONST_0
+  ARRAY_GET
+  LOAD_2
+  IADD
+  ARRAY_SET
+  RETURN
+.end
+
+.function sum args=0 locals=1
+  ICONST_1
+  NEW_ARRAY
+  STORE_0
+  LOAD_0
+  ICONST_0
+  ICONST_0
+  ARRAY_SET
+  CALL map:letters
+  LOAD_0
+  CLOSURE map:add 1
+  CALL dict:each
+  POP
+  LOAD_0
+  ICONST_0
+  ARRAY_GET
+  RETURN
+.end
+"#;
+
+#[test]
+fn string_keys_compare_by_contents() -> Result<()> {
+  for collector in COLLECTORS {
+    let arena = LoaderArena::default();
+    let value: i32 = vm(&arena, collector, SOURCE)?.call("map", "lookup", &[])?;
+    assert_eq!(value, 7);
+  }
+  Ok(())
+}
+
+#[test]
+fn missing_key_is_an_error() -> Result<()> {
+  let arena = LoaderArena::default();
+  let message: String = vm(&arena, Collector::default(), SOURCE)?.call("map", "missing", &[])?;
+  assert_eq!(message, "Key 'key' not found.");
+  Ok(())
+}
+
+#[test]
+fn removed_key_is_gone() -> Result<()> {
+  let arena = LoaderArena::default();
+  let value: i32 = vm(&arena, Collector::default(), SOURCE)?.call("map", "remove", &[])?;
+  assert_eq!(value, 1);
+  Ok(())
+}
+
+#[test]
+fn keys_and_values_are_in_key_order() -> Result<()> {
+  for collector in COLLECTORS {
+    let arena = LoaderArena::default();
+    let mut vm = vm(&arena, collector, SOURCE)?;
+    let entries: Value = vm.call("map", "entries", &[])?;
+    let gc = vm.runtime().gc();
+    let entry = |index| Gc::array_get(entries.reference(), index);
+    let keys = (0..3)
+      .map(|index| String::from_value(Gc::array_get(entry(0)?.reference(), index)?, gc))
+      .collect::<Result<Vec<_>>>()?;
+    assert_eq!(keys, ["a", "b", "c"], "{collector:?}");
+    assert_eq!(display_value(&entry(1)?, gc).to_string(), "array([1, 2, 3])", "{collector:?}");
+    assert_eq!(entry(2)?, Value::from(2), "{collector:?}");
+    assert_eq!(entry(3)?, Value::NULL, "{collector:?}");
+  }
+  Ok(())
+}
+
+#[test]
+fn each_visits_every_entry() -> Result<()> {
+  for collector in COLLECTORS {
+    let arena = LoaderArena::default();
+    let sum: i32 = vm(&arena, collector, SOURCE)?.call("map", "sum", &[])?;
+    assert_eq!(sum, 6, "{collector:?}");
+  }
+  Ok(())
+}
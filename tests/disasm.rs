@@ -0,0 +1,31 @@
+use grape::{asm, disasm, pool_entry::PoolEntry};
+
+const SOURCE: &str = r#"
+.module floats
+.const nan float NaN
+.const inf float inf
+.const ninf double -inf
+.const tenth double 0.1D
+
+.function main args=0 locals=0
+  LOADCONST $nan
+  LOADCONST $inf
+  LOADCONST $ninf
+  LOADCONST $tenth
+  LOADCONST "text\n"
+  RETURN
+.end
+"#;
+
+#[test]
+fn listing_assembles_back_to_the_same_module() {
+  let module = asm::assemble(SOURCE).unwrap();
+  let listing = disasm::display_module(&module).to_string();
+  let module = asm::assemble(&listing).unwrap_or_else(|e| panic!("{e}\n{listing}"));
+  assert_eq!(disasm::display_module(&module).to_string(), listing);
+
+  assert!(matches!(module.constants[0], PoolEntry::Float(x) if x.is_nan()));
+  assert_eq!(module.constants[1], PoolEntry::Float(f32::INFINITY));
+  assert_eq!(module.constants[2], PoolEntry::Double(f64::NEG_INFINITY));
+  assert_eq!(module.constants[3], PoolEntry::Double(0.1));
+}
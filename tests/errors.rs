@@ -0,0 +1,159 @@
+use grape::{asm, gc::Collector, loader::LoaderArena, Error, Result};
+
+use common::vm;
+
+mod common;
+
+const SOURCE: &str = r#"
+.module errors
+
+.function mismatch args=0 locals=0
+try:
+  ICONST_1
+  LOADCONST "one"
+  IADD
+  RETURN
+catch:
+  GET_FIELD message
+  RETURN
+  .catch try catch catch Error
+.end
+
+.function divide args=1 locals=1
+  ICONST_1
+  LOAD_0
+  IDIV
+  RETURN
+.end
+
+.function null args=0 locals=0
+  CONST_NULL
+  ICONST_0
+  ARRAY_GET
+  RETURN
+.end
+
+.function bounds args=0 locals=0
+  ICONST_1
+  NEW_ARRAY
+  LOADCONST -1
+  ARRAY_GET
+  RETURN
+.end
+
+.function array args=1 locals=1
+  LOAD_0
+  NEW_ARRAY
+  RETURN
+.end
+
+.function negative args=0 locals=1
+  ICONST_0
+  ICONST_1
+  ISUB
+  STORE_0
+  IINC 0 0xFF
+  LOAD_0
+  RETURN
+.end
+
+.function deep args=1 locals=1
+  LOAD_0
+  LOAD_0
+  CALL errors:deep
+  IADD
+  RETURN
+.end
+
+.function forever args=0 locals=0
+  CALL errors:forever
+  RETURN
+.end
+"#;
+
+#[test]
+fn type_mismatch_names_the_instruction() -> Result<()> {
+  let arena = LoaderArena::default();
+  let message: String =
+    vm(&arena, Collector::default(), SOURCE)?.call("errors", "mismatch", &[])?;
+  assert_eq!(message, "Expected integer, found string in IADD at 3.");
+  Ok(())
+}
+
+#[test]
+fn division_by_zero_is_an_error() -> Result<()> {
+  let arena = LoaderArena::default();
+  let mut vm = vm(&arena, Collector::default(), SOURCE)?;
+  assert!(matches!(vm.call::<i32>("errors", "divide", &[0.into()]), Err(Error::DivisionByZero)));
+  assert_eq!(vm.call::<i32>("errors", "divide", &[1.into()])?, 1);
+  Ok(())
+}
+
+#[test]
+fn null_operands_are_null_references() -> Result<()> {
+  let arena = LoaderArena::default();
+  let error =
+    vm(&arena, Collector::default(), SOURCE)?.call::<()>("errors", "null", &[]).unwrap_err();
+  assert!(matches!(
+    error,
+    Error::NullReference { expected: "array", opcode: Some(_), ip: Some(2) }
+  ));
+  Ok(())
+}
+
+#[test]
+fn array_index_out_of_bounds() -> Result<()> {
+  let arena = LoaderArena::default();
+  let error =
+    vm(&arena, Collector::default(), SOURCE)?.call::<()>("errors", "bounds", &[]).unwrap_err();
+  assert!(matches!(error, Error::IndexOutOfBounds { index: -1, len: 1 }), "{error}");
+  Ok(())
+}
+
+#[test]
+fn negative_array_sizes_are_errors() -> Result<()> {
+  for max_heap in [None, Some(1 << 20)] {
+    let arena = LoaderArena::default();
+    let mut vm = vm(&arena, Collector::default(), SOURCE)?;
+    vm.set_max_heap(max_heap);
+    let error = vm.call::<()>("errors", "array", &[(-1).into()]).unwrap_err();
+    assert!(matches!(error, Error::NegativeArraySize(-1)), "{error}");
+    assert_eq!(error.to_string(), "Negative array size -1.");
+    vm.call::<()>("errors", "array", &[0.into()])?;
+  }
+  Ok(())
+}
+
+#[test]
+fn negative_integers_keep_their_type() -> Result<()> {
+  let arena = LoaderArena::default();
+  let value: i32 = vm(&arena, Collector::default(), SOURCE)?.call("errors", "negative", &[])?;
+  assert_eq!(value, 254);
+  Ok(())
+}
+
+#[test]
+fn deep_recursion_overflows_the_stack() -> Result<()> {
+  let arena = LoaderArena::default();
+  let mut vm = vm(&arena, Collector::default(), SOURCE)?;
+  let error = vm.call::<i32>("errors", "deep", &[1.into()]).unwrap_err();
+  assert!(matches!(error, Error::StackOverflow), "{error}");
+  let error = vm.call::<()>("errors", "forever", &[]).unwrap_err();
+  assert!(matches!(error, Error::StackOverflow), "{error}");
+  Ok(())
+}
+
+#[test]
+fn modules_failing_verification_are_not_loaded() -> Result<()> {
+  let arena = LoaderArena::default();
+  let mut vm = vm(&arena, Collector::default(), SOURCE)?;
+  let bad = ".module bad\n.function f args=0 locals=0\n  POP\n  RETURN\n.end";
+  let error = vm.register_module(asm::assemble(bad).unwrap()).unwrap_err();
+  assert!(matches!(error, Error::Verify(_)), "{error}");
+  let error = vm.call::<()>("bad", "f", &[]).unwrap_err();
+  assert!(matches!(&error, Error::ModuleNotFound(name) if name == "bad"), "{error}");
+  // A corrected module loads under the same name.
+  let good = ".module bad\n.function f args=0 locals=0\n  RETURN\n.end";
+  vm.register_module(asm::assemble(good).unwrap())?;
+  vm.call::<()>("bad", "f", &[])
+}
@@ -0,0 +1,162 @@
+use grape::{
+  asm,
+  gc::Collector,
+  loader::LoaderArena,
+  module::{builder::ModuleBuilder, Module},
+  Error, Result, Vm,
+};
+
+use common::vm_with;
+
+mod common;
+
+const SOURCE: &str = r#"
+.module exc
+
+.class Boom
+.field message
+.method new args=1 locals=2
+  LOAD_0
+  LOAD_1
+  SET_FIELD message
+  LOAD_0
+  RETURN
+.end
+.end
+
+.function nested args=0 locals=0
+outer:
+  ICONST_0
+  POP
+inner:
+  LOADCONST "boom"
+  NEW Boom
+  THROW
+end:
+errors:
+  POP
+  LOADCONST "inner"
+  RETURN
+booms:
+  GET_FIELD message
+  RETURN
+  .catch inner end errors Error
+  .catch outer end booms Boom
+.end
+
+.function throw args=1 locals=1
+  LOAD_0
+  NEW Boom
+  THROW
+.end
+
+.function rethrow args=0 locals=0
+try:
+  CALL exc:catch_all
+  RETURN
+catch:
+  GET_FIELD message
+  RETURN
+  .catch try catch catch Boom
+.end
+
+.function catch_all args=0 locals=0
+try:
+  LOADCONST "again"
+  CALL exc:throw
+  RETURN
+catch:
+  THROW
+  .catch try catch catch
+.end
+
+.function through_native args=0 locals=0
+try:
+  CALL host:forward
+  RETURN
+catch:
+  GET_FIELD message
+  RETURN
+  .catch try catch catch Boom
+.end
+
+.function swallowed args=0 locals=0
+try:
+  CALL host:swallow
+  RETURN
+catch:
+  POP
+  LOADCONST "caught"
+  RETURN
+  .catch try catch catch
+.end
+"#;
+
+fn host() -> Module {
+  ModuleBuilder::new()
+    .with_name("host")
+    .with_native("forward", 0, |ctx| {
+      let message = ctx.alloc_string("forwarded".to_string())?;
+      ctx.call("exc", "throw", &[message])
+    })
+    .with_native("swallow", 0, |ctx| {
+      let message = ctx.alloc_string("lost".to_string())?;
+      match ctx.call("exc", "throw", &[message]) {
+        Err(Error::Throw(_)) => ctx.alloc_string("swallowed".to_string()).map(Some),
+        result => result,
+      }
+    })
+    .build()
+}
+
+#[test]
+fn handlers_match_by_class_from_the_innermost() -> Result<()> {
+  let arena = LoaderArena::default();
+  let message: String =
+    vm_with(&arena, Collector::default(), [host()], SOURCE)?.call("exc", "nested", &[])?;
+  assert_eq!(message, "boom");
+  Ok(())
+}
+
+#[test]
+fn rethrown_exceptions_reach_the_caller() -> Result<()> {
+  let arena = LoaderArena::default();
+  let message: String =
+    vm_with(&arena, Collector::default(), [host()], SOURCE)?.call("exc", "rethrow", &[])?;
+  assert_eq!(message, "again");
+  Ok(())
+}
+
+#[test]
+fn exceptions_unwind_through_native_calls() -> Result<()> {
+  let arena = LoaderArena::default();
+  let mut vm = vm_with(&arena, Collector::default(), [host()], SOURCE)?;
+  let message: String = vm.call("exc", "through_native", &[])?;
+  assert_eq!(message, "forwarded");
+  // The nested call stops at the native, handlers below it are not searched.
+  let message: String = vm.call("exc", "swallowed", &[])?;
+  assert_eq!(message, "swallowed");
+  Ok(())
+}
+
+#[test]
+fn uncaught_exceptions_return_to_the_host() -> Result<()> {
+  let arena = LoaderArena::default();
+  let mut vm = vm_with(&arena, Collector::default(), [host()], SOURCE)?;
+  let message = vm.alloc_string("escaped")?;
+  let error = vm.call::<()>("exc", "throw", &[message]).unwrap_err();
+  assert_eq!(error.to_string(), "Uncaught exception Boom: escaped.");
+  let message: String = vm.call("exc", "rethrow", &[])?;
+  assert_eq!(message, "again");
+  Ok(())
+}
+
+#[test]
+fn other_format_versions_are_rejected() {
+  let mut bytes = Vec::new();
+  asm::assemble(SOURCE).unwrap().write(&mut bytes).unwrap();
+  bytes[4..8].copy_from_slice(&1u32.to_be_bytes());
+  let arena = LoaderArena::default();
+  let error = Vm::new(&arena).load_bytes(&bytes).unwrap_err();
+  assert_eq!(error.to_string(), "Unsupported grape file version 1, expected 2");
+}
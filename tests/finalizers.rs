@@ -0,0 +1,197 @@
+use std::cell::RefCell;
+
+use grape::{
+  gc::Gc,
+  loader::LoaderArena,
+  module::{builder::ModuleBuilder, Module},
+  value::Value,
+  Result,
+};
+
+use common::{vm_with, COLLECTORS};
+
+mod common;
+
+const SOURCE: &str = r#"
+.module main
+
+.class Tracked
+.field name
+.method new args=1 locals=2
+  LOAD_0
+  LOAD_1
+  SET_FIELD name
+  LOAD_0
+  RETURN
+.end
+.method drop args=0 locals=1
+  LOAD_0
+  GET_FIELD name
+  CALL host:dropped
+  POP
+  RETURN
+.end
+.end
+
+.class Failing
+.field name
+.method new args=1 locals=2
+  LOAD_0
+  LOAD_1
+  SET_FIELD name
+  LOAD_0
+  RETURN
+.end
+.method drop args=0 locals=1
+  LOAD_0
+  GET_FIELD name
+  CALL host:dropped
+  POP
+  CONST_NULL
+  GET_FIELD name
+  RETURN
+.end
+.end
+
+.function weaks args=0 locals=2
+  LOADCONST 1
+  NEW_ARRAY
+  STORE_0
+  LOADCONST 3
+  NEW_ARRAY
+  STORE_1
+  LOAD_1
+  ICONST_0
+  LOADCONST 1
+  NEW_ARRAY
+  CALL weak:new
+  ARRAY_SET
+  LOAD_1
+  ICONST_1
+  LOAD_0
+  CALL weak:new
+  ARRAY_SET
+  CALL gc:collect
+  POP
+  LOAD_1
+  ICONST_0
+  LOAD_1
+  ICONST_0
+  ARRAY_GET
+  CALL weak:get
+  ARRAY_SET
+  LOAD_1
+  ICONST_1
+  LOAD_1
+  ICONST_1
+  ARRAY_GET
+  CALL weak:get
+  ARRAY_SET
+  LOAD_1
+  LOADCONST 2
+  LOAD_0
+  ARRAY_SET
+  LOAD_1
+  RETURN
+.end
+
+.function finalize args=0 locals=2
+  LOADCONST "first"
+  NEW Tracked
+  CALL weak:new
+  STORE_1
+  LOADCONST "second"
+  NEW Tracked
+  STORE_0
+  CALL gc:collect
+  POP
+  CALL gc:collect
+  POP
+  CONST_NULL
+  STORE_0
+  CALL gc:collect
+  POP
+  CALL gc:collect
+  POP
+  LOAD_1
+  CALL weak:get
+  RETURN
+.end
+
+.function failing args=0 locals=0
+  LOADCONST "failing"
+  NEW Failing
+  POP
+  LOADCONST "after"
+  NEW Tracked
+  POP
+  CALL gc:collect
+  POP
+  CALL gc:collect
+  POP
+  LOADCONST "done"
+  RETURN
+.end
+"#;
+
+thread_local! {
+  /// The names of the finalized objects, in order.
+  static DROPPED: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
+fn host() -> Module {
+  ModuleBuilder::new()
+    .with_name("host")
+    .with_native("dropped", 1, |ctx| {
+      let name = ctx.string(0)?.to_string();
+      DROPPED.with_borrow_mut(|dropped| dropped.push(name));
+      Ok(None)
+    })
+    .build()
+}
+
+/// Take the names of the objects finalized since the last call.
+fn dropped() -> Vec<String> {
+  DROPPED.take()
+}
+
+#[test]
+fn weak_references_are_cleared_after_collection() -> Result<()> {
+  for collector in COLLECTORS {
+    let arena = LoaderArena::default();
+    let mut vm = vm_with(&arena, collector, [host()], SOURCE)?;
+    let results: Value = vm.call("main", "weaks", &[])?;
+    let get = |index| Gc::array_get(results.reference(), index);
+    assert_eq!(get(0)?, Value::NULL, "{collector:?}");
+    assert_eq!(get(1)?, get(2)?, "{collector:?}");
+    assert_eq!(get(1)?.type_name(), "array", "{collector:?}");
+  }
+  Ok(())
+}
+
+#[test]
+fn finalizers_run_exactly_once() -> Result<()> {
+  for collector in COLLECTORS {
+    let arena = LoaderArena::default();
+    let mut vm = vm_with(&arena, collector, [host()], SOURCE)?;
+    // The weak reference to the first object is cleared before its finalizer resurrects it.
+    let first: Value = vm.call("main", "finalize", &[])?;
+    assert_eq!(first, Value::NULL, "{collector:?}");
+    assert_eq!(dropped(), ["first", "second"], "{collector:?}");
+  }
+  Ok(())
+}
+
+#[test]
+fn finalizer_errors_do_not_stop_the_program() -> Result<()> {
+  for collector in COLLECTORS {
+    let arena = LoaderArena::default();
+    let mut vm = vm_with(&arena, collector, [host()], SOURCE)?;
+    let done: String = vm.call("main", "failing", &[])?;
+    assert_eq!(done, "done", "{collector:?}");
+    let mut dropped = dropped();
+    dropped.sort();
+    assert_eq!(dropped, ["after", "failing"], "{collector:?}");
+  }
+  Ok(())
+}
@@ -0,0 +1,444 @@
+use std::{collections::HashSet, time::Duration};
+
+use grape::{
+  gc::{Collector, Gc},
+  loader::LoaderArena,
+  module::{builder::ModuleBuilder, Module},
+  runtime::native::NativeCtx,
+  value::Value,
+  vm::FromValue,
+  Result,
+};
+
+use common::{vm_with, COLLECTORS};
+
+mod common;
+
+const SOURCE: &str = r#"
+.module main
+
+.class Box
+.field value
+.method new args=0 locals=1
+  LOAD_0
+  RETURN
+.end
+.end
+
+.function each args=0 locals=1
+  LOADCONST 2
+  NEW_ARRAY
+  STORE_0
+  LOAD_0
+  ICONST_0
+  LOADCONST "first"
+  ARRAY_SET
+  LOAD_0
+  ICONST_1
+  LOADCONST "second"
+  ARRAY_SET
+  LOAD_0
+  CLOSURE main:collect 0
+  LOADCONST 4
+  CALL host:each
+  RETURN
+.end
+
+.function collect args=1 locals=1
+  LOADCONST "garbage"
+  POP
+  CALL gc:collect
+  POP
+  RETURN
+.end
+
+.function constants args=0 locals=1
+  ICONST_0
+  STORE_0
+loop:
+  LOADCONST "constant"
+  POP
+  IINC 0 1
+  LOAD_0
+  LOADCONST 10000
+  I_IFLT loop
+  CALL gc:collect
+  POP
+  LOADCONST "constant"
+  RETURN
+.end
+
+; Whether interning the strings `a` and `b` gives the same object, across a collection.
+.function intern args=2 locals=3
+  LOAD_0
+  CALL string:intern
+  STORE_2
+  LOADCONST "garbage"
+  POP
+  CALL gc:collect
+  POP
+  LOAD_1
+  CALL string:intern
+  LOAD_2
+  IF_REF_EQ same
+  FALSE
+  RETURN
+same:
+  TRUE
+  RETURN
+.end
+
+.function intern_constant args=1 locals=1
+  LOAD_0
+  CALL string:intern
+  LOADCONST "constant"
+  IF_REF_EQ same
+  FALSE
+  RETURN
+same:
+  TRUE
+  RETURN
+.end
+
+.function stats args=0 locals=1
+  ICONST_1
+  CALL gc:set_threshold
+  POP
+  CALL gc:stats
+  STORE_0
+  LOADCONST 8
+  NEW_ARRAY
+  POP
+  LOADCONST 8
+  NEW_ARRAY
+  POP
+  LOAD_0
+  RETURN
+.end
+
+.function young args=0 locals=0
+  PUSH_BYTE 1
+  PUSH_BYTE 2
+  NEW_BYTES 2
+  RETURN
+.end
+
+.function minor args=0 locals=1
+  ICONST_1
+  CALL gc:set_threshold
+  POP
+  ICONST_0
+  STORE_0
+loop:
+  LOADCONST 4
+  NEW_ARRAY
+  POP
+  IINC 0 1
+  LOAD_0
+  LOADCONST 100
+  I_IFLT loop
+  RETURN
+.end
+
+.function set_field args=0 locals=1
+  NEW Box
+  STORE_0
+  CALL gc:collect
+  POP
+  LOAD_0
+  CALL main:young
+  SET_FIELD value
+  CALL main:minor
+  POP
+  LOAD_0
+  GET_FIELD value
+  RETURN
+.end
+
+.function array_set args=0 locals=1
+  ICONST_1
+  NEW_ARRAY
+  STORE_0
+  CALL gc:collect
+  POP
+  LOAD_0
+  ICONST_0
+  CALL main:young
+  ARRAY_SET
+  CALL main:minor
+  POP
+  LOAD_0
+  ICONST_0
+  ARRAY_GET
+  RETURN
+.end
+
+.function set_dict args=0 locals=1
+  NEW_DICT
+  STORE_0
+  CALL gc:collect
+  POP
+  LOAD_0
+  LOADCONST "key"
+  CALL main:young
+  SET_DICT
+  CALL main:minor
+  POP
+  LOAD_0
+  LOADCONST "key"
+  GET_DICT
+  RETURN
+.end
+
+.function native_store args=0 locals=1
+  ICONST_1
+  NEW_ARRAY
+  STORE_0
+  CALL gc:collect
+  POP
+  LOAD_0
+  ICONST_0
+  CALL main:young
+  CALL host:store
+  POP
+  CALL main:minor
+  POP
+  LOAD_0
+  ICONST_0
+  ARRAY_GET
+  RETURN
+.end
+
+.function large args=0 locals=3
+  ICONST_1
+  CALL gc:set_threshold
+  POP
+  LOADCONST 2000
+  NEW_ARRAY
+  STORE_0
+  NEW_DICT
+  STORE_1
+  ICONST_0
+  STORE_2
+fill:
+  LOAD_0
+  LOAD_2
+  ICONST_1
+  NEW_ARRAY
+  ARRAY_SET
+  LOAD_1
+  LOAD_2
+  ICONST_1
+  NEW_ARRAY
+  SET_DICT
+  IINC 2 1
+  LOAD_2
+  LOADCONST 2000
+  I_IFLT fill
+  ICONST_0
+  STORE_2
+garbage:
+  LOADCONST 4
+  NEW_ARRAY
+  POP
+  IINC 2 1
+  LOAD_2
+  LOADCONST 20000
+  I_IFLT garbage
+  LOADCONST 2
+  NEW_ARRAY
+  STORE_2
+  LOAD_2
+  ICONST_0
+  LOAD_0
+  ARRAY_SET
+  LOAD_2
+  ICONST_1
+  LOAD_1
+  ARRAY_SET
+  LOAD_2
+  RETURN
+.end
+
+.function local_only args=0 locals=1
+  CALL main:young
+  STORE_0
+  CALL host:allocate
+  POP
+  LOAD_0
+  RETURN
+.end
+"#;
+
+fn host() -> Module {
+  ModuleBuilder::new()
+    .with_name("host")
+    .with_native("each", 3, |ctx| {
+      let (array, callback) = (ctx.value(0), ctx.value(1));
+      for index in 0..ctx.arg::<i32>(2)? {
+        let item = Gc::array_get(array.reference(), index % 2)?;
+        ctx.call_value(callback, &[item])?;
+      }
+      let last = String::from_value(Gc::array_get(array.reference(), 1)?, ctx.gc())?;
+      ctx.alloc_string(last).map(Some)
+    })
+    .with_native("store", 3, |ctx| {
+      let (array, value) = (ctx.value(0), ctx.value(2));
+      ctx.gc().write_barrier(array, value);
+      Gc::array_set(array.reference(), ctx.arg(1)?, value)?;
+      Ok(None)
+    })
+    .with_native("allocate", 0, |ctx| {
+      garbage(ctx)?;
+      Ok(None)
+    })
+    .with_native("handle_only", 0, |ctx| {
+      let bytes = ctx.alloc_bytes(vec![1, 2])?;
+      garbage(ctx)?;
+      Ok(Some(bytes))
+    })
+    .build()
+}
+
+/// Allocate enough unreachable arrays from a native to run collections.
+fn garbage(ctx: &mut NativeCtx) -> Result<()> {
+  ctx.gc().set_threshold(1);
+  for _ in 0..100 {
+    ctx.scope(|ctx| ctx.alloc_array(4).map(drop))?;
+  }
+  Ok(())
+}
+
+#[test]
+fn native_arguments_survive_collections() -> Result<()> {
+  for collector in COLLECTORS {
+    let arena = LoaderArena::default();
+    let last: String = vm_with(&arena, collector, [host()], SOURCE)?.call("main", "each", &[])?;
+    assert_eq!(last, "second");
+  }
+  Ok(())
+}
+
+#[test]
+fn constant_strings_are_interned() -> Result<()> {
+  for collector in COLLECTORS {
+    let arena = LoaderArena::default();
+    let mut vm = vm_with(&arena, collector, [host()], SOURCE)?;
+    let constant: String = vm.call("main", "constants", &[])?;
+    assert_eq!(constant, "constant");
+    let freed = vm.runtime().gc().stats().objects_freed;
+    assert_eq!(freed, 0);
+  }
+  Ok(())
+}
+
+#[test]
+fn interned_strings_are_shared_and_kept() -> Result<()> {
+  for collector in COLLECTORS {
+    let arena = LoaderArena::default();
+    let mut vm = vm_with(&arena, collector, [host()], SOURCE)?;
+    let constant = vm.alloc_string("constant")?;
+    assert!(vm.call::<bool>("main", "intern_constant", &[constant])?, "{collector:?}");
+    let args = [vm.alloc_string("fresh")?, vm.alloc_string("fresh")?];
+    assert!(vm.call::<bool>("main", "intern", &args)?, "{collector:?}");
+    let args = [vm.alloc_string("fresh")?, vm.alloc_string("other")?];
+    assert!(!vm.call::<bool>("main", "intern", &args)?, "{collector:?}");
+  }
+  Ok(())
+}
+
+#[test]
+fn stats_survive_promotion() -> Result<()> {
+  for collector in COLLECTORS {
+    let arena = LoaderArena::default();
+    let mut vm = vm_with(&arena, collector, [host()], SOURCE)?;
+    let dict: Value = vm.call("main", "stats", &[])?;
+    let gc = vm.runtime().gc();
+    let stats = gc.stats();
+    assert!(stats.minor_collections + stats.major_collections + stats.slices > 1);
+    let objects = gc.objects().map(|(object, _)| object).collect::<HashSet<_>>();
+    let tracked = |value: &Value| !value.is_reference() || objects.contains(value);
+    let fields = Gc::get_dict_fields(dict.reference());
+    assert_eq!(fields.len(), 10);
+    assert!(fields.iter().all(|(key, value)| tracked(&key.0) && tracked(value)));
+    let live = fields.iter().find(|(key, _)| format!("{key:?}") == "\"live\"").unwrap().1;
+    let live = Gc::get_dict_fields(live.reference());
+    assert!(live.iter().all(|(key, value)| tracked(&key.0) && tracked(value)));
+  }
+  Ok(())
+}
+
+#[test]
+fn young_values_stored_in_old_objects_survive_minor_collections() -> Result<()> {
+  for function in ["set_field", "array_set", "set_dict", "native_store"] {
+    let arena = LoaderArena::default();
+    let mut vm = vm_with(&arena, Collector::Generational, [host()], SOURCE)?;
+    let young: Value = vm.call("main", function, &[])?;
+    let gc = vm.runtime().gc();
+    assert!(gc.stats().minor_collections > 0, "{function}");
+    assert!(gc.objects().any(|(object, _)| object == young), "{function}");
+    assert_eq!(Vec::<u8>::from_value(young, gc)?, [1, 2], "{function}");
+  }
+  Ok(())
+}
+
+#[test]
+fn values_rooted_by_a_handle_or_a_local_survive_native_allocations() -> Result<()> {
+  for collector in COLLECTORS {
+    for (module, function) in [("host", "handle_only"), ("main", "local_only")] {
+      let arena = LoaderArena::default();
+      let mut vm = vm_with(&arena, collector, [host()], SOURCE)?;
+      let bytes: Value = vm.call(module, function, &[])?;
+      let gc = vm.runtime().gc();
+      assert!(gc.stats().objects_freed > 0, "{collector:?} {function}");
+      assert!(gc.objects().any(|(object, _)| object == bytes), "{collector:?} {function}");
+      assert_eq!(Vec::<u8>::from_value(bytes, gc)?, [1, 2], "{collector:?} {function}");
+    }
+  }
+  Ok(())
+}
+
+#[test]
+fn current_stats_count_objects_before_the_first_collection() -> Result<()> {
+  for collector in COLLECTORS {
+    let arena = LoaderArena::default();
+    let mut vm = vm_with(&arena, collector, [host()], SOURCE)?;
+    let bytes: Value = vm.call("main", "young", &[])?;
+    let gc = vm.runtime().gc();
+    assert_eq!(gc.stats().collections() + gc.stats().slices, 0, "{collector:?}");
+    assert!(gc.stats().live.is_empty(), "{collector:?}");
+    let stats = gc.current_stats();
+    assert_eq!(stats.heap_bytes, gc.heap_bytes(), "{collector:?}");
+    assert!(stats.heap_bytes > 0, "{collector:?}");
+    assert_eq!(stats.live.get("bytes"), Some(&1), "{collector:?}");
+    assert_eq!(stats.live.values().sum::<usize>(), gc.objects().count(), "{collector:?}");
+    assert!(gc.objects().any(|(object, _)| object == bytes));
+  }
+  Ok(())
+}
+
+#[test]
+fn incremental_slices_stay_within_their_budget() -> Result<()> {
+  let arena = LoaderArena::default();
+  let mut vm = vm_with(&arena, Collector::Incremental { budget: 16 }, [host()], SOURCE)?;
+  let large: Value = vm.call("main", "large", &[])?;
+  let gc = vm.runtime().gc();
+  let stats = gc.stats();
+  // The array and the dict of 2000 values are scanned over many slices.
+  assert!(stats.major_collections > 0);
+  assert!(stats.slices > 2000 / 16);
+  assert!(stats.max_slice_work > 0 && stats.max_slice_work <= 16, "{}", stats.max_slice_work);
+  assert!(stats.max_pause > Duration::ZERO && stats.max_pause < stats.total_pause);
+  assert!(stats.last_pause <= stats.max_pause);
+
+  let array = Gc::array_get(large.reference(), 0)?;
+  for index in 0..2000 {
+    assert_eq!(Gc::array_get(array.reference(), index)?.type_name(), "array");
+  }
+  let dict = Gc::get_dict_fields(Gc::array_get(large.reference(), 1)?.reference());
+  assert_eq!(dict.len(), 2000);
+  assert!(dict.values().all(|value| value.type_name() == "array"));
+  let objects = gc.objects().map(|(object, _)| object).collect::<HashSet<_>>();
+  assert!(dict.values().all(|value| objects.contains(value)));
+  Ok(())
+}
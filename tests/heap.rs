@@ -0,0 +1,175 @@
+use grape::{
+  gc::{Collector, Gc},
+  loader::LoaderArena,
+  value::Value,
+  vm::FromValue,
+  Error, Result,
+};
+
+use common::{vm, COLLECTORS};
+
+mod common;
+
+const SOURCE: &str = r#"
+.module heap
+
+.function grow_dict args=0 locals=2
+  NEW_DICT
+  STORE_0
+  ICONST_0
+  STORE_1
+loop:
+  LOAD_0
+  LOAD_1
+  ICONST_0
+  SET_DICT
+  IINC 1 1
+  GOTO loop
+.end
+
+.function grow_bytes args=0 locals=1
+  NEW_BYTES 0
+  STORE_0
+loop:
+  LOAD_0
+  PUSH_BYTE 7
+  BYTES_PUSH
+  GOTO loop
+.end
+
+.function append args=0 locals=1
+  NEW_BYTES 0
+  STORE_0
+loop:
+  LOAD_0
+  LOAD_0
+  CALL bytes:append
+  POP
+  LOAD_0
+  PUSH_BYTE 7
+  BYTES_PUSH
+  GOTO loop
+.end
+
+.function array args=1 locals=1
+  LOAD_0
+  NEW_ARRAY
+  RETURN
+.end
+
+.function retain args=1 locals=3
+  LOAD_0
+  NEW_ARRAY
+  STORE_1
+  ICONST_0
+  STORE_2
+fill:
+  LOAD_1
+  LOAD_2
+  PUSH_BYTE 1
+  PUSH_BYTE 2
+  NEW_BYTES 2
+  ARRAY_SET
+  IINC 2 1
+  LOAD_2
+  LOAD_0
+  I_IFLT fill
+  ICONST_0
+  STORE_2
+garbage:
+  PUSH_BYTE 0
+  NEW_BYTES 1
+  POP
+  IINC 2 1
+  LOAD_2
+  LOADCONST 20000
+  I_IFLT garbage
+  LOAD_1
+  RETURN
+.end
+
+.function recover args=0 locals=2
+try:
+  NEW_DICT
+  STORE_0
+  ICONST_0
+  STORE_1
+loop:
+  LOAD_0
+  LOAD_1
+  ICONST_0
+  SET_DICT
+  IINC 1 1
+  GOTO loop
+catch:
+  GET_FIELD message
+  CONST_NULL
+  STORE_0
+  LOADCONST 100
+  NEW_ARRAY
+  POP
+  RETURN
+  .catch try catch catch Error
+.end
+"#;
+
+const MAX_HEAP: usize = 64 << 10;
+
+#[test]
+fn growing_objects_hit_the_limit() -> Result<()> {
+  for collector in COLLECTORS {
+    for (function, kind) in [("grow_dict", "dict"), ("grow_bytes", "bytes"), ("append", "bytes")] {
+      let arena = LoaderArena::default();
+      let mut vm = vm(&arena, collector, SOURCE)?;
+      vm.set_max_heap(Some(MAX_HEAP));
+      let error = vm.call::<()>("heap", function, &[]).unwrap_err();
+      assert!(
+        matches!(error, Error::OutOfMemory { kind: found, site: Some(_), .. } if found == kind),
+        "{function}: {error}"
+      );
+      assert!(vm.runtime().gc().heap_bytes() <= MAX_HEAP, "{function}");
+    }
+  }
+  Ok(())
+}
+
+#[test]
+fn huge_arrays_are_out_of_memory() -> Result<()> {
+  let arena = LoaderArena::default();
+  let mut vm = vm(&arena, Collector::default(), SOURCE)?;
+  vm.set_max_heap(Some(MAX_HEAP));
+  let error = vm.call::<()>("heap", "array", &[i32::MAX.into()]).unwrap_err();
+  assert!(matches!(error, Error::OutOfMemory { kind: "array", .. }), "{error}");
+  vm.call::<()>("heap", "array", &[16.into()])?;
+  Ok(())
+}
+
+#[test]
+fn out_of_memory_is_catchable() -> Result<()> {
+  for collector in COLLECTORS {
+    let arena = LoaderArena::default();
+    let mut vm = vm(&arena, collector, SOURCE)?;
+    vm.set_max_heap(Some(MAX_HEAP));
+    let message: String = vm.call("heap", "recover", &[])?;
+    assert!(message.starts_with("Out of memory allocating 16 bytes for dict at heap:recover%"));
+    assert!(vm.runtime().gc().heap_bytes() <= MAX_HEAP);
+  }
+  Ok(())
+}
+
+#[test]
+fn copying_collections_near_the_limit() -> Result<()> {
+  let arena = LoaderArena::default();
+  let mut vm = vm(&arena, Collector::Copying, SOURCE)?;
+  vm.set_max_heap(Some(MAX_HEAP));
+  let len = 1200;
+  let array: Value = vm.call("heap", "retain", &[len.into()])?;
+  let gc = vm.runtime().gc();
+  assert!(gc.stats().major_collections > 10);
+  assert!(gc.heap_bytes() > MAX_HEAP / 2);
+  for index in 0..len {
+    let bytes = Vec::<u8>::from_value(Gc::array_get(array.reference(), index)?, gc)?;
+    assert_eq!(bytes, [1, 2]);
+  }
+  Ok(())
+}
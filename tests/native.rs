@@ -0,0 +1,171 @@
+use std::{cell::RefCell, rc::Rc};
+
+use grape::{
+  gc::Collector,
+  loader::LoaderArena,
+  module::{builder::ModuleBuilder, Module},
+  value::Value,
+  Error, Result,
+};
+
+use common::{vm_with, COLLECTORS};
+
+mod common;
+
+const SOURCE: &str = r#"
+.module main
+
+.function release args=1 locals=2
+  LOADCONST "closed"
+  LOAD_0
+  CALL host:open
+  STORE_1
+  LOAD_1
+  CALL host:close
+  POP
+  CALL gc:collect
+  POP
+  CONST_NULL
+  STORE_1
+  CALL gc:collect
+  POP
+  CALL gc:collect
+  POP
+  RETURN
+.end
+
+.function close_twice args=1 locals=2
+  LOADCONST "twice"
+  LOAD_0
+  CALL host:open
+  STORE_1
+  LOAD_1
+  CALL host:close
+  POP
+  LOAD_1
+  CALL host:close
+  RETURN
+.end
+
+.function collect args=1 locals=1
+  LOADCONST "lost"
+  LOAD_0
+  CALL host:open
+  POP
+  CALL gc:collect
+  POP
+  CALL gc:collect
+  POP
+  RETURN
+.end
+
+.function keep args=1 locals=1
+  LOADCONST "kept"
+  LOAD_0
+  CALL host:open
+  CALL gc:collect
+  POP
+  RETURN
+.end
+"#;
+
+type Log = Rc<RefCell<Vec<String>>>;
+
+/// Native data recording when it is dropped.
+struct Resource {
+  name: String,
+  log: Log,
+}
+
+impl Drop for Resource {
+  fn drop(&mut self) {
+    self.log.borrow_mut().push(format!("drop {}", self.name));
+  }
+}
+
+/// A module whose `host:open(name, hooked)` allocates a `Resource`, dropped by a hook when
+/// `hooked`.
+fn host(log: &Log) -> Module {
+  let log = log.clone();
+  ModuleBuilder::new()
+    .with_name("host")
+    .with_native("open", 2, move |ctx| {
+      let name = ctx.string(0)?.to_string();
+      if !ctx.arg::<bool>(1)? {
+        return ctx.alloc_native("Resource", Resource { name, log: log.clone() }).map(Some);
+      }
+      let hook = log.clone();
+      let on_drop = move |name| hook.borrow_mut().push(format!("hook {name}"));
+      let native = ctx.gc().alloc_native_with_drop("Resource", name, on_drop)?;
+      Ok(Some(ctx.handle(native)))
+    })
+    .with_native("close", 1, |ctx| {
+      ctx.release(0)?;
+      Ok(None)
+    })
+    .build()
+}
+
+/// Call `main:function(hooked)`, returning the drops it logged, none may follow with the VM.
+fn run(collector: Collector, function: &str, hooked: bool) -> (Result<()>, Vec<String>) {
+  let log = Log::default();
+  let arena = LoaderArena::default();
+  let mut vm = vm_with(&arena, collector, [host(&log)], SOURCE).unwrap();
+  let result = vm.call("main", function, &[hooked.into()]);
+  let dropped = log.borrow().clone();
+  drop(vm);
+  assert_eq!(*log.borrow(), dropped, "{collector:?} dropped again with the VM");
+  (result, dropped)
+}
+
+#[test]
+fn explicit_release_drops_once() -> Result<()> {
+  for collector in COLLECTORS {
+    for (hooked, dropped) in [(false, "drop closed"), (true, "hook closed")] {
+      let (result, log) = run(collector, "release", hooked);
+      result?;
+      assert_eq!(log, [dropped], "{collector:?}");
+    }
+  }
+  Ok(())
+}
+
+#[test]
+fn releasing_twice_is_an_error() {
+  for collector in COLLECTORS {
+    for (hooked, dropped) in [(false, "drop twice"), (true, "hook twice")] {
+      let (result, log) = run(collector, "close_twice", hooked);
+      assert!(matches!(result, Err(Error::Released("Resource"))), "{collector:?}");
+      assert_eq!(log, [dropped], "{collector:?}");
+    }
+  }
+}
+
+#[test]
+fn collection_drops_once() -> Result<()> {
+  for collector in COLLECTORS {
+    for (hooked, dropped) in [(false, "drop lost"), (true, "hook lost")] {
+      let (result, log) = run(collector, "collect", hooked);
+      result?;
+      assert_eq!(log, [dropped], "{collector:?}");
+    }
+  }
+  Ok(())
+}
+
+#[test]
+fn live_natives_drop_with_the_vm() -> Result<()> {
+  for collector in COLLECTORS {
+    for (hooked, dropped) in [(false, "drop kept"), (true, "hook kept")] {
+      let log = Log::default();
+      let arena = LoaderArena::default();
+      let mut vm = vm_with(&arena, collector, [host(&log)], SOURCE)?;
+      let native: Value = vm.call("main", "keep", &[hooked.into()])?;
+      assert_eq!(native.type_name(), "native");
+      assert!(log.borrow().is_empty(), "{collector:?}");
+      drop(vm);
+      assert_eq!(*log.borrow(), [dropped], "{collector:?}");
+    }
+  }
+  Ok(())
+}
@@ -0,0 +1,307 @@
+use grape::{asm, disasm, gc::Collector, loader::LoaderArena, value::Value, Error, Result};
+
+use common::{vm, COLLECTORS};
+
+mod common;
+
+const SOURCE: &str = r#"
+.module numbers
+
+.function long args=0 locals=1
+  LOADCONST 0x7FFFFFFFFFFFFFFFL
+  STORE_0
+  CALL gc:collect
+  POP
+  LOAD_0
+  LOADCONST 2L
+  LSUB
+  CALL gc:collect
+  POP
+  RETURN
+.end
+
+.function bor args=0 locals=0
+  PUSH_BYTE 12
+  PUSH_BYTE 10
+  BOR
+  RETURN
+.end
+
+.function bxor args=0 locals=0
+  PUSH_BYTE 12
+  PUSH_BYTE 10
+  BXOR
+  RETURN
+.end
+
+.function wrap args=0 locals=0
+  LOADCONST 0x7FFFFFFFFFFFFFFFL
+  ICONST_1
+  I2L
+  LADD
+  RETURN
+.end
+
+.function double args=0 locals=1
+  LOADCONST 0.1D
+  LOADCONST 0.2D
+  DADD
+  STORE_0
+  CALL gc:collect
+  POP
+  LOAD_0
+  RETURN
+.end
+
+.function compare args=0 locals=0
+  LOADCONST 0x100000000L
+  LOADCONST 0x100000000L
+  L_IFEQ equal
+  ICONST_0
+  RETURN
+equal:
+  LOADCONST 0.1D
+  LOADCONST 0.2D
+  D_IFLT less
+  ICONST_0
+  RETURN
+less:
+  ICONST_1
+  RETURN
+.end
+
+.function float_equal args=2 locals=2
+  LOAD_0
+  LOAD_1
+  F_IFEQ equal
+  FALSE
+  RETURN
+equal:
+  TRUE
+  RETURN
+.end
+
+.function float_less args=2 locals=2
+  LOAD_0
+  LOAD_1
+  F_IFLT less
+  FALSE
+  RETURN
+less:
+  TRUE
+  RETURN
+.end
+
+.function float_not_equal args=2 locals=2
+  LOAD_0
+  LOAD_1
+  F_IFNEQ different
+  FALSE
+  RETURN
+different:
+  TRUE
+  RETURN
+.end
+
+.function byte_less args=2 locals=2
+  LOAD_0
+  LOAD_1
+  B_IFLT less
+  FALSE
+  RETURN
+less:
+  TRUE
+  RETURN
+.end
+
+.function distinct args=2 locals=2
+  LOAD_0
+  LOAD_1
+  IF_REF_NE distinct
+  FALSE
+  RETURN
+distinct:
+  TRUE
+  RETURN
+.end
+
+.function is_zero args=1 locals=1
+  LOAD_0
+  IS_ZERO
+  RETURN
+.end
+
+.function cmp args=2 locals=2
+  LOAD_0
+  LOAD_1
+  CMP
+  RETURN
+.end
+
+.function same args=0 locals=0
+  LOADCONST "a"
+  LOADCONST "a"
+  IF_REF_EQ same
+  FALSE
+  RETURN
+same:
+  TRUE
+  RETURN
+.end
+"#;
+
+#[test]
+fn boxed_longs_survive_collections() -> Result<()> {
+  for collector in COLLECTORS {
+    let arena = LoaderArena::default();
+    let value: i64 = vm(&arena, collector, SOURCE)?.call("numbers", "long", &[])?;
+    assert_eq!(value, i64::MAX - 2);
+  }
+  Ok(())
+}
+
+#[test]
+fn long_arithmetic_wraps() -> Result<()> {
+  let arena = LoaderArena::default();
+  let value: i64 = vm(&arena, Collector::default(), SOURCE)?.call("numbers", "wrap", &[])?;
+  assert_eq!(value, i64::MIN);
+  Ok(())
+}
+
+#[test]
+fn byte_bitwise_operators() -> Result<()> {
+  let arena = LoaderArena::default();
+  let mut vm = vm(&arena, Collector::default(), SOURCE)?;
+  assert_eq!(vm.call::<u8>("numbers", "bor", &[])?, 0b1110);
+  assert_eq!(vm.call::<u8>("numbers", "bxor", &[])?, 0b0110);
+  Ok(())
+}
+
+#[test]
+fn doubles_keep_their_precision() -> Result<()> {
+  for collector in COLLECTORS {
+    let arena = LoaderArena::default();
+    let value: f64 = vm(&arena, collector, SOURCE)?.call("numbers", "double", &[])?;
+    assert_eq!(value, 0.1 + 0.2);
+  }
+  Ok(())
+}
+
+#[test]
+fn boxed_values_compare_by_value() -> Result<()> {
+  let arena = LoaderArena::default();
+  let value: i32 = vm(&arena, Collector::default(), SOURCE)?.call("numbers", "compare", &[])?;
+  assert_eq!(value, 1);
+  Ok(())
+}
+
+#[test]
+fn nan_is_never_equal() -> Result<()> {
+  let arena = LoaderArena::default();
+  let mut vm = vm(&arena, Collector::default(), SOURCE)?;
+  let equal: bool = vm.call("numbers", "float_equal", &[f32::NAN.into(), f32::NAN.into()])?;
+  assert!(!equal);
+  let equal: bool = vm.call("numbers", "float_equal", &[0.5f32.into(), 0.5f32.into()])?;
+  assert!(equal);
+  Ok(())
+}
+
+#[test]
+fn cmp_orders_numbers_of_one_type() -> Result<()> {
+  let arena = LoaderArena::default();
+  let mut vm = vm(&arena, Collector::default(), SOURCE)?;
+  let long = vm.alloc_long(i64::MAX)?;
+  let cases: [([Value; 2], i32); 4] = [
+    ([2.into(), 1.into()], 1),
+    ([1u8.into(), 1u8.into()], 0),
+    ([long, vm.alloc_long(0)?], 1),
+    ([0.5f32.into(), f32::NAN.into()], -1),
+  ];
+  for (args, expected) in cases {
+    assert_eq!(vm.call::<i32>("numbers", "cmp", &args)?, expected);
+  }
+  let error = vm.call::<i32>("numbers", "cmp", &[1.into(), 1u8.into()]).unwrap_err();
+  assert!(matches!(error, Error::TypeMismatch { expected: "integer", found: "byte", .. }));
+  Ok(())
+}
+
+#[test]
+fn is_zero_accepts_longs_and_doubles() -> Result<()> {
+  let arena = LoaderArena::default();
+  let mut vm = vm(&arena, Collector::default(), SOURCE)?;
+  for (long, expected) in [(0, true), (i64::MIN, false)] {
+    let value = vm.alloc_long(long)?;
+    assert_eq!(vm.call::<bool>("numbers", "is_zero", &[value])?, expected);
+  }
+  for (double, expected) in [(-0., true), (0.1, false)] {
+    let value = vm.alloc_double(double)?;
+    assert_eq!(vm.call::<bool>("numbers", "is_zero", &[value])?, expected);
+  }
+  Ok(())
+}
+
+#[test]
+fn interned_strings_are_the_same_object() -> Result<()> {
+  let arena = LoaderArena::default();
+  let same: bool = vm(&arena, Collector::default(), SOURCE)?.call("numbers", "same", &[])?;
+  assert!(same);
+  Ok(())
+}
+
+#[test]
+fn float_branches_are_not_taken_on_nan() -> Result<()> {
+  let arena = LoaderArena::default();
+  let mut vm = vm(&arena, Collector::default(), SOURCE)?;
+  let cases: [(f32, f32, bool, bool); 4] = [
+    (0.5, 1., true, true),
+    (1., 0.5, false, true),
+    (f32::NAN, 1., false, true),
+    (1., f32::NAN, false, true),
+  ];
+  for (a, b, less, not_equal) in cases {
+    assert_eq!(vm.call::<bool>("numbers", "float_less", &[a.into(), b.into()])?, less, "{a} < {b}");
+    let args = [a.into(), b.into()];
+    assert_eq!(vm.call::<bool>("numbers", "float_not_equal", &args)?, not_equal, "{a} != {b}");
+  }
+  Ok(())
+}
+
+#[test]
+fn bytes_compare_unsigned() -> Result<()> {
+  let arena = LoaderArena::default();
+  let mut vm = vm(&arena, Collector::default(), SOURCE)?;
+  for (a, b, less) in [(200u8, 255u8, true), (255, 1, false), (3, 3, false)] {
+    assert_eq!(vm.call::<bool>("numbers", "byte_less", &[a.into(), b.into()])?, less, "{a} < {b}");
+  }
+  let error = vm.call::<bool>("numbers", "byte_less", &[1.into(), 2u8.into()]).unwrap_err();
+  assert!(matches!(error, Error::TypeMismatch { expected: "byte", .. }), "{error}");
+  Ok(())
+}
+
+#[test]
+fn references_compare_by_identity() -> Result<()> {
+  let arena = LoaderArena::default();
+  let mut vm = vm(&arena, Collector::default(), SOURCE)?;
+  let (a, b) = (vm.alloc_string("a")?, vm.alloc_string("a")?);
+  assert!(vm.call::<bool>("numbers", "distinct", &[a, b])?);
+  assert!(!vm.call::<bool>("numbers", "distinct", &[a, a])?);
+  Ok(())
+}
+
+#[test]
+fn comparisons_are_verified_and_disassembled() -> Result<()> {
+  let listing = disasm::display_module(&asm::assemble(SOURCE).unwrap()).to_string();
+  let module = asm::assemble(&listing).unwrap();
+  assert_eq!(disasm::display_module(&module).to_string(), listing);
+  for opcode in ["F_IFLT @", "F_IFNEQ @", "B_IFLT @", "IF_REF_EQ @", "IF_REF_NE @", "CMP"] {
+    assert!(listing.contains(opcode), "{opcode}: {listing}");
+  }
+
+  let arena = LoaderArena::default();
+  let mut vm = vm(&arena, Collector::default(), SOURCE)?;
+  let bad =
+    ".module bad\n.function f args=0 locals=0\n  ICONST_1\n  B_IFEQ end\nend:\n  RETURN\n.end";
+  let error = vm.register_module(asm::assemble(bad).unwrap()).unwrap_err();
+  assert!(matches!(error, Error::Verify(_)), "{error}");
+  Ok(())
+}
@@ -0,0 +1,155 @@
+use std::{
+  collections::{BTreeSet, HashSet},
+  fs::File,
+  io::BufReader,
+  path::PathBuf,
+};
+
+use grape::{
+  gc::{snapshot::Snapshot, Collector},
+  loader::LoaderArena,
+  Result,
+};
+
+use common::{vm, COLLECTORS};
+
+mod common;
+
+const SOURCE: &str = r#"
+.module main
+
+.function leak args=2 locals=4
+  LOADCONST 2
+  NEW_ARRAY
+  STORE_2
+  LOAD_2
+  ICONST_0
+  LOADCONST "kept"
+  ARRAY_SET
+  CALL gc:collect
+  POP
+  LOAD_0
+  CALL gc:snapshot
+  POP
+  ICONST_0
+  STORE_3
+garbage:
+  LOADCONST 4
+  NEW_ARRAY
+  POP
+  IINC 3 1
+  LOAD_3
+  LOADCONST 2000
+  I_IFLT garbage
+  CALL gc:collect
+  POP
+  LOAD_2
+  ICONST_1
+  LOADCONST 3
+  NEW_ARRAY
+  ARRAY_SET
+  ICONST_0
+  STORE_3
+leak:
+  LOAD_2
+  ICONST_1
+  ARRAY_GET
+  LOAD_3
+  NEW_DICT
+  ARRAY_SET
+  IINC 3 1
+  LOAD_3
+  LOADCONST 3
+  I_IFLT leak
+  CALL gc:collect
+  POP
+  LOAD_1
+  CALL gc:snapshot
+  POP
+  RETURN
+.end
+"#;
+
+fn path(name: &str) -> PathBuf {
+  std::env::temp_dir().join(format!("grape-{}-{name}.snap", std::process::id()))
+}
+
+fn read(path: &PathBuf) -> Snapshot {
+  let snapshot = Snapshot::read(&mut BufReader::new(File::open(path).unwrap())).unwrap();
+  std::fs::remove_file(path).unwrap();
+  snapshot
+}
+
+/// The objects reachable from the roots of `snapshot`.
+fn reachable(snapshot: &Snapshot) -> BTreeSet<u64> {
+  let mut reachable = BTreeSet::new();
+  let mut pending = snapshot.roots.clone();
+  while let Some(id) = pending.pop() {
+    if reachable.insert(id) {
+      pending.extend(snapshot.objects.get(&id).map(|object| &object.refs[..]).unwrap_or_default());
+    }
+  }
+  reachable
+}
+
+/// Snapshots of `main:leak` before and after it leaks an array of three dicts.
+fn leak(collector: Collector) -> Result<(Snapshot, Snapshot)> {
+  let name = format!("{collector:?}").to_lowercase();
+  let (before, after) = (path(&format!("{name}-before")), path(&format!("{name}-after")));
+  let arena = LoaderArena::default();
+  let mut vm = vm(&arena, collector, SOURCE)?;
+  let args =
+    [vm.alloc_string(before.to_str().unwrap())?, vm.alloc_string(after.to_str().unwrap())?];
+  vm.call::<()>("main", "leak", &args)?;
+  Ok((read(&before), read(&after)))
+}
+
+#[test]
+fn objects_keep_their_identity() -> Result<()> {
+  for collector in COLLECTORS {
+    let (before, after) = leak(collector)?;
+    let (before, after) = (reachable(&before), reachable(&after));
+    assert!(!before.is_empty(), "{collector:?}");
+    assert!(before.is_subset(&after), "{collector:?}");
+    assert_eq!(after.difference(&before).count(), 4, "{collector:?}");
+  }
+  Ok(())
+}
+
+#[test]
+fn diff_reports_growth_and_retention_paths() -> Result<()> {
+  for collector in COLLECTORS {
+    let (before, after) = leak(collector)?;
+    let diff = before.diff(&after).to_string();
+    let dict = diff.lines().find(|line| line.starts_with("dict ")).unwrap();
+    assert_eq!(dict.split_whitespace().nth(2), Some("+3"), "{collector:?}: {diff}");
+    let paths = diff.lines().filter(|line| line.starts_with("root -> "));
+    let dicts = paths
+      .filter(|path| path.rsplit(" -> ").next().unwrap().starts_with("dict@"))
+      .map(|path| path.split(" -> ").collect::<Vec<_>>())
+      .collect::<Vec<_>>();
+    assert_eq!(dicts.len(), 3, "{collector:?}: {diff}");
+    // Every dict is retained by the same arrays.
+    let retainers = dicts.iter().map(|path| path[..path.len() - 1].to_vec());
+    assert_eq!(retainers.collect::<HashSet<_>>().len(), 1, "{collector:?}: {diff}");
+  }
+  Ok(())
+}
+
+#[test]
+fn snapshots_round_trip() -> Result<()> {
+  let (snapshot, _) = leak(Collector::default())?;
+  let mut text = Vec::new();
+  snapshot.write(&mut text).unwrap();
+  let read = Snapshot::read(&mut &text[..]).unwrap();
+  assert_eq!(read.roots, snapshot.roots);
+  assert_eq!(read.objects.len(), snapshot.objects.len());
+  for (id, object) in &snapshot.objects {
+    let copy = &read.objects[id];
+    assert_eq!((&copy.kind, copy.size, &copy.refs), (&object.kind, object.size, &object.refs));
+  }
+
+  let error = Snapshot::read(&mut &b"grape-heap-snapshot 1\nroot 55d0c0a3b2f0\n"[..]).unwrap_err();
+  assert_eq!(error.to_string(), "not a heap snapshot");
+  Ok(())
+}
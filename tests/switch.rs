@@ -0,0 +1,161 @@
+use grape::{asm, disasm, loader::LoaderArena, Error, Result};
+
+use common::vm_from_bytes;
+
+mod common;
+
+const SOURCE: &str = r#"
+.module switch
+
+.function table args=1 locals=1
+  LOAD_0
+  TABLESWITCH other 1 3 one two three
+one:
+  LOADCONST "one"
+  RETURN
+two:
+  LOADCONST "two"
+  RETURN
+three:
+  LOADCONST "three"
+  RETURN
+other:
+  LOADCONST "other"
+  RETURN
+.end
+
+.function lookup args=1 locals=1
+  LOAD_0
+  LOOKUPSWITCH other 3 -5 minus 10 ten 0x7FFFFFFF max
+minus:
+  LOADCONST "minus"
+  RETURN
+ten:
+  LOADCONST "ten"
+  RETURN
+max:
+  LOADCONST "max"
+  RETURN
+other:
+  LOADCONST "other"
+  RETURN
+.end
+
+.function lowest args=1 locals=1
+  LOAD_0
+  TABLESWITCH other -2147483648 -2147483647 min next
+min:
+  LOADCONST "min"
+  RETURN
+next:
+  LOADCONST "next"
+  RETURN
+other:
+  LOADCONST "other"
+  RETURN
+.end
+
+.function no_keys args=1 locals=1
+  LOAD_0
+  LOOKUPSWITCH other 0
+  LOADCONST "unreachable"
+  RETURN
+other:
+  LOADCONST "other"
+  RETURN
+.end
+"#;
+
+#[test]
+fn table_switch_branches_on_range() -> Result<()> {
+  let arena = LoaderArena::default();
+  let mut vm = vm_from_bytes(&arena, SOURCE)?;
+  for (key, expected) in [(0, "other"), (1, "one"), (2, "two"), (3, "three"), (4, "other")] {
+    assert_eq!(vm.call::<String>("switch", "table", &[key.into()])?, expected);
+  }
+  Ok(())
+}
+
+#[test]
+fn lookup_switch_matches_keys() -> Result<()> {
+  let arena = LoaderArena::default();
+  let mut vm = vm_from_bytes(&arena, SOURCE)?;
+  let cases = [(-5, "minus"), (10, "ten"), (i32::MAX, "max"), (0, "other"), (i32::MIN, "other")];
+  for (key, expected) in cases {
+    assert_eq!(vm.call::<String>("switch", "lookup", &[key.into()])?, expected);
+  }
+  Ok(())
+}
+
+#[test]
+fn switches_cover_the_integer_range() -> Result<()> {
+  let arena = LoaderArena::default();
+  let mut vm = vm_from_bytes(&arena, SOURCE)?;
+  let cases = [(i32::MIN, "min"), (i32::MIN + 1, "next"), (0, "other"), (i32::MAX, "other")];
+  for (key, expected) in cases {
+    assert_eq!(vm.call::<String>("switch", "lowest", &[key.into()])?, expected);
+  }
+  for key in [i32::MIN, 0, i32::MAX] {
+    assert_eq!(vm.call::<String>("switch", "no_keys", &[key.into()])?, "other");
+  }
+  Ok(())
+}
+
+#[test]
+fn switch_keys_must_be_integers() -> Result<()> {
+  let arena = LoaderArena::default();
+  let mut vm = vm_from_bytes(&arena, SOURCE)?;
+  for function in ["table", "lookup"] {
+    let key = vm.alloc_string("one")?;
+    let error = vm.call::<String>("switch", function, &[key]).unwrap_err();
+    assert!(
+      matches!(error, Error::TypeMismatch { expected: "integer", found: "string", .. }),
+      "{function}: {error}"
+    );
+  }
+  Ok(())
+}
+
+#[test]
+fn switches_disassemble_to_source() {
+  let module = asm::assemble(SOURCE).unwrap();
+  let listing = disasm::display_module(&module).to_string();
+  let module = asm::assemble(&listing).unwrap();
+  assert_eq!(disasm::display_module(&module).to_string(), listing);
+  assert!(listing.contains("LOOKUPSWITCH @33 3 -5 @24 10 @27 2147483647 @30"), "{listing}");
+}
+
+#[test]
+fn unsorted_lookup_keys_are_rejected() {
+  let source = r#"
+.module switch
+
+.function lookup args=1 locals=1
+  LOAD_0
+  LOOKUPSWITCH other 2 10 ten -5 ten
+ten:
+other:
+  LOAD_0
+  RETURN
+.end
+"#;
+  let arena = LoaderArena::default();
+  assert!(matches!(vm_from_bytes(&arena, source), Err(Error::Verify(_))));
+}
+
+#[test]
+fn empty_tables_are_rejected() {
+  let source = r#"
+.module switch
+
+.function table args=1 locals=1
+  LOAD_0
+  TABLESWITCH other 2 1
+other:
+  LOAD_0
+  RETURN
+.end
+"#;
+  let arena = LoaderArena::default();
+  assert!(matches!(vm_from_bytes(&arena, source), Err(Error::Verify(_))));
+}
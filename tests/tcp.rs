@@ -0,0 +1,291 @@
+use std::{
+  io::{Read, Write},
+  net::{Shutdown, TcpListener, TcpStream},
+  thread,
+  time::{Duration, Instant},
+};
+
+use grape::{gc::Collector, loader::LoaderArena, Result, Value};
+
+use common::vm;
+
+mod common;
+
+const SOURCE: &str = r#"
+.module net
+
+.function listen args=0 locals=0
+  LOADCONST "127.0.0.1:0"
+  CALL tcp:new_listener
+  RETURN
+.end
+
+; Answer the first message of a connection with itself.
+.function echo args=1 locals=2
+  LOAD_0
+  CALL tcp:accept
+  STORE_1
+  LOAD_1
+  LOAD_1
+  CALL tcp:recv_string
+  CALL tcp:send_string
+  POP
+  LOAD_1
+  CALL tcp:destroy
+  POP
+  LOAD_0
+  CALL tcp:destroy
+  POP
+  RETURN
+.end
+
+.function receive args=1 locals=2
+  LOAD_0
+  CALL tcp:accept
+  STORE_1
+  LOAD_1
+  CALL tcp:recv_string
+  LOAD_1
+  CALL tcp:destroy
+  POP
+  RETURN
+.end
+
+.function request args=2 locals=3
+  LOAD_0
+  CALL tcp:connect
+  STORE_2
+  LOAD_2
+  LOADCONST 1000
+  CALL tcp:set_timeout
+  POP
+  LOAD_2
+  LOAD_1
+  CALL tcp:send_string
+  POP
+  LOAD_2
+  CALL tcp:recv_string
+  LOAD_2
+  CALL tcp:shutdown
+  POP
+  LOAD_2
+  CALL tcp:destroy
+  POP
+  RETURN
+.end
+
+.function peer args=1 locals=2
+  LOAD_0
+  CALL tcp:connect
+  STORE_1
+  LOAD_1
+  CALL tcp:peer_addr
+  LOAD_1
+  CALL tcp:destroy
+  POP
+  RETURN
+.end
+
+.function request_bytes args=1 locals=2
+  LOAD_0
+  CALL tcp:connect
+  STORE_1
+  LOAD_1
+  PUSH_BYTE 1
+  PUSH_BYTE 2
+  NEW_BYTES 2
+  DUP
+  PUSH_BYTE 255
+  BYTES_PUSH
+  CALL tcp:send_bytes
+  POP
+  LOAD_1
+  LOADCONST 16
+  CALL tcp:recv_bytes
+  RETURN
+.end
+
+.function try_connect args=1 locals=1
+try:
+  LOAD_0
+  CALL tcp:connect
+  POP
+  LOADCONST "connected"
+  RETURN
+catch:
+  GET_FIELD message
+  RETURN
+  .catch try catch catch Error
+.end
+
+; Receive from a server that never answers, giving up after 50 milliseconds.
+.function wait args=1 locals=1
+  LOAD_0
+  CALL tcp:connect
+  STORE_0
+  LOAD_0
+  LOADCONST 50
+  CALL tcp:set_timeout
+  POP
+try:
+  LOAD_0
+  CALL tcp:recv_string
+  RETURN
+catch:
+  GET_FIELD message
+  RETURN
+  .catch try catch catch Error
+.end
+
+.function use_after_destroy args=1 locals=2
+  LOAD_0
+  CALL tcp:connect
+  STORE_1
+  LOAD_1
+  CALL tcp:destroy
+  POP
+  LOAD_1
+  CALL tcp:peer_addr
+  RETURN
+.end
+"#;
+
+#[test]
+fn listener_echoes_loopback_client() -> Result<()> {
+  let arena = LoaderArena::default();
+  let mut vm = vm(&arena, Collector::default(), SOURCE)?;
+
+  let listener: Value = vm.call("net", "listen", &[])?;
+  let addr: String = vm.call("tcp", "local_addr", &[listener])?;
+  let client = thread::spawn(move || {
+    let mut stream = TcpStream::connect(addr).unwrap();
+    stream.write_all(b"ping").unwrap();
+    stream.shutdown(Shutdown::Write).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    response
+  });
+
+  vm.call::<()>("net", "echo", &[listener])?;
+  assert_eq!(client.join().unwrap(), "ping");
+  Ok(())
+}
+
+#[test]
+fn stream_connects_to_loopback_server() -> Result<()> {
+  let arena = LoaderArena::default();
+  let mut vm = vm(&arena, Collector::default(), SOURCE)?;
+
+  let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+  let addr = listener.local_addr().unwrap().to_string();
+  let server = thread::spawn(move || {
+    let (mut stream, _) = listener.accept().unwrap();
+    let mut request = [0; 4];
+    stream.read_exact(&mut request).unwrap();
+    stream.write_all(b"pong").unwrap();
+    request
+  });
+
+  let request = vm.alloc_string("ping")?;
+  let addr_value = vm.alloc_string(&addr)?;
+  let response: String = vm.call("net", "request", &[addr_value, request])?;
+  assert_eq!(response, "pong");
+  assert_eq!(&server.join().unwrap(), b"ping");
+  Ok(())
+}
+
+#[test]
+fn stream_reports_peer_addr() -> Result<()> {
+  let arena = LoaderArena::default();
+  let mut vm = vm(&arena, Collector::default(), SOURCE)?;
+
+  let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+  let addr = listener.local_addr().unwrap().to_string();
+  let addr_value = vm.alloc_string(&addr)?;
+  let peer: String = vm.call("net", "peer", &[addr_value])?;
+  assert_eq!(peer, addr);
+  Ok(())
+}
+
+#[test]
+fn stream_sends_and_receives_bytes() -> Result<()> {
+  let arena = LoaderArena::default();
+  let mut vm = vm(&arena, Collector::default(), SOURCE)?;
+
+  let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+  let addr = listener.local_addr().unwrap().to_string();
+  let server = thread::spawn(move || {
+    let (mut stream, _) = listener.accept().unwrap();
+    let mut request = [0; 3];
+    stream.read_exact(&mut request).unwrap();
+    stream.write_all(&[request[2], request[1], request[0]]).unwrap();
+  });
+
+  let addr = vm.alloc_string(&addr)?;
+  let response: Vec<u8> = vm.call("net", "request_bytes", &[addr])?;
+  server.join().unwrap();
+  assert_eq!(response, [255, 2, 1]);
+  Ok(())
+}
+
+#[test]
+fn connection_errors_are_catchable() -> Result<()> {
+  let arena = LoaderArena::default();
+  let mut vm = vm(&arena, Collector::default(), SOURCE)?;
+
+  let addr = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().to_string();
+  let addr = vm.alloc_string(&addr)?;
+  let message: String = vm.call("net", "try_connect", &[addr])?;
+  assert_ne!(message, "connected");
+  Ok(())
+}
+
+#[test]
+fn reads_time_out() -> Result<()> {
+  let arena = LoaderArena::default();
+  let mut vm = vm(&arena, Collector::default(), SOURCE)?;
+
+  // The connection is left in the backlog, nothing is ever sent on it.
+  let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+  let addr = vm.alloc_string(&listener.local_addr().unwrap().to_string())?;
+  let start = Instant::now();
+  let message: String = vm.call("net", "wait", &[addr])?;
+  assert!(!message.is_empty());
+  assert!(start.elapsed() < Duration::from_secs(5), "{:?}", start.elapsed());
+  Ok(())
+}
+
+#[test]
+fn destroyed_stream_is_an_error() -> Result<()> {
+  let arena = LoaderArena::default();
+  let mut vm = vm(&arena, Collector::default(), SOURCE)?;
+
+  let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+  let addr = listener.local_addr().unwrap().to_string();
+  let addr = vm.alloc_string(&addr)?;
+  let error = vm.call::<String>("net", "use_after_destroy", &[addr]).unwrap_err();
+  assert_eq!(error.to_string(), "Native object TcpStream was released.");
+  Ok(())
+}
+
+#[test]
+fn characters_split_between_reads_are_received_whole() -> Result<()> {
+  let arena = LoaderArena::default();
+  let mut vm = vm(&arena, Collector::default(), SOURCE)?;
+
+  let listener: Value = vm.call("net", "listen", &[])?;
+  let addr: String = vm.call("tcp", "local_addr", &[listener])?;
+  let client = thread::spawn(move || {
+    let mut stream = TcpStream::connect(addr).unwrap();
+    let (start, end) = "grâpe".as_bytes().split_at(3);
+    stream.write_all(start).unwrap();
+    stream.flush().unwrap();
+    thread::sleep(Duration::from_millis(100));
+    stream.write_all(end).unwrap();
+  });
+
+  let received: String = vm.call("net", "receive", &[listener])?;
+  client.join().unwrap();
+  assert!("grâpe".starts_with(&received) && received.len() >= 4, "{received:?}");
+  Ok(())
+}